@@ -0,0 +1,113 @@
+//! Bearer-token guard for operator endpoints under `/api/admin`.
+//!
+//! Admin routes are disabled unless `ADMIN_TOKEN` is set; callers must then
+//! send `Authorization: Bearer <token>`.
+
+use axum::{
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use tracing::{event, Level};
+
+/// Path prefix shared by all admin routes.
+pub const ADMIN_PREFIX: &str = "/api/admin";
+
+/// Reasons an admin request is refused.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AdminError {
+    /// No admin token is configured, so admin routes are off.
+    Disabled,
+    /// The bearer token is missing or does not match.
+    InvalidToken,
+}
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Disabled => (
+                StatusCode::FORBIDDEN,
+                "Admin API is disabled: ADMIN_TOKEN is not set",
+            )
+                .into_response(),
+            Self::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid admin token").into_response(),
+        }
+    }
+}
+
+/// Checks the request's bearer token against the configured admin token.
+///
+/// # Errors
+///
+/// Returns [`AdminError::Disabled`] (403) if no admin token is configured, or
+/// [`AdminError::InvalidToken`] (401) if the bearer token is missing or wrong.
+pub fn authorize(expected: Option<&str>, headers: &HeaderMap) -> Result<(), AdminError> {
+    let Some(expected) = expected else {
+        return Err(AdminError::Disabled);
+    };
+
+    let supplied = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    if constant_time_eq(supplied.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        event!(
+            name: "admin.auth.rejected",
+            Level::WARN,
+            "Rejected admin request with missing or invalid token"
+        );
+        Err(AdminError::InvalidToken)
+    }
+}
+
+/// Compares two byte strings without short-circuiting on the first mismatch.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_authorize() {
+        assert!(authorize(Some("secret"), &bearer("secret")).is_ok());
+        assert_eq!(
+            authorize(Some("secret"), &bearer("guess")),
+            Err(AdminError::InvalidToken)
+        );
+        assert_eq!(
+            authorize(Some("secret"), &HeaderMap::new()),
+            Err(AdminError::InvalidToken)
+        );
+        assert_eq!(
+            authorize(None, &bearer("secret")),
+            Err(AdminError::Disabled)
+        );
+    }
+
+    #[test]
+    fn test_admin_error_status() {
+        assert_eq!(
+            AdminError::Disabled.into_response().status(),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            AdminError::InvalidToken.into_response().status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! The calculator page, its stylesheet and its script.
+//!
+//! Files are read from `static_dir` (`static/` by default) so the frontend
+//! can be edited without a rebuild. Whatever the directory lacks, or all of
+//! it when the directory is missing, is answered from the copies embedded at
+//! compile time, so the binary alone still serves a working page.
+//! `index.html` is a template whose slots `page` fills in.
+//!
+//! Stylesheets and scripts are sent with `Cache-Control: public,
+//! max-age=300`. Their names carry no version, so the lifetime stays short;
+//! files from disk are revalidated through `Last-Modified`.
+
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE},
+        HeaderValue, Method, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use tower_http::services::ServeDir;
+use tracing::{event, Level};
+
+use crate::http::AppState;
+
+/// `Cache-Control` of `.css` and `.js` responses.
+pub const CACHE_CONTROL_VALUE: &str = "public, max-age=300";
+
+/// Page template built into the binary.
+const INDEX_HTML: &str = include_str!("../static/index.html");
+
+/// Files built into the binary: path, content type, contents.
+const EMBEDDED: &[(&str, &str, &str)] = &[
+    (
+        "/styles.css",
+        "text/css; charset=utf-8",
+        include_str!("../static/styles.css"),
+    ),
+    (
+        "/app.js",
+        "text/javascript; charset=utf-8",
+        include_str!("../static/app.js"),
+    ),
+];
+
+/// Where the frontend files come from.
+#[derive(Debug, Default)]
+pub struct Assets {
+    /// The static directory, if it exists; embedded copies only otherwise.
+    dir: Option<PathBuf>,
+}
+
+impl Assets {
+    /// Serves `dir`, or only the embedded copies if it is not a directory.
+    pub fn new(dir: &Path) -> Self {
+        if dir.is_dir() {
+            return Self {
+                dir: Some(dir.to_path_buf()),
+            };
+        }
+        event!(
+            name: "app.assets.embedded",
+            Level::INFO,
+            dir = %dir.display(),
+            "Static directory {{dir}} not found; serving the embedded page"
+        );
+        Self::default()
+    }
+
+    /// The page template, from disk when present.
+    pub async fn index(&self) -> Cow<'static, str> {
+        let Some(dir) = &self.dir else {
+            return Cow::Borrowed(INDEX_HTML);
+        };
+        match tokio::fs::read_to_string(dir.join("index.html")).await {
+            Ok(page) => Cow::Owned(page),
+            Err(_) => Cow::Borrowed(INDEX_HTML),
+        }
+    }
+
+    /// Answers `request` from the directory, else from the embedded copies.
+    pub async fn serve(&self, request: Request) -> Response {
+        if request.method() != Method::GET && request.method() != Method::HEAD {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        let path = request.uri().path().to_owned();
+        let from_disk = match &self.dir {
+            Some(dir) => ServeDir::new(dir)
+                .try_call(request)
+                .await
+                .ok()
+                .filter(|response| response.status() != StatusCode::NOT_FOUND)
+                .map(|response| response.map(Body::new)),
+            None => None,
+        };
+        let mut response = from_disk.unwrap_or_else(|| embedded(&path));
+        let cacheable = path.ends_with(".css") || path.ends_with(".js");
+        if cacheable && !response.status().is_client_error() {
+            response
+                .headers_mut()
+                .insert(CACHE_CONTROL, HeaderValue::from_static(CACHE_CONTROL_VALUE));
+        }
+        response
+    }
+}
+
+/// The embedded copy of `path`, or `404`.
+fn embedded(path: &str) -> Response {
+    match EMBEDDED.iter().find(|(name, _, _)| *name == path) {
+        Some((_, content_type, contents)) => {
+            ([(CONTENT_TYPE, *content_type)], *contents).into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Serves every path no route claims.
+pub async fn static_handler(State(state): State<AppState>, request: Request) -> Response {
+    state.assets.serve(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn get(assets: Assets, uri: &str) -> (StatusCode, Response, String) {
+        let state = AppState {
+            assets: Arc::new(assets),
+            ..AppState::default()
+        };
+        let response = crate::build_router(state)
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let (parts, body) = response.into_parts();
+        let bytes = body.collect().await.unwrap().to_bytes();
+        let response = Response::from_parts(parts, Body::empty());
+        (status, response, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    fn header<'a>(response: &'a Response, name: &str) -> &'a str {
+        response.headers()[name].to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_missing_directory_serves_embedded_copies() {
+        let missing = std::env::temp_dir().join("bmi-static-does-not-exist");
+        let assets = || Assets::new(&missing);
+
+        let (status, response, body) = get(assets(), "/styles.css").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(header(&response, "content-type").starts_with("text/css"));
+        assert_eq!(header(&response, "cache-control"), CACHE_CONTROL_VALUE);
+        assert_eq!(body, include_str!("../static/styles.css"));
+
+        let (status, response, _) = get(assets(), "/app.js").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(header(&response, "content-type").starts_with("text/javascript"));
+        assert_eq!(header(&response, "cache-control"), CACHE_CONTROL_VALUE);
+
+        let (status, _, page) = get(assets(), "/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains(r#"<link rel="stylesheet" href="/styles.css">"#));
+        assert!(page.contains(r#"<script src="/app.js"></script>"#));
+
+        let (status, response, _) = get(assets(), "/missing.css").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(response.headers().get("cache-control").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_directory_files_take_precedence() {
+        let dir = std::env::temp_dir().join(format!("bmi-static-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("styles.css"), "body { color: red; }").unwrap();
+        std::fs::write(
+            dir.join("index.html"),
+            "<p>custom</p><!-- category-legend -->",
+        )
+        .unwrap();
+        std::fs::write(dir.join("logo.svg"), "<svg/>").unwrap();
+
+        let (status, response, body) = get(Assets::new(&dir), "/styles.css").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(header(&response, "content-type").starts_with("text/css"));
+        assert_eq!(header(&response, "cache-control"), CACHE_CONTROL_VALUE);
+        assert!(response.headers().contains_key("last-modified"));
+        assert_eq!(body, "body { color: red; }");
+
+        // Not on disk: the embedded copy
+        let (status, _, body) = get(Assets::new(&dir), "/app.js").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, include_str!("../static/app.js"));
+
+        let (status, response, _) = get(Assets::new(&dir), "/logo.svg").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(header(&response, "content-type"), "image/svg+xml");
+        assert!(response.headers().get("cache-control").is_none());
+
+        let (_, _, page) = get(Assets::new(&dir), "/").await;
+        assert!(page.starts_with("<p>custom</p><div data-classification="));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
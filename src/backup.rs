@@ -0,0 +1,353 @@
+//! Instance backups as gzip-compressed NDJSON.
+//!
+//! `bmi_calculator export --output backup.jsonl.gz` writes one JSON object
+//! per line: a `{"kind": "backup", "version": 1}` header, then every stored
+//! calculation as `{"kind": "history", "version": 2, "id": ..., ...}`,
+//! oldest first. The same data always produces the same bytes.
+//!
+//! `bmi_calculator import --input backup.jsonl.gz` checks the header and
+//! every record's `kind` and `version`, then upserts the records by id in a
+//! single transaction, so importing twice changes nothing and a rejected dump
+//! stores nothing. Both directions stream one record at a time.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    config::AppConfig,
+    storage::{Record, SqliteStorage},
+};
+
+/// Version written in the header; newer dumps are refused.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Version of `history` records; version 2 added `session`.
+const HISTORY_VERSION: u32 = 2;
+
+/// One line of a dump.
+#[derive(Serialize, Deserialize)]
+struct Line<T> {
+    kind: String,
+    version: u32,
+    #[serde(flatten)]
+    body: T,
+}
+
+/// Body of a `history` line.
+#[derive(Serialize, Deserialize)]
+struct HistoryEntry {
+    #[serde(flatten)]
+    record: Record,
+    /// Absent in version 1 records and for rows made without a session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session: Option<String>,
+}
+
+/// Records written or read, per kind.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Counts {
+    /// Calculation history rows.
+    pub history: u64,
+    /// Rows an import added or changed.
+    pub changed: u64,
+}
+
+/// Writes every stored record to `writer`.
+///
+/// # Errors
+///
+/// Returns error if the database cannot be read or `writer` fails.
+pub(crate) fn export(storage: &SqliteStorage, writer: impl Write) -> Result<Counts> {
+    let mut encoder = GzEncoder::new(writer, Compression::default());
+    let mut counts = Counts::default();
+    write_line(
+        &mut encoder,
+        &Line {
+            kind: "backup".to_string(),
+            version: FORMAT_VERSION,
+            body: Map::new(),
+        },
+    )?;
+    storage.each_record(|session, record| {
+        counts.history += 1;
+        write_line(
+            &mut encoder,
+            &Line {
+                kind: "history".to_string(),
+                version: HISTORY_VERSION,
+                body: HistoryEntry { record, session },
+            },
+        )
+    })?;
+    encoder
+        .finish()
+        .and_then(|mut writer| writer.flush())
+        .context("cannot write backup")?;
+    Ok(counts)
+}
+
+fn write_line(writer: &mut impl Write, line: &impl Serialize) -> Result<()> {
+    serde_json::to_writer(&mut *writer, line).context("cannot write backup")?;
+    writer.write_all(b"\n").context("cannot write backup")
+}
+
+/// Validates the dump in `reader` and upserts its records.
+///
+/// # Errors
+///
+/// Returns error, with nothing stored, if the dump is not gzip NDJSON, lacks
+/// the header, has a newer or unknown version or kind, or cannot be written.
+pub(crate) fn import(storage: &SqliteStorage, reader: impl Read) -> Result<Counts> {
+    let mut lines = BufReader::new(GzDecoder::new(reader)).lines().enumerate();
+    let header = match lines.next() {
+        Some((_, line)) => parse(&line.context("cannot read backup")?, 1)?,
+        None => bail!("backup is empty"),
+    };
+    if header.kind != "backup" {
+        bail!(
+            "line 1: expected the backup header, got kind {:?}",
+            header.kind
+        );
+    }
+    if header.version > FORMAT_VERSION {
+        bail!(
+            "backup format version {} is newer than this build ({FORMAT_VERSION})",
+            header.version
+        );
+    }
+
+    let mut counts = Counts::default();
+    let records = lines.map(|(index, line)| {
+        let number = index + 1;
+        let line = parse(&line.context("cannot read backup")?, number)?;
+        match line.kind.as_str() {
+            "history" if line.version <= HISTORY_VERSION => {
+                counts.history += 1;
+                serde_json::from_value::<HistoryEntry>(Value::Object(line.body))
+                    .map(|entry| (entry.session, entry.record))
+                    .with_context(|| format!("line {number}: invalid history record"))
+            }
+            "history" => bail!(
+                "line {number}: history version {} is newer than this build ({HISTORY_VERSION})",
+                line.version
+            ),
+            kind => bail!("line {number}: unknown record kind {kind:?}"),
+        }
+    });
+    let changed = storage.upsert(records)?;
+    counts.changed = changed;
+    Ok(counts)
+}
+
+fn parse(line: &str, number: usize) -> Result<Line<Map<String, Value>>> {
+    serde_json::from_str(line).with_context(|| format!("line {number}: not a backup record"))
+}
+
+/// Runs `export --output <path>`, returning a summary to print.
+///
+/// # Errors
+///
+/// Returns error for an unset `database_url` or a failed export.
+pub fn export_to(config: &AppConfig, path: &Path) -> Result<String> {
+    let counts = export_file(&open(config, "export")?, path)?;
+    Ok(format!(
+        "Exported {} history records to {}",
+        counts.history,
+        path.display()
+    ))
+}
+
+/// Runs `import --input <path>`, returning a summary to print.
+///
+/// # Errors
+///
+/// Returns error for an unset `database_url` or a failed import.
+pub fn import_from(config: &AppConfig, path: &Path) -> Result<String> {
+    let storage = open(config, "import")?;
+    let file = File::open(path).with_context(|| format!("cannot open {}", path.display()))?;
+    let counts = import(&storage, file)?;
+    Ok(format!(
+        "Imported {} history records from {} ({} added or changed)",
+        counts.history,
+        path.display(),
+        counts.changed
+    ))
+}
+
+fn open(config: &AppConfig, command: &str) -> Result<SqliteStorage> {
+    let Some(url) = config.database_url.as_deref() else {
+        bail!("{command} needs database_url (BMI_DATABASE_URL)");
+    };
+    SqliteStorage::open(url)
+}
+
+/// Exports next to `path` and renames into place, so a failed export never
+/// leaves a truncated backup behind.
+fn export_file(storage: &SqliteStorage, path: &Path) -> Result<Counts> {
+    let partial = path.with_extension("partial");
+    let file =
+        File::create(&partial).with_context(|| format!("cannot create {}", partial.display()))?;
+    let counts = export(storage, BufWriter::new(file)).inspect_err(|_| {
+        let _ = std::fs::remove_file(&partial);
+    })?;
+    std::fs::rename(&partial, path).with_context(|| format!("cannot write {}", path.display()))?;
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::{Calculation, Storage},
+        units::UnitSystem,
+    };
+    use chrono::{TimeZone, Utc};
+
+    fn seeded(rows: u32) -> SqliteStorage {
+        let storage = SqliteStorage::open("sqlite::memory:").unwrap();
+        for row in 0..rows {
+            let session = (row % 3 != 0).then(|| format!("session-{}", row % 2));
+            storage
+                .insert(
+                    session.as_deref(),
+                    &Calculation {
+                        recorded_at: Utc
+                            .timestamp_opt(1_760_000_000 + i64::from(row), 123)
+                            .unwrap(),
+                        units: if row % 2 == 0 {
+                            UnitSystem::Metric
+                        } else {
+                            UnitSystem::Imperial
+                        },
+                        weight_kg: 60.0 + f64::from(row) / 7.0,
+                        height_m: 1.75,
+                        bmi: 19.6 + f64::from(row) / 21.0,
+                        category: "Normal weight".to_string(),
+                    },
+                )
+                .unwrap();
+        }
+        storage
+    }
+
+    fn rows(storage: &SqliteStorage) -> Vec<(Option<String>, Record)> {
+        let mut rows = Vec::new();
+        storage
+            .each_record(|session, record| {
+                rows.push((session, record));
+                Ok(())
+            })
+            .unwrap();
+        rows
+    }
+
+    fn gzip(text: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_export_wipe_import_round_trip() {
+        let source = seeded(300);
+        let mut dump = Vec::new();
+        assert_eq!(export(&source, &mut dump).unwrap().history, 300);
+
+        let restored = SqliteStorage::open("sqlite::memory:").unwrap();
+        let counts = import(&restored, dump.as_slice()).unwrap();
+        assert_eq!(
+            counts,
+            Counts {
+                history: 300,
+                changed: 300
+            }
+        );
+        assert_eq!(rows(&restored), rows(&source));
+        assert_eq!(
+            restored.history("session-1", 500, 0).unwrap(),
+            source.history("session-1", 500, 0).unwrap()
+        );
+
+        let again = import(&restored, dump.as_slice()).unwrap();
+        assert_eq!(
+            again,
+            Counts {
+                history: 300,
+                changed: 0
+            }
+        );
+        let mut second = Vec::new();
+        export(&restored, &mut second).unwrap();
+        assert_eq!(second, dump);
+    }
+
+    #[test]
+    fn test_import_checks_versions_and_kinds() {
+        let record = r#"{"kind":"history","version":1,"id":1,"recorded_at":"2026-10-14T09:30:00Z","units":"metric","weight_kg":70.0,"height_m":1.75,"bmi":22.86,"category":"Normal weight"}"#;
+        for (text, expected) in [
+            (String::new(), "empty"),
+            (format!("{record}\n"), "backup header"),
+            (
+                format!("{{\"kind\":\"backup\",\"version\":2}}\n{record}\n"),
+                "newer than this build",
+            ),
+            (
+                format!("{{\"kind\":\"backup\",\"version\":1}}\n{record}\n{{\"kind\":\"goal\",\"version\":1}}\n"),
+                "line 3: unknown record kind",
+            ),
+            (
+                format!("{{\"kind\":\"backup\",\"version\":1}}\n{}\n", record.replace("\"version\":1", "\"version\":9")),
+                "line 2: history version 9",
+            ),
+        ] {
+            let storage = SqliteStorage::open("sqlite::memory:").unwrap();
+            let Err(error) = import(&storage, gzip(&text).as_slice()) else {
+                panic!("{expected}: imported");
+            };
+            let error = format!("{error:#}");
+            assert!(error.contains(expected), "{expected}: {error}");
+            assert!(rows(&storage).is_empty(), "{expected}");
+        }
+        assert!(import(&seeded(0), b"not gzip".as_slice()).is_err());
+
+        let version_1 = seeded(0);
+        let dump = format!("{{\"kind\":\"backup\",\"version\":1}}\n{record}\n");
+        import(&version_1, gzip(&dump).as_slice()).unwrap();
+        assert_eq!(rows(&version_1)[0].0, None);
+    }
+
+    #[test]
+    fn test_commands_use_the_configured_database() {
+        let dir = std::env::temp_dir().join(format!("bmi-backup-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = |name: &str| AppConfig {
+            database_url: Some(dir.join(name).display().to_string()),
+            ..AppConfig::default()
+        };
+        let dump = dir.join("backup.jsonl.gz");
+        let source =
+            SqliteStorage::open(config("source.db").database_url.as_deref().unwrap()).unwrap();
+        source.upsert(rows(&seeded(3)).into_iter().map(Ok)).unwrap();
+        drop(source);
+
+        let summary = export_to(&config("source.db"), &dump).unwrap();
+        assert!(
+            summary.starts_with("Exported 3 history records"),
+            "{summary}"
+        );
+        let summary = import_from(&config("restored.db"), &dump).unwrap();
+        assert!(summary.ends_with("(3 added or changed)"), "{summary}");
+
+        let error = export_to(&AppConfig::default(), &dump).unwrap_err();
+        assert!(error.to_string().contains("needs database_url"), "{error}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
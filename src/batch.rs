@@ -0,0 +1,290 @@
+//! Batch calculations for whole cohorts in one request.
+//!
+//! `POST /api/calculate/batch` takes a JSON array of calculation requests and
+//! answers one result per entry, in order. Entries are validated on their
+//! own, so a bad row yields an error item instead of failing the batch.
+//! Results and errors are localized like single calculations. Form tokens
+//! are ignored; batches are not double-submit protected. With the `proto`
+//! feature the batch may also be sent or answered as protobuf.
+
+use axum::{
+    extract::{FromRequest, Request, State},
+    response::Response,
+    Json,
+};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{event, Level};
+use utoipa::ToSchema;
+
+use crate::{
+    error::{BmiError, ErrorDetail, ErrorResponse},
+    i18n::{self, Locale},
+    process_bmi_request_with, validation, AppState, BmiRequest, BmiResponse,
+};
+
+/// Most entries accepted in one batch without a memory budget.
+pub const MAX_ENTRIES: usize = 10_000;
+
+/// Default request body limit for the batch route, room for [`MAX_ENTRIES`]
+/// entries (`limits.batch_body_bytes`).
+pub const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Outcome of one batch entry, tagged with its position in the request.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum BatchItem {
+    /// The entry was calculated.
+    Ok {
+        /// Position of the entry in the request array.
+        index: usize,
+        /// Same body as `POST /api/calculate`.
+        result: BmiResponse,
+    },
+    /// The entry was rejected.
+    Err {
+        /// Position of the entry in the request array.
+        index: usize,
+        /// Same `{"code", "message"}` object as a single-request error.
+        error: ErrorDetail,
+    },
+}
+
+/// Response body for `POST /api/calculate/batch`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResponse {
+    /// Number of entries in the request.
+    pub processed: usize,
+    /// Number of entries that were rejected.
+    pub failed: usize,
+    /// One item per entry, in request order.
+    pub results: Vec<BatchItem>,
+}
+
+/// Computes one item per decoded entry within `bounds`, in `locale`; entries
+/// that did not decode are item errors.
+pub fn calculate(
+    entries: Vec<Result<BmiRequest, BmiError>>,
+    bounds: &validation::Bounds,
+    locale: Locale,
+) -> BatchResponse {
+    let results: Vec<BatchItem> = entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            match entry.and_then(|request| process_bmi_request_with(&request, bounds)) {
+                Ok(mut result) => {
+                    i18n::localize(&mut result, locale);
+                    BatchItem::Ok { index, result }
+                }
+                Err(error) => BatchItem::Err {
+                    index,
+                    error: i18n::localize_error(error, locale).body(),
+                },
+            }
+        })
+        .collect();
+
+    BatchResponse {
+        processed: results.len(),
+        failed: results
+            .iter()
+            .filter(|item| matches!(item, BatchItem::Err { .. }))
+            .count(),
+        results,
+    }
+}
+
+/// Reads a JSON array body, decoding each entry on its own.
+///
+/// # Errors
+///
+/// Returns a [`BmiError`] if the body is not a JSON array or is too large.
+pub(crate) async fn read_json(
+    request: Request,
+) -> Result<Vec<Result<BmiRequest, BmiError>>, BmiError> {
+    let Json(entries) = Json::<Vec<Value>>::from_request(request, &()).await?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            serde_json::from_value(entry).map_err(|err| BmiError::MalformedPayload(err.to_string()))
+        })
+        .collect())
+}
+
+/// Calculates decoded `entries` within the memory budget's entry cap.
+///
+/// # Errors
+///
+/// Returns [`BmiError::PayloadTooLarge`] if there are too many entries.
+pub(crate) fn run(
+    state: &AppState,
+    entries: Vec<Result<BmiRequest, BmiError>>,
+    locale: Locale,
+) -> Result<BatchResponse, BmiError> {
+    let max_entries = state.memory.sizing().batch_entries;
+    if entries.len() > max_entries {
+        return Err(BmiError::PayloadTooLarge(format!(
+            "Batch has {} entries; at most {max_entries} are accepted",
+            entries.len()
+        )));
+    }
+
+    let response = calculate(entries, &state.bounds, locale);
+    event!(
+        name: "bmi.batch.processed",
+        Level::INFO,
+        processed = response.processed,
+        failed = response.failed,
+        "Batch processed: {{processed}} entries, {{failed}} failed"
+    );
+    Ok(response)
+}
+
+/// Handles `POST /api/calculate/batch`.
+///
+/// # Errors
+///
+/// Returns a [`BmiError`] for the whole request if:
+/// - The body is not a JSON array (HTTP 422)
+/// - The body exceeds [`MAX_BODY_BYTES`] or holds more entries than the
+///   memory budget allows, [`MAX_ENTRIES`] by default (HTTP 413)
+///
+/// Invalid entries do not fail the request; they are reported per item.
+#[utoipa::path(
+    post,
+    path = "/api/calculate/batch",
+    tag = "calculation",
+    summary = "Calculate BMI for many entries",
+    description = "Entries are validated on their own; a bad entry yields an error item instead of failing the batch.",
+    request_body = Vec<BmiRequest>,
+    responses(
+        (status = 200, description = "One result or error per entry", body = BatchResponse),
+        (status = 413, description = "Body or entry count too large", body = ErrorResponse),
+        (status = 422, description = "Body is not a JSON array", body = ErrorResponse),
+        (status = 503, description = "Shed under memory pressure", body = ErrorResponse),
+    )
+)]
+pub async fn batch_handler(State(state): State<AppState>, request: Request) -> Response {
+    let locale = Locale::requested(request.headers(), request.uri().query());
+    #[cfg(feature = "proto")]
+    if crate::proto::is_protobuf(request.headers())
+        || crate::proto::accepts_protobuf(request.headers())
+    {
+        return crate::proto::batch_handler(&state, locale, request).await;
+    }
+
+    let outcome = read_json(request)
+        .await
+        .and_then(|entries| run(&state, entries, locale));
+    match outcome {
+        Ok(response) => i18n::respond(locale, Json(response)),
+        Err(error) => i18n::respond(locale, i18n::localize_error(error, locale)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{
+            header::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE, CONTENT_TYPE},
+            StatusCode,
+        },
+    };
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn post(body: String) -> (StatusCode, Value) {
+        let request = Request::post("/api/calculate/batch")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = crate::build_router(crate::AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_mixed_batch_reports_each_entry() {
+        let (status, body) = post(
+            r#"[
+                {"weight_kg": 70, "height_m": 1.75},
+                {"weight_kg": -1, "height_m": 1.75},
+                {"weight_kg": "heavy", "height_m": 1.75},
+                {"units": "imperial", "weight_lb": 154, "height_in": 69}
+            ]"#
+            .to_string(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["processed"], 4);
+        assert_eq!(body["failed"], 2);
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results[0]["index"], 0);
+        assert_eq!(results[0]["result"]["category"], "Normal weight");
+        assert_eq!(results[1]["index"], 1);
+        assert_eq!(results[1]["error"]["code"], "validation_failed");
+        assert_eq!(results[1]["error"]["fields"][0]["field"], "weight_kg");
+        assert_eq!(results[2]["error"]["code"], "malformed_payload");
+        assert_eq!(results[3]["result"]["units"], "imperial");
+    }
+
+    #[tokio::test]
+    async fn test_results_are_localized() {
+        let request = Request::post("/api/calculate/batch")
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT_LANGUAGE, "fr")
+            .body(Body::from(r#"[{"weight_kg": 70, "height_m": 1.75}]"#))
+            .unwrap();
+        let response = crate::build_router(crate::AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()[CONTENT_LANGUAGE], "fr");
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["results"][0]["result"]["category"], "Poids normal");
+        assert_eq!(
+            body["results"][0]["result"]["category_code"],
+            "normal_weight"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_too_many_entries_are_rejected() {
+        let entry = r#"{"weight_kg":70,"height_m":1.75}"#;
+        let body = format!("[{}]", vec![entry; MAX_ENTRIES + 1].join(","));
+
+        let (status, body) = post(body).await;
+
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["error"]["code"], "payload_too_large");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        let body = format!("[\"{}\"]", "x".repeat(MAX_BODY_BYTES));
+
+        let (status, body) = post(body).await;
+
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["error"]["code"], "payload_too_large");
+    }
+
+    #[tokio::test]
+    async fn test_non_array_body_is_malformed() {
+        let (status, body) = post(r#"{"weight_kg": 70}"#.to_string()).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "malformed_payload");
+    }
+}
@@ -0,0 +1,937 @@
+//! BMI calculation, independent of any transport.
+//!
+//! [`calculate_bmi`] and [`categorize_bmi`] are the pure formula and WHO
+//! lookup. [`process_bmi_request`] runs the full pipeline every route shares:
+//! unit normalization, calculation, unit checks and categorization, with the
+//! same logging and metrics whichever transport the request came from.
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{error, telemetry, timings, units, validation};
+
+/// BMI calculation request payload.
+///
+/// Contains weight in kilograms and height in meters (SI units), or pounds
+/// and feet/inches when `units` is `imperial`. Weight may also be given in
+/// stones and pounds with either system.
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::BmiRequest;
+///
+/// let request = BmiRequest {
+///     weight_kg: Some(70.0),
+///     height_m: Some(1.75),
+///     ..BmiRequest::default()
+/// };
+/// ```
+#[derive(Debug, Default, Deserialize, Serialize, utoipa::ToSchema, utoipa::IntoParams)]
+#[schema(
+    description = "One weight and one height, in metric or imperial units.",
+    example = json!({ "weight_kg": 70.0, "height_m": 1.75 })
+)]
+#[into_params(parameter_in = Query)]
+pub struct BmiRequest {
+    /// Unit system of the measurement fields (defaults to metric).
+    #[serde(default)]
+    pub units: units::UnitSystem,
+    /// Weight in kilograms (must be positive).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(exclusive_minimum = 0.0, example = 70.0)]
+    pub weight_kg: Option<f64>,
+    /// Height in meters (must be positive).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(exclusive_minimum = 0.0, example = 1.75)]
+    pub height_m: Option<f64>,
+    /// Height in centimeters, instead of `height_m`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(exclusive_minimum = 0.0, example = 175.0)]
+    pub height_cm: Option<f64>,
+    /// Weight in pounds (imperial).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(exclusive_minimum = 0.0, example = 154.0)]
+    pub weight_lb: Option<f64>,
+    /// Height in feet (imperial); `height_in` then adds the remaining inches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(exclusive_minimum = 0.0, example = 5.0)]
+    pub height_ft: Option<f64>,
+    /// Height in inches (imperial), total or on top of `height_ft`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(minimum = 0.0, example = 9.0)]
+    pub height_in: Option<f64>,
+    /// Weight in stones (UK), with either unit system.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(exclusive_minimum = 0.0, example = 11.0)]
+    pub weight_st: Option<f64>,
+    /// Pounds on top of `weight_st`, in `[0, 14)`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(minimum = 0.0, exclusive_maximum = 14.0, example = 0.0)]
+    pub weight_lb_remainder: Option<f64>,
+    /// Optional single-use token issued with the page (see `form_token`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[param(ignore)]
+    pub form_token: Option<String>,
+    /// Reject values that look like another unit instead of warning (see `units`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict_units: bool,
+    /// Decimal places of `bmi_rounded`, 0 to 4 (defaults to 1).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(minimum = 0, maximum = 4, example = 1)]
+    pub precision: Option<u8>,
+    /// Add `bmi_prime` and `ponderal_index` to the response.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub extended: bool,
+    /// Threshold table for the categories (defaults to `standard`).
+    #[serde(default)]
+    pub classification: Classification,
+}
+
+/// Threshold table used to categorize a BMI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Classification {
+    /// WHO international cutoffs: overweight from 25, obese from 30.
+    #[default]
+    Standard,
+    /// WHO cutoffs for Asian populations: overweight from 23, obese from 27.5.
+    AsianPacific,
+}
+
+impl Classification {
+    /// Name as used on the wire.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::AsianPacific => "asian_pacific",
+        }
+    }
+
+    /// Coarse categories of this scheme, laid out like [`CATEGORIES`].
+    pub fn categories(self) -> &'static [(&'static str, f64)] {
+        match self {
+            Self::Standard => &CATEGORIES,
+            Self::AsianPacific => &ASIAN_PACIFIC_CATEGORIES,
+        }
+    }
+
+    /// Detailed categories of this scheme, laid out like [`DETAILED_CATEGORIES`].
+    pub fn detailed_categories(self) -> &'static [(&'static str, f64)] {
+        match self {
+            Self::Standard => &DETAILED_CATEGORIES,
+            Self::AsianPacific => &ASIAN_PACIFIC_DETAILED_CATEGORIES,
+        }
+    }
+}
+
+/// BMI calculation response payload.
+///
+/// Contains calculated BMI value and health category interpretation.
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::{
+///     bmi::{healthy_weight_range, Classification},
+///     units::UnitSystem,
+///     BmiResponse,
+/// };
+///
+/// let response = BmiResponse {
+///     bmi: 22.857142857142858,
+///     bmi_rounded: 22.9,
+///     category: "Normal weight".to_string(),
+///     category_code: "normal_weight".to_string(),
+///     category_detailed: "Normal weight".to_string(),
+///     classification: Classification::Standard,
+///     units: UnitSystem::Metric,
+///     weight_kg: 70.0,
+///     healthy_weight_range: healthy_weight_range(1.75, UnitSystem::Metric, Classification::Standard),
+///     bmi_prime: None,
+///     ponderal_index: None,
+///     warnings: Vec::new(),
+/// };
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize, utoipa::ToSchema)]
+#[schema(
+    description = "Calculated BMI with its WHO category.",
+    example = json!({
+        "bmi": 22.857142857142858,
+        "bmi_rounded": 22.9,
+        "category": "Normal weight",
+        "category_code": "normal_weight",
+        "category_detailed": "Normal weight",
+        "classification": "standard",
+        "units": "metric",
+        "weight_kg": 70.0,
+        "healthy_weight_range": { "min_kg": 56.7, "max_kg": 76.6 }
+    })
+)]
+pub struct BmiResponse {
+    /// Calculated BMI value.
+    pub bmi: f64,
+    /// `bmi` rounded half-up to the requested precision, for display.
+    pub bmi_rounded: f64,
+    /// Health category based on WHO standards, from the unrounded `bmi`.
+    pub category: String,
+    /// Stable code of `category`, such as `normal_weight`, in every locale.
+    pub category_code: String,
+    /// Finer WHO class: thinness grades and obesity classes I to III.
+    pub category_detailed: String,
+    /// Threshold table the categories come from.
+    pub classification: Classification,
+    /// Unit system the request was given in.
+    pub units: units::UnitSystem,
+    /// Weight used for the calculation, in kilograms after any conversion.
+    pub weight_kg: f64,
+    /// Normal-weight range at the submitted height.
+    pub healthy_weight_range: HealthyWeightRange,
+    /// BMI relative to the upper normal limit, when `extended` was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bmi_prime: Option<Indicator>,
+    /// Weight over height cubed (kg/m³), when `extended` was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ponderal_index: Option<Indicator>,
+    /// Non-fatal issues with the input, such as a suspected unit mix-up.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// Weights between which a height is in the "Normal weight" category,
+/// rounded to one decimal.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, utoipa::ToSchema)]
+pub struct HealthyWeightRange {
+    /// Lowest normal weight in kilograms.
+    pub min_kg: f64,
+    /// Weight in kilograms where "Overweight" starts.
+    pub max_kg: f64,
+    /// `min_kg` in pounds, for imperial requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_lb: Option<f64>,
+    /// `max_kg` in pounds, for imperial requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_lb: Option<f64>,
+}
+
+/// A secondary index with a short reading of its value.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, utoipa::ToSchema)]
+pub struct Indicator {
+    /// Value of the index.
+    pub value: f64,
+    /// What the value suggests, e.g. "Within the normal range".
+    #[schema(value_type = String)]
+    pub interpretation: Cow<'static, str>,
+}
+
+/// Calculates BMI from weight and height.
+///
+/// Uses the standard BMI formula: BMI = weight(kg) / height(m)²
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::calculate_bmi;
+///
+/// let bmi = calculate_bmi(70.0, 1.75)?;
+/// assert_eq!(bmi, 22.857142857142858);
+/// # Ok::<(), bmi_calculator::error::BmiCalcError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns a [`BmiCalcError`](error::BmiCalcError) if either input is zero,
+/// negative or not finite, or if the result overflows.
+pub fn calculate_bmi(weight_kg: f64, height_m: f64) -> Result<f64, error::BmiCalcError> {
+    if !(weight_kg.is_finite() && weight_kg > 0.0) {
+        return Err(error::BmiCalcError::Weight(weight_kg));
+    }
+    if !(height_m.is_finite() && height_m > 0.0) {
+        return Err(error::BmiCalcError::Height(height_m));
+    }
+    let bmi = weight_kg / (height_m * height_m);
+    if !bmi.is_finite() {
+        return Err(error::BmiCalcError::Bmi(bmi));
+    }
+    Ok(bmi)
+}
+
+/// Categorizes BMI value according to WHO standards.
+///
+/// Returns health category as a string based on BMI ranges:
+/// - Underweight: BMI < 18.5
+/// - Normal weight: 18.5 ≤ BMI < 25
+/// - Overweight: 25 ≤ BMI < 30
+/// - Obese: BMI ≥ 30
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::categorize_bmi;
+///
+/// assert_eq!(categorize_bmi(22.0)?, "Normal weight");
+/// assert_eq!(categorize_bmi(17.0)?, "Underweight");
+/// assert_eq!(categorize_bmi(27.0)?, "Overweight");
+/// assert_eq!(categorize_bmi(32.0)?, "Obese");
+/// # Ok::<(), bmi_calculator::error::BmiCalcError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns [`BmiCalcError::Bmi`](error::BmiCalcError::Bmi)
+/// for zero, negative, NaN or infinite values, which no category can
+/// describe.
+pub fn categorize_bmi(bmi: f64) -> Result<&'static str, error::BmiCalcError> {
+    categorize_with(&CATEGORIES, bmi)
+}
+
+/// Classifies a BMI into the full WHO table ([`DETAILED_CATEGORIES`]).
+///
+/// Like [`categorize_bmi`], each class includes its lower bound: 35.0 is
+/// already "Obese class II" and 40.0 "Obese class III".
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::bmi::categorize_bmi_detailed;
+///
+/// assert_eq!(categorize_bmi_detailed(16.5)?, "Moderate thinness");
+/// assert_eq!(categorize_bmi_detailed(34.9)?, "Obese class I");
+/// assert_eq!(categorize_bmi_detailed(35.0)?, "Obese class II");
+/// assert_eq!(categorize_bmi_detailed(40.0)?, "Obese class III");
+/// # Ok::<(), bmi_calculator::error::BmiCalcError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns [`BmiCalcError::Bmi`](error::BmiCalcError::Bmi) for the same
+/// values as [`categorize_bmi`].
+pub fn categorize_bmi_detailed(bmi: f64) -> Result<&'static str, error::BmiCalcError> {
+    categorize_with(&DETAILED_CATEGORIES, bmi)
+}
+
+/// Classifies a BMI with any threshold table laid out like [`CATEGORIES`]:
+/// the first entry whose upper bound is above `bmi`.
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::bmi::{categorize_with, Classification};
+///
+/// let table = Classification::AsianPacific.categories();
+/// assert_eq!(categorize_with(table, 24.0)?, "Overweight");
+/// assert_eq!(categorize_with(table, 27.5)?, "Obese");
+/// # Ok::<(), bmi_calculator::error::BmiCalcError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns [`BmiCalcError::Bmi`](error::BmiCalcError::Bmi) for the same
+/// values as [`categorize_bmi`].
+///
+/// # Panics
+///
+/// Panics if the last upper bound of `table` is finite and `bmi` is above it.
+pub fn categorize_with(
+    table: &[(&'static str, f64)],
+    bmi: f64,
+) -> Result<&'static str, error::BmiCalcError> {
+    if !(bmi.is_finite() && bmi > 0.0) {
+        return Err(error::BmiCalcError::Bmi(bmi));
+    }
+    Ok(table
+        .iter()
+        .find(|(_, upper)| bmi < *upper)
+        .map(|(category, _)| *category)
+        .expect("category tables end at infinity"))
+}
+
+/// Bulleted HTML legend of a scheme's detailed categories for the main page.
+pub(crate) fn category_legend(classification: Classification) -> String {
+    let mut lower = None;
+    let lines: Vec<String> = classification
+        .detailed_categories()
+        .iter()
+        .map(|&(category, upper)| {
+            let range = match (lower, upper.is_finite()) {
+                (None, _) => format!("&lt; {upper}"),
+                (Some(lower), true) => format!("{lower} to &lt; {upper}"),
+                (Some(lower), false) => format!("≥ {lower}"),
+            };
+            lower = Some(upper);
+            format!("• {category}: {range}")
+        })
+        .collect();
+    lines.join("<br>\n")
+}
+
+/// Decimal places of `bmi_rounded` when the request does not say.
+pub const DEFAULT_PRECISION: u8 = 1;
+
+/// Most decimal places a request may ask for.
+pub const MAX_PRECISION: u8 = 4;
+
+/// Rounds a BMI to `precision` decimal places, halves away from zero.
+///
+/// Scales by a power of ten and rounds the scaled value, so the result is
+/// the same on every platform and never goes through string formatting.
+/// Scaled values within a few ulps of a half count as the half, so 1.005
+/// rounds up to 1.01 even though the nearest `f64` lies just below it.
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::bmi::round_bmi;
+///
+/// assert_eq!(round_bmi(22.857142857142858, 1), 22.9);
+/// assert_eq!(round_bmi(22.857142857142858, 0), 23.0);
+/// assert_eq!(round_bmi(22.857142857142858, 3), 22.857);
+/// assert_eq!(round_bmi(1.005, 2), 1.01);
+/// ```
+pub fn round_bmi(bmi: f64, precision: u8) -> f64 {
+    let scale = 10_f64.powi(i32::from(precision));
+    let scaled = bmi.abs() * scale;
+    let floor = scaled.floor();
+    // Scaling leaves decimal halves such as 100.5 a few ulps short
+    let tolerance = 4.0 * f64::EPSILON * scaled.max(1.0);
+    let rounded = if scaled - floor >= 0.5 - tolerance {
+        floor + 1.0
+    } else {
+        floor
+    };
+    (rounded / scale).copysign(bmi)
+}
+
+/// Computes the normal-weight range for `height_m` under `classification`.
+///
+/// Pounds are included when `system` is imperial.
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::{
+///     bmi::{healthy_weight_range, Classification},
+///     units::UnitSystem,
+/// };
+///
+/// let range = healthy_weight_range(1.75, UnitSystem::Metric, Classification::Standard);
+/// assert_eq!((range.min_kg, range.max_kg), (56.7, 76.6));
+/// assert_eq!(range.min_lb, None);
+///
+/// let range = healthy_weight_range(1.75, UnitSystem::Imperial, Classification::Standard);
+/// assert_eq!((range.min_lb, range.max_lb), (Some(124.9), Some(168.8)));
+///
+/// let range = healthy_weight_range(1.75, UnitSystem::Metric, Classification::AsianPacific);
+/// assert_eq!(range.max_kg, 70.4);
+/// ```
+pub fn healthy_weight_range(
+    height_m: f64,
+    system: units::UnitSystem,
+    classification: Classification,
+) -> HealthyWeightRange {
+    let table = classification.categories();
+    let (_, lower) = table[0];
+    let (_, upper) = table[1];
+    let weight_at = |bmi: f64| bmi * height_m * height_m;
+    let (min_kg, max_kg) = (weight_at(lower), weight_at(upper));
+    let pounds = |kg: f64| round_bmi(kg / units::KG_PER_LB, 1);
+    let imperial = system == units::UnitSystem::Imperial;
+    HealthyWeightRange {
+        min_kg: round_bmi(min_kg, 1),
+        max_kg: round_bmi(max_kg, 1),
+        min_lb: imperial.then(|| pounds(min_kg)),
+        max_lb: imperial.then(|| pounds(max_kg)),
+    }
+}
+
+/// Computes BMI Prime: the BMI divided by the upper normal limit (25).
+///
+/// Values from 0.74 up to 1.0 are in the normal range.
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::bmi::{bmi_prime, bmi_prime_interpretation};
+///
+/// let prime = bmi_prime(22.857142857142858);
+/// assert!((prime - 0.914).abs() < 0.001);
+/// assert_eq!(bmi_prime_interpretation(prime), "Within the normal range");
+/// ```
+pub fn bmi_prime(bmi: f64) -> f64 {
+    let (_, upper) = CATEGORIES[1];
+    bmi / upper
+}
+
+/// Reads a [`bmi_prime`] value against the WHO categories.
+pub fn bmi_prime_interpretation(prime: f64) -> &'static str {
+    let (_, lower) = CATEGORIES[0];
+    let (_, upper) = CATEGORIES[1];
+    if prime < lower / upper {
+        "Below the normal range"
+    } else if prime < 1.0 {
+        "Within the normal range"
+    } else {
+        "Above the normal range"
+    }
+}
+
+/// Typical adult ponderal index range in kg/m³, lower bound included.
+const PONDERAL_NORMAL: (f64, f64) = (11.0, 15.0);
+
+/// Computes the ponderal (Rohrer's) index: weight over height cubed.
+///
+/// Unlike BMI it does not drift with height, so it suits very short and
+/// very tall people better.
+///
+/// # Examples
+///
+/// ```
+/// use bmi_calculator::bmi::{ponderal_index, ponderal_index_interpretation};
+///
+/// let index = ponderal_index(70.0, 1.75);
+/// assert!((index - 13.06).abs() < 0.01);
+/// assert_eq!(ponderal_index_interpretation(index), "Within the typical adult range");
+/// ```
+pub fn ponderal_index(weight_kg: f64, height_m: f64) -> f64 {
+    weight_kg / height_m.powi(3)
+}
+
+/// Reads a [`ponderal_index`] value against the typical adult range.
+pub fn ponderal_index_interpretation(index: f64) -> &'static str {
+    let (lower, upper) = PONDERAL_NORMAL;
+    if index < lower {
+        "Below the typical adult range"
+    } else if index < upper {
+        "Within the typical adult range"
+    } else {
+        "Above the typical adult range"
+    }
+}
+
+/// WHO categories with the BMI at which the next one starts, lightest first.
+///
+/// Shared by [`categorize_bmi`] and the weight-range hints, as the
+/// [`Classification::Standard`] table.
+pub const CATEGORIES: [(&str, f64); 4] = [
+    ("Underweight", 18.5),
+    ("Normal weight", 25.0),
+    ("Overweight", 30.0),
+    ("Obese", f64::INFINITY),
+];
+
+/// Full WHO classification, same layout as [`CATEGORIES`]: thinness split
+/// into three grades and obesity into classes I to III.
+pub const DETAILED_CATEGORIES: [(&str, f64); 8] = [
+    ("Severe thinness", 16.0),
+    ("Moderate thinness", 17.0),
+    ("Mild thinness", 18.5),
+    ("Normal weight", 25.0),
+    ("Overweight", 30.0),
+    ("Obese class I", 35.0),
+    ("Obese class II", 40.0),
+    ("Obese class III", f64::INFINITY),
+];
+
+/// [`CATEGORIES`] with the WHO action points for Asian populations.
+pub const ASIAN_PACIFIC_CATEGORIES: [(&str, f64); 4] = [
+    ("Underweight", 18.5),
+    ("Normal weight", 23.0),
+    ("Overweight", 27.5),
+    ("Obese", f64::INFINITY),
+];
+
+/// [`ASIAN_PACIFIC_CATEGORIES`] with thinness split into three grades.
+pub const ASIAN_PACIFIC_DETAILED_CATEGORIES: [(&str, f64); 6] = [
+    ("Severe thinness", 16.0),
+    ("Moderate thinness", 17.0),
+    ("Mild thinness", 18.5),
+    ("Normal weight", 23.0),
+    ("Overweight", 27.5),
+    ("Obese", f64::INFINITY),
+];
+
+/// Validates a request and computes its BMI response.
+///
+/// Shared by every transport so validation, logging and metrics stay
+/// identical.
+///
+/// # Errors
+///
+/// Returns a [`BmiError`](error::BmiError) if:
+/// - Required fields for the unit system are missing, or systems are mixed
+/// - Weight or height are outside the default [`validation::Bounds`]
+/// - `strict_units` is set and a value looks like it uses another unit
+/// - `precision` is above [`MAX_PRECISION`]
+pub fn process_bmi_request(payload: &BmiRequest) -> Result<BmiResponse, error::BmiError> {
+    process_bmi_request_with(payload, &validation::Bounds::default())
+}
+
+/// [`process_bmi_request`] with configured plausibility `bounds`.
+///
+/// # Errors
+///
+/// As [`process_bmi_request`], checking `bounds` instead of the defaults.
+pub fn process_bmi_request_with(
+    payload: &BmiRequest,
+    bounds: &validation::Bounds,
+) -> Result<BmiResponse, error::BmiError> {
+    process_timed(payload, bounds, &mut timings::Timings::default())
+}
+
+/// One-line form of a result, e.g. `BMI: 22.9 (Normal weight)`, with
+/// `bmi_rounded` at the requested `precision`.
+pub(crate) fn text_line(response: &BmiResponse, precision: Option<u8>) -> String {
+    let precision = usize::from(precision.unwrap_or(DEFAULT_PRECISION));
+    format!(
+        "BMI: {:.precision$} ({})",
+        response.bmi_rounded, response.category
+    )
+}
+
+/// [`process_bmi_request_with`], recording stage durations into `timings`.
+pub(crate) fn process_timed(
+    payload: &BmiRequest,
+    bounds: &validation::Bounds,
+    timings: &mut timings::Timings,
+) -> Result<BmiResponse, error::BmiError> {
+    let outcome = compute_response(payload, bounds, timings);
+    match &outcome {
+        Ok(_) => telemetry::calculation_succeeded(),
+        Err(error) => telemetry::validation_failed(error.code()),
+    }
+    outcome
+}
+
+/// Body of [`process_bmi_request`], without the metrics.
+fn compute_response(
+    payload: &BmiRequest,
+    bounds: &validation::Bounds,
+    timings: &mut timings::Timings,
+) -> Result<BmiResponse, error::BmiError> {
+    let precision = payload.precision.unwrap_or(DEFAULT_PRECISION);
+    if precision > MAX_PRECISION {
+        return Err(error::BmiError::InvalidPrecision(format!(
+            "precision must be between 0 and {MAX_PRECISION}, got {precision}"
+        )));
+    }
+    let units::Measurements {
+        weight_kg,
+        height_m,
+    } = timings
+        .measure("normalization", || {
+            let measurements = units::normalize(payload)?;
+            validation::check(payload, measurements, bounds)?;
+            Ok::<_, error::BmiError>(measurements)
+        })
+        .inspect_err(|error| {
+            event!(
+                name: "bmi.validation.failed",
+                Level::WARN,
+                units = payload.units.as_str(),
+                code = error.code(),
+                reason = error.message(),
+                "Invalid input: {{reason}}"
+            );
+        })?;
+
+    event!(
+        name: "bmi.calculation.started",
+        Level::INFO,
+        weight_kg = weight_kg,
+        height_m = height_m,
+        units = payload.units.as_str(),
+        "BMI calculation requested: weight={{weight_kg}}kg, height={{height_m}}m"
+    );
+
+    let bmi = timings
+        .measure("calculation", || calculate_bmi(weight_kg, height_m))
+        .inspect_err(|error| {
+            event!(
+                name: "bmi.validation.failed",
+                Level::WARN,
+                weight_kg = weight_kg,
+                height_m = height_m,
+                reason = %error,
+                "Invalid input: {{reason}}"
+            );
+        })?;
+
+    // The heuristics look at the fields as sent, in either unit system
+    let suspicions = timings.measure("validation", || units::check(payload));
+    if let Some(suspicion) = suspicions.first() {
+        event!(
+            name: "bmi.units.suspicious",
+            Level::WARN,
+            field = suspicion.field.name(),
+            suspected_unit = suspicion.suspected_unit,
+            strict = payload.strict_units,
+            "Input {{field}} looks like {{suspected_unit}}"
+        );
+        if payload.strict_units {
+            return Err(error::BmiError::SuspectedUnitMismatch(format!(
+                "{suspicion} (strict_units)"
+            )));
+        }
+    }
+
+    let classification = payload.classification;
+    let (category, category_detailed) = timings.measure("categorization", || {
+        Ok::<_, error::BmiCalcError>((
+            categorize_with(classification.categories(), bmi)?,
+            categorize_with(classification.detailed_categories(), bmi)?,
+        ))
+    })?;
+
+    event!(
+        name: "bmi.calculation.success",
+        Level::INFO,
+        bmi = bmi,
+        category = category,
+        "BMI calculated: {{bmi}}, category: {{category}}"
+    );
+
+    Ok(BmiResponse {
+        bmi,
+        bmi_rounded: round_bmi(bmi, precision),
+        category: category.to_string(),
+        category_code: crate::i18n::category_code(category).to_string(),
+        category_detailed: category_detailed.to_string(),
+        classification,
+        units: payload.units,
+        weight_kg,
+        healthy_weight_range: healthy_weight_range(height_m, payload.units, classification),
+        bmi_prime: payload.extended.then(|| {
+            let prime = bmi_prime(bmi);
+            Indicator {
+                value: prime,
+                interpretation: bmi_prime_interpretation(prime).into(),
+            }
+        }),
+        ponderal_index: payload.extended.then(|| {
+            let index = ponderal_index(weight_kg, height_m);
+            Indicator {
+                value: index,
+                interpretation: ponderal_index_interpretation(index).into(),
+            }
+        }),
+        warnings: suspicions.iter().map(ToString::to_string).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_bmi() {
+        let bmi = calculate_bmi(70.0, 1.75).unwrap();
+        assert!((bmi - 22.857).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_calculate_bmi_rejects_degenerate_inputs() {
+        use error::BmiCalcError;
+
+        for value in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                calculate_bmi(value, 1.75),
+                Err(BmiCalcError::Weight(_))
+            ));
+            assert!(matches!(
+                calculate_bmi(70.0, value),
+                Err(BmiCalcError::Height(_))
+            ));
+        }
+        assert!(matches!(
+            calculate_bmi(f64::MAX, f64::MIN_POSITIVE),
+            Err(BmiCalcError::Bmi(_))
+        ));
+    }
+
+    #[test]
+    fn test_categorize_bmi() {
+        assert_eq!(categorize_bmi(17.0), Ok("Underweight"));
+        assert_eq!(categorize_bmi(22.0), Ok("Normal weight"));
+        assert_eq!(categorize_bmi(27.0), Ok("Overweight"));
+        assert_eq!(categorize_bmi(32.0), Ok("Obese"));
+    }
+
+    #[test]
+    fn test_round_bmi() {
+        assert_eq!(round_bmi(24.95, 1), 25.0);
+        assert_eq!(round_bmi(24.94, 1), 24.9);
+        assert_eq!(round_bmi(18.5, 0), 19.0);
+        assert_eq!(round_bmi(22.857142857142858, 4), 22.8571);
+        assert_eq!(round_bmi(1.005, 2), 1.01);
+        assert_eq!(round_bmi(2.675, 2), 2.68);
+        assert_eq!(round_bmi(-2.675, 2), -2.68);
+        assert_eq!(round_bmi(0.000_000_5, 6), 0.000_001);
+        assert_eq!(round_bmi(99.96, 1), 100.0);
+        assert!(round_bmi(f64::NAN, 1).is_nan());
+    }
+
+    #[test]
+    fn test_healthy_weight_range() {
+        let range = healthy_weight_range(1.75, units::UnitSystem::Metric, Classification::Standard);
+        assert!((range.min_kg - 56.7).abs() < 0.1);
+        assert!((range.max_kg - 76.5).abs() < 0.15);
+        assert_eq!((range.min_lb, range.max_lb), (None, None));
+
+        let request = BmiRequest {
+            units: units::UnitSystem::Imperial,
+            weight_lb: Some(154.0),
+            height_in: Some(69.0),
+            ..BmiRequest::default()
+        };
+        let range = process_bmi_request(&request).unwrap().healthy_weight_range;
+        assert_eq!(
+            range,
+            healthy_weight_range(69.0 * 0.0254, request.units, request.classification)
+        );
+        assert_eq!((range.min_lb, range.max_lb), (Some(125.3), Some(169.3)));
+    }
+
+    #[test]
+    fn test_secondary_indices() {
+        assert_eq!(bmi_prime(25.0), 1.0);
+        assert_eq!(
+            bmi_prime_interpretation(bmi_prime(18.4)),
+            "Below the normal range"
+        );
+        assert_eq!(
+            bmi_prime_interpretation(bmi_prime(18.5)),
+            "Within the normal range"
+        );
+        assert_eq!(
+            bmi_prime_interpretation(bmi_prime(25.0)),
+            "Above the normal range"
+        );
+
+        assert_eq!(ponderal_index(8.0, 2.0), 1.0);
+        assert_eq!(
+            ponderal_index_interpretation(10.9),
+            "Below the typical adult range"
+        );
+        assert_eq!(
+            ponderal_index_interpretation(11.0),
+            "Within the typical adult range"
+        );
+        assert_eq!(
+            ponderal_index_interpretation(15.0),
+            "Above the typical adult range"
+        );
+    }
+
+    #[test]
+    fn test_extended_adds_secondary_indices() {
+        let mut request = BmiRequest {
+            weight_kg: Some(70.0),
+            height_m: Some(1.75),
+            ..BmiRequest::default()
+        };
+        let response = process_bmi_request(&request).unwrap();
+        assert_eq!((response.bmi_prime, response.ponderal_index), (None, None));
+
+        request.extended = true;
+        let response = process_bmi_request(&request).unwrap();
+        let prime = response.bmi_prime.unwrap();
+        assert_eq!(prime.value, response.bmi / 25.0);
+        assert_eq!(prime.interpretation, "Within the normal range");
+        let index = response.ponderal_index.unwrap();
+        assert!((index.value - 13.06).abs() < 0.01);
+        assert_eq!(index.interpretation, "Within the typical adult range");
+    }
+
+    #[test]
+    fn test_rounded_bmi_keeps_the_exact_category() {
+        let request = |precision| BmiRequest {
+            weight_kg: Some(24.96),
+            height_m: Some(1.0),
+            precision,
+            ..BmiRequest::default()
+        };
+
+        let response = process_bmi_request(&request(None)).unwrap();
+        assert_eq!(response.bmi, 24.96);
+        assert_eq!(response.bmi_rounded, 25.0);
+        assert_eq!(response.category, "Normal weight");
+
+        assert_eq!(
+            process_bmi_request(&request(Some(0))).unwrap().bmi_rounded,
+            25.0
+        );
+        assert_eq!(
+            process_bmi_request(&request(Some(4))).unwrap().bmi_rounded,
+            24.96
+        );
+        assert!(matches!(
+            process_bmi_request(&request(Some(5))),
+            Err(error::BmiError::InvalidPrecision(_))
+        ));
+    }
+
+    #[test]
+    fn test_categorize_bmi_detailed_boundaries() {
+        for (bmi, expected) in [
+            (15.9, "Severe thinness"),
+            (16.0, "Moderate thinness"),
+            (17.0, "Mild thinness"),
+            (18.5, "Normal weight"),
+            (30.0, "Obese class I"),
+            (34.999, "Obese class I"),
+            (35.0, "Obese class II"),
+            (39.999, "Obese class II"),
+            (40.0, "Obese class III"),
+            (60.0, "Obese class III"),
+        ] {
+            assert_eq!(categorize_bmi_detailed(bmi), Ok(expected), "{bmi}");
+        }
+        assert!(categorize_bmi_detailed(f64::NAN).is_err());
+        assert_eq!(categorize_bmi(45.0), Ok("Obese"));
+    }
+
+    #[test]
+    fn test_asian_pacific_boundaries() {
+        let request = |bmi: f64| BmiRequest {
+            weight_kg: Some(bmi),
+            height_m: Some(1.0),
+            classification: Classification::AsianPacific,
+            ..BmiRequest::default()
+        };
+        for (bmi, expected, standard) in [
+            (22.99, "Normal weight", "Normal weight"),
+            (23.0, "Overweight", "Normal weight"),
+            (27.49, "Overweight", "Overweight"),
+            (27.5, "Obese", "Overweight"),
+        ] {
+            let response = process_bmi_request(&request(bmi)).unwrap();
+            assert_eq!(response.category, expected, "{bmi}");
+            assert_eq!(response.classification, Classification::AsianPacific);
+            assert_eq!(categorize_bmi(bmi), Ok(standard), "{bmi}");
+        }
+        let response = process_bmi_request(&request(27.5)).unwrap();
+        assert_eq!(response.category_detailed, "Obese");
+        assert_eq!(response.healthy_weight_range.max_kg, 23.0);
+    }
+
+    #[test]
+    fn test_category_legend_follows_the_table() {
+        let legend = category_legend(Classification::Standard);
+        assert!(legend.starts_with("• Severe thinness: &lt; 16<br>"));
+        assert!(legend.contains("• Obese class II: 35 to &lt; 40<br>"));
+        assert!(legend.ends_with("• Obese class III: ≥ 40"));
+        assert_eq!(legend.matches('•').count(), DETAILED_CATEGORIES.len());
+    }
+
+    #[test]
+    fn test_categorize_bmi_rejects_degenerate_values() {
+        for bmi in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                categorize_bmi(bmi),
+                Err(error::BmiCalcError::Bmi(_))
+            ));
+        }
+    }
+}
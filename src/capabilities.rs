@@ -0,0 +1,240 @@
+//! Feature discovery for clients.
+//!
+//! `GET /api/capabilities` tells SDKs what this deployment supports: optional
+//! features, effective limits, unit systems, locales, content types and API
+//! versions. Every value is read from the same [`AppState`] fields that
+//! switch the corresponding routes and limits, so the answer follows the live
+//! configuration. The body carries a strong `ETag`; a matching
+//! `If-None-Match` gets `304 Not Modified`.
+
+use axum::{
+    extract::State,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{changelog, i18n, storage, units::UnitSystem, AppState};
+
+/// Optional features and whether this server has them enabled.
+#[derive(Debug, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct Features {
+    /// `POST /api/calculate/batch`.
+    pub batch: bool,
+    /// `GET /api/history` and the session cookie.
+    pub history: bool,
+    /// `GET /api/history/export`.
+    pub history_export: bool,
+    /// `application/x-protobuf` on `POST /api/calculate` and its batch route.
+    pub protobuf: bool,
+    /// `application/xml` on `/api/calculate`.
+    pub xml: bool,
+    /// `GET /api/calculate/t/{token}`.
+    pub measurement_tokens: bool,
+    /// Measurement tokens must be signed.
+    pub signed_measurement_tokens: bool,
+    /// Per-client rate limiting on `/api/*`.
+    pub rate_limit: bool,
+    /// `/api/admin/*` routes.
+    pub admin: bool,
+    /// `GET /metrics`.
+    pub metrics: bool,
+}
+
+/// Effective limits.
+#[derive(Debug, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct Limits {
+    /// Entries accepted per batch request (after any memory budget).
+    pub max_batch_entries: usize,
+    /// Largest batch body in bytes.
+    pub max_batch_body_bytes: usize,
+    /// Largest `limit` on `GET /api/history`.
+    pub max_history_limit: u32,
+}
+
+/// Body of `GET /api/capabilities`.
+#[derive(Debug, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct Capabilities {
+    /// Version of the running server.
+    pub server_version: &'static str,
+    /// API versions described in the changelog, newest first.
+    pub api_versions: Vec<String>,
+    /// Optional features.
+    pub features: Features,
+    /// Effective limits.
+    pub limits: Limits,
+    /// Unit systems accepted by the calculation endpoints.
+    pub units: Vec<UnitSystem>,
+    /// Locales of response messages.
+    pub locales: Vec<&'static str>,
+    /// Media types of `POST /api/calculate`; `text/plain` is response-only.
+    pub content_types: Vec<&'static str>,
+}
+
+impl Capabilities {
+    /// Reads the capabilities of the server running with `state`.
+    pub fn of(state: &AppState) -> Self {
+        let history = state.storage.is_some();
+        let mut content_types = vec!["application/json", "text/plain"];
+        if cfg!(feature = "proto") {
+            content_types.push("application/x-protobuf");
+        }
+        if cfg!(feature = "xml") {
+            content_types.push("application/xml");
+        }
+        Self {
+            server_version: env!("CARGO_PKG_VERSION"),
+            api_versions: changelog::entries()
+                .iter()
+                .map(|entry| entry.version.clone())
+                .collect(),
+            features: Features {
+                batch: true,
+                history,
+                history_export: history,
+                protobuf: cfg!(feature = "proto"),
+                xml: cfg!(feature = "xml"),
+                measurement_tokens: true,
+                signed_measurement_tokens: state.measurement_tokens.requires_signature(),
+                rate_limit: state.rate_limiter.is_some(),
+                admin: state.admin_token.is_some(),
+                metrics: state.metrics.is_some(),
+            },
+            limits: Limits {
+                max_batch_entries: state.memory.sizing().batch_entries,
+                max_batch_body_bytes: state.limits.batch_body_bytes,
+                max_history_limit: storage::MAX_LIMIT,
+            },
+            units: vec![UnitSystem::Metric, UnitSystem::Imperial],
+            locales: i18n::Locale::ALL
+                .into_iter()
+                .map(i18n::Locale::as_str)
+                .collect(),
+            content_types,
+        }
+    }
+}
+
+/// Strong validator: a digest of the serialized body.
+fn etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("\"{hex}\"")
+}
+
+/// Returns true if `If-None-Match` lists `etag` or `*`.
+fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|candidate| candidate.trim().trim_start_matches("W/"))
+        .any(|candidate| candidate == etag || candidate == "*")
+}
+
+/// Handles `GET /api/capabilities`.
+#[utoipa::path(
+    get,
+    path = "/api/capabilities",
+    tag = "discovery",
+    summary = "Describe this server's features and limits",
+    description = "Sends an `ETag`; repeat the request with `If-None-Match` to get `304` while nothing changed.",
+    responses(
+        (status = 200, description = "Features, limits and supported formats", body = Capabilities),
+        (status = 304, description = "Unchanged since the given `ETag`"),
+    )
+)]
+pub async fn capabilities_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let body = serde_json::to_vec(&Capabilities::of(&state)).expect("capabilities serialize");
+    let etag = etag(&body);
+    let cache = [
+        (ETAG, etag.clone()),
+        (CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    if not_modified(&headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, cache).into_response();
+    }
+    (cache, [(CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get(state: &AppState, if_none_match: Option<&str>) -> (StatusCode, String, Value) {
+        let mut request = Request::get("/api/capabilities");
+        if let Some(etag) = if_none_match {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = crate::build_router(state.clone())
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, etag, body)
+    }
+
+    #[tokio::test]
+    async fn test_reports_live_configuration() {
+        let plain = AppState::default();
+        let (status, plain_etag, body) = get(&plain, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["features"]["history"], false);
+        assert_eq!(body["features"]["admin"], false);
+        assert_eq!(
+            body["limits"]["max_batch_entries"],
+            crate::batch::MAX_ENTRIES
+        );
+        assert_eq!(body["units"], serde_json::json!(["metric", "imperial"]));
+        assert_eq!(body["api_versions"][0], env!("CARGO_PKG_VERSION"));
+
+        let configured = AppState {
+            storage: Some(Arc::new(
+                storage::SqliteStorage::open("sqlite::memory:").unwrap(),
+            )),
+            admin_token: Some(Arc::from("secret")),
+            memory: Arc::new(crate::memory::Memory::new(Some(16))),
+            ..AppState::default()
+        };
+        let (_, configured_etag, body) = get(&configured, None).await;
+        assert_eq!(body["features"]["history"], true);
+        assert_eq!(body["features"]["history_export"], true);
+        assert_eq!(body["features"]["admin"], true);
+        assert_eq!(body["limits"]["max_batch_entries"], 2048);
+        assert_ne!(configured_etag, plain_etag);
+    }
+
+    #[tokio::test]
+    async fn test_matching_etag_is_not_modified() {
+        let state = AppState::default();
+        let (_, etag, _) = get(&state, None).await;
+
+        for header in [
+            etag.clone(),
+            format!("\"other\", W/{etag}"),
+            "*".to_string(),
+        ] {
+            let (status, again, body) = get(&state, Some(&header)).await;
+            assert_eq!(status, StatusCode::NOT_MODIFIED, "{header}");
+            assert_eq!(again, etag);
+            assert_eq!(body, Value::Null);
+        }
+        assert_eq!(get(&state, Some("\"stale\"")).await.0, StatusCode::OK);
+    }
+}
@@ -0,0 +1,233 @@
+//! Machine-readable API changelog.
+//!
+//! Entries live in the embedded `changelog.json` data module and are served
+//! at `GET /api/changelog`, optionally filtered with `?since=<version>`.
+
+use std::sync::OnceLock;
+
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Raw changelog data, one entry per release.
+const CHANGELOG_JSON: &str = include_str!("changelog.json");
+
+/// A release and the API changes it introduced.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Entry {
+    /// Release version (`major.minor.patch`).
+    pub version: String,
+    /// Release date (`YYYY-MM-DD`).
+    pub date: String,
+    /// Changes shipped in this release.
+    pub changes: Vec<Change>,
+}
+
+/// A single API-visible change.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Change {
+    /// Change type: `added_endpoint`, `added_field`, `deprecated_field`, or `behavior_change`.
+    pub kind: ChangeKind,
+    /// Affected route, when the change is tied to one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Affected field, when the change is tied to one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// Human-readable summary.
+    pub description: String,
+}
+
+/// Categories of API change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// A new route.
+    AddedEndpoint,
+    /// A new request or response field.
+    AddedField,
+    /// A field scheduled for removal.
+    DeprecatedField,
+    /// Changed semantics of an existing surface.
+    BehaviorChange,
+}
+
+/// Response body for `GET /api/changelog`.
+#[derive(Debug, Serialize)]
+pub struct ChangelogResponse {
+    /// Version of the running server.
+    pub current_version: &'static str,
+    /// Matching entries, newest first.
+    pub entries: Vec<Entry>,
+}
+
+/// Query parameters for `GET /api/changelog`.
+#[derive(Debug, Deserialize)]
+pub struct ChangelogQuery {
+    /// Only return releases strictly newer than this version.
+    pub since: Option<String>,
+}
+
+/// Returns the parsed changelog, newest release first.
+///
+/// # Panics
+///
+/// Panics if the embedded `changelog.json` is malformed; the unit tests
+/// guarantee it parses.
+pub fn entries() -> &'static [Entry] {
+    static ENTRIES: OnceLock<Vec<Entry>> = OnceLock::new();
+    ENTRIES.get_or_init(|| {
+        let mut entries: Vec<Entry> =
+            serde_json::from_str(CHANGELOG_JSON).expect("embedded changelog.json must be valid");
+        entries.sort_by_key(|entry| std::cmp::Reverse(parse_version(&entry.version)));
+        entries
+    })
+}
+
+/// Keeps only entries newer than `since`.
+pub fn entries_since(entries: &[Entry], since: (u64, u64, u64)) -> Vec<Entry> {
+    entries
+        .iter()
+        .filter(|entry| parse_version(&entry.version).is_some_and(|version| version > since))
+        .cloned()
+        .collect()
+}
+
+/// Parses a `major.minor.patch` version, accepting a leading `v`.
+///
+/// Missing minor or patch components default to zero (`1.3` is `1.3.0`).
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |part| part.parse().ok())?;
+    let patch = parts.next().map_or(Some(0), |part| part.parse().ok())?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+/// Serves the API changelog.
+///
+/// # Errors
+///
+/// Returns HTTP 400 if `since` is not a valid version.
+pub async fn changelog_handler(Query(query): Query<ChangelogQuery>) -> Response {
+    let entries = match query.since.as_deref() {
+        None => entries().to_vec(),
+        Some(since) => match parse_version(since) {
+            Some(since) => entries_since(entries(), since),
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid version in since parameter: {since}"),
+                )
+                    .into_response()
+            }
+        },
+    };
+
+    Json(ChangelogResponse {
+        current_version: env!("CARGO_PKG_VERSION"),
+        entries,
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(version: &str) -> Entry {
+        Entry {
+            version: version.to_string(),
+            date: "2025-01-01".to_string(),
+            changes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_embedded_changelog_parses() {
+        assert!(!entries().is_empty());
+        for entry in entries() {
+            assert!(
+                parse_version(&entry.version).is_some(),
+                "bad version {}",
+                entry.version
+            );
+            assert_eq!(entry.date.len(), 10, "bad date {}", entry.date);
+        }
+    }
+
+    #[test]
+    fn test_current_minor_release_has_entry() {
+        let (major, minor, _) = parse_version(env!("CARGO_PKG_VERSION")).unwrap();
+        assert!(
+            entries().iter().any(|entry| {
+                parse_version(&entry.version).is_some_and(|(m, n, _)| (m, n) == (major, minor))
+            }),
+            "changelog.json needs an entry for {major}.{minor}"
+        );
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("1.5.2"), Some((1, 5, 2)));
+        assert_eq!(parse_version("v1.3"), Some((1, 3, 0)));
+        assert_eq!(parse_version("2"), Some((2, 0, 0)));
+        assert_eq!(parse_version("1.x"), None);
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn test_entries_since() {
+        let entries = [entry("1.5.0"), entry("1.4.0"), entry("1.3.0")];
+
+        let newer: Vec<_> = entries_since(&entries, (1, 3, 0))
+            .into_iter()
+            .map(|entry| entry.version)
+            .collect();
+        assert_eq!(newer, ["1.5.0", "1.4.0"]);
+
+        assert!(entries_since(&entries, (1, 5, 0)).is_empty());
+        assert_eq!(entries_since(&entries, (0, 9, 0)).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_changelog_endpoint() {
+        use axum::{body::Body, extract::Request};
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        let router = crate::build_router(crate::AppState::default());
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/changelog?since=0.0.1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["current_version"], env!("CARGO_PKG_VERSION"));
+        assert!(!body["entries"].as_array().unwrap().is_empty());
+
+        let response = router
+            .oneshot(
+                Request::get("/api/changelog?since=banana")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
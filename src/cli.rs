@@ -0,0 +1,331 @@
+//! Command-line arguments: `serve` (the default), `calc`, `token`, `export`
+//! and `import`.
+//!
+//! `bmi_calculator calc --weight-kg 70 --height-m 1.75` prints
+//! `BMI: 22.9 (Normal weight)` without starting the server. The arguments
+//! become a [`BmiRequest`] run through [`process_bmi_request_with`] with the
+//! configured bounds, exactly as `POST /api/calculate` does, so the two
+//! cannot disagree. Without a subcommand the server starts as before.
+//!
+//! `token` mints a [`measurement_token`]; `export` and `import` move the
+//! history through a [`backup`](crate::backup).
+
+use std::{net::IpAddr, path::PathBuf};
+
+use anyhow::{bail, Result};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+
+use crate::{
+    bmi, config::AppConfig, measurement_token, process_bmi_request_with, units::UnitSystem,
+    validation, BmiRequest,
+};
+
+/// Arguments of the `bmi_calculator` binary.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "BMI calculator web server and command line",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    /// What to run; the server when omitted.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// `serve` options, also accepted without the subcommand.
+    #[command(flatten)]
+    pub serve: ServeArgs,
+}
+
+/// Subcommands.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the HTTP server (the default).
+    Serve(ServeArgs),
+    /// Calculate one BMI and print it.
+    Calc(CalcArgs),
+    /// Print a measurement token for a QR code.
+    Token(TokenArgs),
+    /// Write the history to a gzip-compressed backup.
+    Export(ExportArgs),
+    /// Restore a backup written by `export`.
+    Import(ImportArgs),
+}
+
+/// Options of `serve`.
+#[derive(Debug, Default, Args)]
+pub struct ServeArgs {
+    /// Port to listen on, overriding the configuration.
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Address to bind, overriding the configuration.
+    #[arg(long)]
+    pub bind: Option<IpAddr>,
+    /// Serve canned API fixtures instead of calculating.
+    #[arg(long)]
+    pub mock: bool,
+    /// Bind with `SO_REUSEPORT` so a new process can overlap the old one.
+    #[arg(long)]
+    pub reuseport: bool,
+    /// Adopt the listening socket on file descriptor `N`.
+    #[arg(long, value_name = "N")]
+    pub inherit_fd: Option<i32>,
+}
+
+impl ServeArgs {
+    /// Applies the flags on top of `config`.
+    pub fn apply(&self, config: &mut AppConfig) {
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        if let Some(bind) = self.bind {
+            config.bind = bind;
+        }
+        if self.mock {
+            config.mock.enabled = true;
+        }
+        if self.reuseport {
+            config.listener.reuseport = true;
+        }
+        if let Some(fd) = self.inherit_fd {
+            config.listener.inherit_fd = Some(fd);
+        }
+    }
+}
+
+/// Options of `calc`, named like the request fields.
+#[derive(Debug, Default, Args)]
+pub struct CalcArgs {
+    /// Unit system: `metric` or `imperial`.
+    #[arg(long, default_value = "metric", value_parser = parse_units)]
+    pub units: UnitSystem,
+    /// Weight in kilograms.
+    #[arg(long)]
+    pub weight_kg: Option<f64>,
+    /// Height in meters.
+    #[arg(long)]
+    pub height_m: Option<f64>,
+    /// Height in centimeters, instead of `--height-m`.
+    #[arg(long)]
+    pub height_cm: Option<f64>,
+    /// Weight in pounds (imperial).
+    #[arg(long)]
+    pub weight_lb: Option<f64>,
+    /// Height in feet (imperial); `--height-in` adds the remaining inches.
+    #[arg(long)]
+    pub height_ft: Option<f64>,
+    /// Height in inches (imperial).
+    #[arg(long)]
+    pub height_in: Option<f64>,
+    /// Decimal places of the printed BMI, 0 to 4.
+    #[arg(long)]
+    pub precision: Option<u8>,
+    /// Print the full JSON response instead of one line.
+    #[arg(long)]
+    pub json: bool,
+}
+
+fn parse_units(value: &str) -> Result<UnitSystem> {
+    match value {
+        "metric" => Ok(UnitSystem::Metric),
+        "imperial" => Ok(UnitSystem::Imperial),
+        _ => bail!("expected metric or imperial"),
+    }
+}
+
+impl CalcArgs {
+    /// The request `POST /api/calculate` would receive.
+    pub fn to_request(&self) -> BmiRequest {
+        BmiRequest {
+            units: self.units,
+            weight_kg: self.weight_kg,
+            height_m: self.height_m,
+            height_cm: self.height_cm,
+            weight_lb: self.weight_lb,
+            height_ft: self.height_ft,
+            height_in: self.height_in,
+            precision: self.precision,
+            ..BmiRequest::default()
+        }
+    }
+}
+
+/// Runs `calc`, returning what to print; warnings go to stderr.
+///
+/// # Errors
+///
+/// Returns the [`BmiError`](crate::error::BmiError) the HTTP API would
+/// answer with if the measurements are missing, conflicting or out of
+/// `bounds`.
+pub fn calc(args: &CalcArgs, bounds: &validation::Bounds) -> Result<String> {
+    let response = process_bmi_request_with(&args.to_request(), bounds)?;
+    if args.json {
+        return Ok(serde_json::to_string(&response)?);
+    }
+    for warning in &response.warnings {
+        eprintln!("warning: {warning}");
+    }
+    Ok(bmi::text_line(&response, args.precision))
+}
+
+/// Options of `token`: metric or imperial measurements, not both.
+#[derive(Debug, Default, Args)]
+#[command(group(ArgGroup::new("weight").required(true).args(["weight_kg", "weight_lb"])))]
+pub struct TokenArgs {
+    /// Weight in kilograms, with `--height-m`.
+    #[arg(long, requires = "height_m", conflicts_with_all = ["weight_lb", "height_in"])]
+    pub weight_kg: Option<f64>,
+    /// Height in meters, with `--weight-kg`.
+    #[arg(long, requires = "weight_kg")]
+    pub height_m: Option<f64>,
+    /// Weight in pounds, with `--height-in`.
+    #[arg(long, requires = "height_in", conflicts_with = "height_m")]
+    pub weight_lb: Option<f64>,
+    /// Height in inches, with `--weight-lb`.
+    #[arg(long, requires = "weight_lb")]
+    pub height_in: Option<f64>,
+}
+
+impl TokenArgs {
+    /// The measurement to encode.
+    ///
+    /// # Errors
+    ///
+    /// Returns error unless exactly one unit system is complete; the parser
+    /// already ensures this for arguments from the command line.
+    pub fn measurement(&self) -> Result<measurement_token::Measurement> {
+        let (units, weight, height) = match (
+            self.weight_kg,
+            self.height_m,
+            self.weight_lb,
+            self.height_in,
+        ) {
+            (Some(weight), Some(height), None, None) => (UnitSystem::Metric, weight, height),
+            (None, None, Some(weight), Some(height)) => (UnitSystem::Imperial, weight, height),
+            _ => bail!("use either --weight-kg with --height-m, or --weight-lb with --height-in"),
+        };
+        Ok(measurement_token::Measurement {
+            units,
+            weight,
+            height,
+        })
+    }
+}
+
+/// Options of `export`.
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Backup file to write, e.g. `backup.jsonl.gz`.
+    #[arg(long, value_name = "PATH")]
+    pub output: PathBuf,
+}
+
+/// Options of `import`.
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// Backup file to read.
+    #[arg(long, value_name = "PATH")]
+    pub input: PathBuf,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("bmi_calculator").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn test_no_subcommand_serves() {
+        let cli = parse(&["--port", "8080", "--reuseport"]);
+        assert!(cli.command.is_none());
+
+        let mut config = AppConfig::default();
+        cli.serve.apply(&mut config);
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.bind, AppConfig::default().bind);
+        assert!(!config.mock.enabled);
+
+        parse(&["--mock"]).serve.apply(&mut config);
+        assert!(config.mock().unwrap().is_some());
+
+        let Some(Command::Serve(serve)) = parse(&["serve", "--bind", "127.0.0.1"]).command else {
+            panic!("expected serve");
+        };
+        assert_eq!(serve.bind, Some(IpAddr::from([127, 0, 0, 1])));
+    }
+
+    #[test]
+    fn test_calc_matches_the_api() {
+        let Some(Command::Calc(args)) =
+            parse(&["calc", "--weight-kg", "70", "--height-m", "1.75"]).command
+        else {
+            panic!("expected calc");
+        };
+        let bounds = validation::Bounds::default();
+        assert_eq!(calc(&args, &bounds).unwrap(), "BMI: 22.9 (Normal weight)");
+
+        let api = process_bmi_request_with(&args.to_request(), &bounds).unwrap();
+        let json = CalcArgs { json: true, ..args };
+        assert_eq!(
+            calc(&json, &bounds).unwrap(),
+            serde_json::to_string(&api).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_calc_rejects_what_the_api_rejects() {
+        let Some(Command::Calc(args)) = parse(&[
+            "calc",
+            "--units",
+            "imperial",
+            "--weight-lb",
+            "154",
+            "--height-m",
+            "1.75",
+        ])
+        .command
+        else {
+            panic!("expected calc");
+        };
+        let error = calc(&args, &validation::Bounds::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            process_bmi_request_with(&args.to_request(), &validation::Bounds::default())
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(Cli::try_parse_from(["bmi_calculator", "calc", "--units", "cubits"]).is_err());
+    }
+
+    #[test]
+    fn test_tools_are_subcommands() {
+        let Some(Command::Token(args)) =
+            parse(&["token", "--weight-kg=70", "--height-m", "1.75"]).command
+        else {
+            panic!("expected token");
+        };
+        let measurement = args.measurement().unwrap();
+        assert_eq!(measurement.units, UnitSystem::Metric);
+        assert_eq!((measurement.weight, measurement.height), (70.0, 1.75));
+        for line in [
+            &["token", "--weight-kg", "70", "--height-in", "69"][..],
+            &["token", "--weight-lb", "154"],
+            &["token"],
+        ] {
+            assert!(
+                Cli::try_parse_from(std::iter::once("bmi_calculator").chain(line.iter().copied()))
+                    .is_err(),
+                "{line:?}"
+            );
+        }
+
+        let Some(Command::Export(args)) = parse(&["export", "--output=backup.jsonl.gz"]).command
+        else {
+            panic!("expected export");
+        };
+        assert_eq!(args.output, PathBuf::from("backup.jsonl.gz"));
+        assert!(Cli::try_parse_from(["bmi_calculator", "export"]).is_err());
+        assert!(Cli::try_parse_from(["bmi_calculator", "import", "--output", "x"]).is_err());
+    }
+}
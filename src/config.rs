@@ -0,0 +1,820 @@
+//! Startup configuration from `bmi.toml` and the environment.
+//!
+//! Values are layered, later ones winning:
+//! 1. Built-in defaults
+//! 2. `bmi.toml` in the working directory, or the file named by `BMI_CONFIG`
+//! 3. Unprefixed variables predating this file: `PORT` (Heroku),
+//!    `CONN_*`, `RATE_LIMIT_*`, `MOCK_*`, `MAINTENANCE_*`, `ADMIN_TOKEN`,
+//!    `BMI_REUSEPORT` and the like
+//! 4. `BMI_*` variables, with `__` for nesting (`BMI_RATE_LIMIT__BURST=40`)
+//!
+//! See `bmi.example.toml` for every key. Anything invalid aborts startup.
+
+use std::{collections::HashSet, fmt, net::IpAddr, path::PathBuf, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use figment::{
+    providers::{Env, Format, Serialized, Toml},
+    Figment,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    connection, cors, health, limits, listener, maintenance, measurement_token, mock, rate_limit,
+    secrets, validation,
+};
+
+/// Config file read when `BMI_CONFIG` is unset; optional.
+const DEFAULT_PATH: &str = "bmi.toml";
+
+/// Unprefixed variables accepted alongside `BMI_*`, with their keys.
+const LEGACY_VARS: &[(&str, &str)] = &[
+    ("PORT", "port"),
+    ("CONN_MAX_TOTAL", "connections.max_total"),
+    ("CONN_MAX_PER_IP", "connections.max_per_ip"),
+    (
+        "CONN_HEADER_TIMEOUT_SECS",
+        "connections.header_timeout_secs",
+    ),
+    ("CONN_EXEMPT_CIDRS", "connections.exempt_cidrs"),
+    ("RATE_LIMIT_PER_SEC", "rate_limit.per_sec"),
+    ("RATE_LIMIT_BURST", "rate_limit.burst"),
+    ("RATE_LIMIT_TRUST_FORWARDED", "rate_limit.trust_forwarded"),
+    ("MEMORY_BUDGET_MB", "memory_budget_mb"),
+    ("READINESS_INFORMATIONAL", "readiness_informational"),
+    ("MOCK_MODE", "mock.enabled"),
+    ("MOCK_FIXTURES_DIR", "mock.fixtures_dir"),
+    ("MOCK_LATENCY_MS", "mock.latency_ms"),
+    ("MOCK_ERROR_RATE", "mock.error_rate"),
+    ("MAINTENANCE_MODE", "maintenance.enabled"),
+    ("MAINTENANCE_MESSAGE", "maintenance.message"),
+    ("BMI_TOKEN_MAX_AGE_SECS", "tokens.max_age_secs"),
+    ("BMI_REUSEPORT", "listener.reuseport"),
+    ("BMI_INHERIT_FD", "listener.inherit_fd"),
+    ("ADMIN_TOKEN", "secrets.admin_token"),
+    ("ADMIN_TOKEN_FILE", "secrets.admin_token_file"),
+    ("BMI_TOKEN_KEY", "secrets.token_key"),
+    ("BMI_TOKEN_KEY_FILE", "secrets.token_key_file"),
+    ("BMI_SESSION_KEY", "secrets.session_key"),
+    ("BMI_SESSION_KEY_FILE", "secrets.session_key_file"),
+];
+
+/// Free-text keys taken from their variables as-is; figment would otherwise
+/// read a key of `0123` as a number and a message of `true` as a bool.
+const VERBATIM_KEYS: &[&str] = &[
+    "maintenance.message",
+    "secrets.admin_token",
+    "secrets.token_key",
+    "secrets.session_key",
+];
+
+/// Everything `main` needs to start serving.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// TCP port to listen on.
+    pub port: u16,
+    /// Address to bind; `0.0.0.0` for all IPv4 interfaces.
+    pub bind: IpAddr,
+    /// `tracing` filter directives, e.g. `bmi_calculator=debug`.
+    pub log_filter: String,
+    /// Comma-separated CORS origins; `*` allows any.
+    pub allowed_origins: String,
+    /// Seconds in-flight requests get to finish after SIGTERM or Ctrl-C.
+    pub drain_timeout_secs: u64,
+    /// Seconds a `/status` snapshot is reused and may be cached by clients.
+    pub status_cache_secs: u64,
+    /// Directory of the page's HTML, CSS and JavaScript; the embedded copies
+    /// are served for whatever it lacks.
+    pub static_dir: PathBuf,
+    /// Seconds a `/api/ws` connection may stay silent before it is closed.
+    pub ws_idle_timeout_secs: u64,
+    /// Memory the process should stay within, in MiB; unset means unbounded.
+    pub memory_budget_mb: Option<u64>,
+    /// SQLite database recording calculations, e.g. `sqlite://bmi.db`; unset
+    /// disables history.
+    pub database_url: Option<String>,
+    /// Comma-separated readiness checks reported by `/readyz` but never
+    /// gating it.
+    pub readiness_informational: String,
+    /// Per-client limits on `/api/*`.
+    pub rate_limit: RateLimitConfig,
+    /// Accept-loop limits.
+    pub connections: ConnectionConfig,
+    /// Native HTTPS.
+    pub tls: TlsConfig,
+    /// Plausibility bounds on measurements.
+    pub validation: ValidationConfig,
+    /// Request body caps and handler deadline.
+    pub limits: LimitsConfig,
+    /// Listening socket handover between processes.
+    pub listener: ListenerConfig,
+    /// Canned API responses for frontend work.
+    pub mock: MockConfig,
+    /// Boot-time maintenance window.
+    pub maintenance: MaintenanceConfig,
+    /// QR measurement tokens.
+    pub tokens: TokensConfig,
+    /// Admin token and signing keys.
+    pub secrets: SecretsConfig,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            port: 3000,
+            bind: IpAddr::from([0, 0, 0, 0]),
+            log_filter: "bmi_calculator=info,tower_http=debug".to_string(),
+            allowed_origins: "*".to_string(),
+            drain_timeout_secs: 30,
+            status_cache_secs: 10,
+            static_dir: PathBuf::from("static"),
+            ws_idle_timeout_secs: 60,
+            memory_budget_mb: None,
+            database_url: None,
+            readiness_informational: String::new(),
+            rate_limit: RateLimitConfig::default(),
+            connections: ConnectionConfig::default(),
+            tls: TlsConfig::default(),
+            validation: ValidationConfig::default(),
+            limits: LimitsConfig::default(),
+            listener: ListenerConfig::default(),
+            mock: MockConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            tokens: TokensConfig::default(),
+            secrets: SecretsConfig::default(),
+        }
+    }
+}
+
+/// `[rate_limit]` section.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Sustained requests per second per client; `0` disables limiting.
+    pub per_sec: f64,
+    /// Requests a client may send at once.
+    pub burst: f64,
+    /// Key clients on the last `X-Forwarded-For` entry.
+    #[serde(deserialize_with = "flag")]
+    pub trust_forwarded: bool,
+    /// Send `X-RateLimit-*` headers on API responses.
+    pub headers: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_sec: rate_limit::DEFAULT_PER_SEC,
+            burst: rate_limit::DEFAULT_BURST,
+            trust_forwarded: false,
+            headers: true,
+        }
+    }
+}
+
+/// `[connections]` section.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionConfig {
+    /// Maximum concurrent connections overall.
+    pub max_total: usize,
+    /// Maximum concurrent connections from one IP.
+    pub max_per_ip: usize,
+    /// Seconds a client has to send its request headers.
+    pub header_timeout_secs: u64,
+    /// Comma-separated networks exempt from the per-IP cap.
+    pub exempt_cidrs: String,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        let limits = connection::Limits::default();
+        Self {
+            max_total: limits.max_total,
+            max_per_ip: limits.max_per_ip,
+            header_timeout_secs: limits.header_timeout.as_secs(),
+            exempt_cidrs: String::new(),
+        }
+    }
+}
+
+/// `[tls]` section; HTTPS is on when both paths are set.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// PEM certificate chain.
+    pub cert_path: Option<PathBuf>,
+    /// PEM private key.
+    pub key_path: Option<PathBuf>,
+    /// Plain HTTP port redirecting to HTTPS; none if unset.
+    pub redirect_port: Option<u16>,
+}
+
+/// `[validation]` section; both ends of each range are accepted.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    /// Lightest accepted weight in kilograms.
+    pub min_weight_kg: f64,
+    /// Heaviest accepted weight in kilograms.
+    pub max_weight_kg: f64,
+    /// Shortest accepted height in meters.
+    pub min_height_m: f64,
+    /// Tallest accepted height in meters.
+    pub max_height_m: f64,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        let bounds = validation::Bounds::default();
+        Self {
+            min_weight_kg: *bounds.weight_kg.start(),
+            max_weight_kg: *bounds.weight_kg.end(),
+            min_height_m: *bounds.height_m.start(),
+            max_height_m: *bounds.height_m.end(),
+        }
+    }
+}
+
+/// `[limits]` section.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Largest request body read on single-request routes, in bytes.
+    pub body_bytes: usize,
+    /// Largest request body read on `/api/calculate/batch`, in bytes.
+    pub batch_body_bytes: usize,
+    /// Seconds a handler gets to respond before a `503 timeout`.
+    pub handler_timeout_secs: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        let limits = limits::Limits::default();
+        Self {
+            body_bytes: limits.body_bytes,
+            batch_body_bytes: limits.batch_body_bytes,
+            handler_timeout_secs: limits.handler_timeout.as_secs(),
+        }
+    }
+}
+
+/// `[listener]` section; both handover modes are unix only and exclusive.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListenerConfig {
+    /// Bind with `SO_REUSEPORT` so old and new processes can overlap during
+    /// a deploy; `--reuseport` also sets it.
+    #[serde(deserialize_with = "flag")]
+    pub reuseport: bool,
+    /// Adopt the listening socket on this file descriptor; `--inherit-fd`
+    /// also sets it.
+    pub inherit_fd: Option<i32>,
+}
+
+/// `[mock]` section.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockConfig {
+    /// Answer API routes from fixtures instead of calculating; `--mock` also
+    /// sets it.
+    #[serde(deserialize_with = "flag")]
+    pub enabled: bool,
+    /// Directory of `<METHOD>_<path>.json` files overriding the built-in
+    /// fixtures.
+    pub fixtures_dir: Option<PathBuf>,
+    /// Delay before every mocked response, in milliseconds.
+    pub latency_ms: u64,
+    /// Fraction of mocked requests answered with a 500, from 0 to 1.
+    pub error_rate: f64,
+}
+
+/// `[maintenance]` section; the window can also be toggled at runtime.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// Start in maintenance.
+    #[serde(deserialize_with = "flag")]
+    pub enabled: bool,
+    /// Explanation shown to API clients and in the page banner.
+    pub message: String,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            message: maintenance::DEFAULT_MESSAGE.to_string(),
+        }
+    }
+}
+
+/// `[tokens]` section.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokensConfig {
+    /// Oldest measurement token accepted, in seconds.
+    pub max_age_secs: u64,
+}
+
+impl Default for TokensConfig {
+    fn default() -> Self {
+        Self {
+            max_age_secs: measurement_token::DEFAULT_MAX_AGE.as_secs(),
+        }
+    }
+}
+
+/// `[secrets]` section; each secret is given inline or as a file (Docker and
+/// Kubernetes secrets), never both.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecretsConfig {
+    /// Bearer token for `/api/admin/*`; the routes are disabled when unset.
+    pub admin_token: Option<String>,
+    /// File holding `admin_token`.
+    pub admin_token_file: Option<PathBuf>,
+    /// HMAC key for measurement tokens; when set, tokens must be signed.
+    pub token_key: Option<String>,
+    /// File holding `token_key`.
+    pub token_key_file: Option<PathBuf>,
+    /// HMAC key for history session cookies; random per start when unset.
+    pub session_key: Option<String>,
+    /// File holding `session_key`.
+    pub session_key_file: Option<PathBuf>,
+}
+
+/// Shows which secrets are set without their values.
+impl fmt::Debug for SecretsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |value: &Option<String>| value.as_ref().map(|_| "<redacted>");
+        f.debug_struct("SecretsConfig")
+            .field("admin_token", &redacted(&self.admin_token))
+            .field("admin_token_file", &self.admin_token_file)
+            .field("token_key", &redacted(&self.token_key))
+            .field("token_key_file", &self.token_key_file)
+            .field("session_key", &redacted(&self.session_key))
+            .field("session_key_file", &self.session_key_file)
+            .finish()
+    }
+}
+
+/// Reads a bool, also accepting the `1` and `0` older variables documented.
+fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Number(u8),
+    }
+    match Flag::deserialize(deserializer)? {
+        Flag::Bool(value) => Ok(value),
+        Flag::Number(0) => Ok(false),
+        Flag::Number(1) => Ok(true),
+        Flag::Number(_) => Err(serde::de::Error::custom("expected true, false, 1 or 0")),
+    }
+}
+
+impl AppConfig {
+    /// Loads and validates the configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file is unreadable or malformed, `BMI_CONFIG`
+    /// names a missing file, or a value has the wrong type or is invalid.
+    pub fn load() -> Result<Self> {
+        let path = match std::env::var_os("BMI_CONFIG") {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                if !path.is_file() {
+                    bail!("BMI_CONFIG: no such file {}", path.display());
+                }
+                path
+            }
+            None => PathBuf::from(DEFAULT_PATH),
+        };
+
+        let mut figment = Figment::from(Serialized::defaults(Self::default()));
+        if path.is_file() {
+            figment = figment.merge(Toml::file_exact(&path));
+        }
+        let legacy = Env::raw().filter_map(|name| {
+            LEGACY_VARS
+                .iter()
+                .find(|(var, _)| name == *var)
+                .map(|(_, key)| (*key).into())
+        });
+        figment = figment
+            .merge(legacy)
+            .merge(Env::prefixed("BMI_").split("__"));
+        for key in VERBATIM_KEYS {
+            let legacy = LEGACY_VARS
+                .iter()
+                .find(|(_, legacy_key)| legacy_key == key)
+                .map(|(var, _)| (*var).to_string());
+            let prefixed = format!("BMI_{}", key.replace('.', "__").to_uppercase());
+            for var in legacy.into_iter().chain([prefixed]) {
+                if let Ok(value) = std::env::var(&var) {
+                    figment = figment.merge(Serialized::default(key, value));
+                }
+            }
+        }
+        let config: Self = figment.extract().context("invalid configuration")?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks values beyond their types.
+    fn validate(&self) -> Result<()> {
+        EnvFilter::try_new(&self.log_filter)
+            .with_context(|| format!("log_filter: invalid directives {:?}", self.log_filter))?;
+        cors::Origins::parse(&self.allowed_origins)?;
+        self.connection_limits()?;
+        self.rate_limiter()?;
+        self.validation_bounds()?;
+        self.request_limits()?;
+        listener::ListenerMode::from_config(self)?;
+        self.mock()?;
+        self.token_policy()?;
+        if self.ws_idle_timeout_secs == 0 {
+            bail!("ws_idle_timeout_secs must be at least 1");
+        }
+        let tls = &self.tls;
+        match (&tls.cert_path, &tls.key_path) {
+            (Some(_), None) => bail!("tls.cert_path is set without tls.key_path"),
+            (None, Some(_)) => bail!("tls.key_path is set without tls.cert_path"),
+            (None, None) if tls.redirect_port.is_some() => {
+                bail!("tls.redirect_port needs tls.cert_path and tls.key_path")
+            }
+            _ => {}
+        }
+        if tls.redirect_port == Some(self.port) {
+            bail!("tls.redirect_port must differ from port");
+        }
+        Ok(())
+    }
+
+    /// TLS acceptor for the main port; `None` serves plain HTTP.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the certificate or key cannot be loaded.
+    pub fn tls_acceptor(&self) -> Result<Option<tokio_rustls::TlsAcceptor>> {
+        match (&self.tls.cert_path, &self.tls.key_path) {
+            (Some(cert), Some(key)) => crate::tls::acceptor(cert, key).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Accept-loop limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if an exempt CIDR is malformed.
+    pub fn connection_limits(&self) -> Result<connection::Limits> {
+        let section = &self.connections;
+        Ok(connection::Limits {
+            max_total: section.max_total,
+            max_per_ip: section.max_per_ip,
+            header_timeout: std::time::Duration::from_secs(section.header_timeout_secs),
+            exempt: section
+                .exempt_cidrs
+                .split(',')
+                .map(str::trim)
+                .filter(|cidr| !cidr.is_empty())
+                .map(connection::Cidr::parse)
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// API rate limiter; `None` when `per_sec` is zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the rate is negative or the burst is below one.
+    pub fn rate_limiter(&self) -> Result<Option<rate_limit::RateLimiter>> {
+        let section = &self.rate_limit;
+        if section.per_sec == 0.0 {
+            return Ok(None);
+        }
+        if section.per_sec.is_nan() || section.per_sec < 0.0 {
+            bail!("rate_limit.per_sec must be zero or positive");
+        }
+        if section.burst.is_nan() || section.burst < 1.0 {
+            bail!("rate_limit.burst must be at least 1");
+        }
+        Ok(Some(
+            rate_limit::RateLimiter::new(section.per_sec, section.burst, section.trust_forwarded)
+                .with_headers(section.headers),
+        ))
+    }
+
+    /// Measurement bounds.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a bound is not a positive number or a minimum is not
+    /// below its maximum.
+    pub fn validation_bounds(&self) -> Result<validation::Bounds> {
+        let section = &self.validation;
+        for (name, min, max) in [
+            ("weight_kg", section.min_weight_kg, section.max_weight_kg),
+            ("height_m", section.min_height_m, section.max_height_m),
+        ] {
+            if !(min.is_finite() && max.is_finite() && min > 0.0) {
+                bail!("validation.min_{name} and max_{name} must be positive numbers");
+            }
+            if min >= max {
+                bail!("validation.min_{name} must be below validation.max_{name}");
+            }
+        }
+        Ok(validation::Bounds {
+            weight_kg: section.min_weight_kg..=section.max_weight_kg,
+            height_m: section.min_height_m..=section.max_height_m,
+        })
+    }
+
+    /// Body caps and handler deadline from `[limits]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a value is zero.
+    pub fn request_limits(&self) -> Result<limits::Limits> {
+        let section = &self.limits;
+        if section.body_bytes == 0 || section.batch_body_bytes == 0 {
+            bail!("limits.body_bytes and limits.batch_body_bytes must be at least 1");
+        }
+        if section.handler_timeout_secs == 0 {
+            bail!("limits.handler_timeout_secs must be at least 1");
+        }
+        Ok(limits::Limits {
+            body_bytes: section.body_bytes,
+            batch_body_bytes: section.batch_body_bytes,
+            handler_timeout: Duration::from_secs(section.handler_timeout_secs),
+        })
+    }
+
+    /// Readiness state with the `readiness_informational` checks.
+    pub fn health(&self) -> health::Health {
+        let informational: HashSet<String> = self
+            .readiness_informational
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect();
+        health::Health::new(informational)
+    }
+
+    /// Maintenance switch, started in maintenance if `[maintenance]` says so.
+    pub fn maintenance(&self) -> maintenance::Maintenance {
+        let section = &self.maintenance;
+        maintenance::Maintenance::new(section.enabled.then(|| maintenance::Window {
+            message: section.message.clone(),
+            estimated_end: None,
+        }))
+    }
+
+    /// Mock responder; `None` unless `[mock]` is enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the error rate is outside 0 to 1 or the fixtures
+    /// directory is invalid.
+    pub fn mock(&self) -> Result<Option<mock::Mock>> {
+        let section = &self.mock;
+        if !(0.0..=1.0).contains(&section.error_rate) {
+            bail!("mock.error_rate must be between 0 and 1");
+        }
+        if !section.enabled {
+            return Ok(None);
+        }
+        let mut mock = mock::Mock::new()
+            .with_latency(Duration::from_millis(section.latency_ms))
+            .with_error_rate(section.error_rate);
+        if let Some(dir) = &section.fixtures_dir {
+            mock = mock.with_fixtures_dir(dir)?;
+        }
+        Ok(Some(mock))
+    }
+
+    /// Measurement token policy, keyed by `secrets.token_key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the key cannot be resolved or the max age is zero.
+    pub fn token_policy(&self) -> Result<measurement_token::TokenPolicy> {
+        if self.tokens.max_age_secs == 0 {
+            bail!("tokens.max_age_secs must be at least 1");
+        }
+        let key = self.secrets()?.token_key.value;
+        Ok(measurement_token::TokenPolicy::new(
+            key.map(String::into_bytes),
+            Duration::from_secs(self.tokens.max_age_secs),
+        ))
+    }
+
+    /// Secret values, with any `*_file` read.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a secret is set both inline and as a file, or its
+    /// file cannot be read.
+    pub fn secrets(&self) -> Result<secrets::Secrets> {
+        let section = &self.secrets;
+        Ok(secrets::Secrets {
+            admin_token: secrets::resolve(
+                "admin_token",
+                section.admin_token.as_deref(),
+                section.admin_token_file.as_deref(),
+            )?,
+            token_key: secrets::resolve(
+                "token_key",
+                section.token_key.as_deref(),
+                section.token_key_file.as_deref(),
+            )?,
+            session_key: secrets::resolve(
+                "session_key",
+                section.session_key.as_deref(),
+                section.session_key_file.as_deref(),
+            )?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use figment::Jail;
+
+    /// Runs `f` with a scratch directory and environment.
+    // `Jail` dictates the closure's `figment::Error` return type
+    #[allow(clippy::result_large_err)]
+    fn jail(f: impl FnOnce(&mut Jail) -> Result<()>) {
+        Jail::expect_with(|jail| f(jail).map_err(|error| format!("{error:#}").into()));
+    }
+
+    #[test]
+    fn test_defaults_without_file_or_env() {
+        jail(|_| {
+            assert_eq!(AppConfig::load()?, AppConfig::default());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_env_over_file_over_defaults() {
+        jail(|jail| {
+            jail.create_file(
+                "bmi.toml",
+                r#"
+                port = 8080
+                bind = "127.0.0.1"
+                allowed_origins = "https://clinic.example"
+
+                [rate_limit]
+                burst = 5
+                "#,
+            )?;
+            jail.set_env("PORT", "9000");
+            jail.set_env("RATE_LIMIT_BURST", "7");
+            jail.set_env("BMI_BIND", "::1");
+            jail.set_env("BMI_RATE_LIMIT__PER_SEC", "2");
+            jail.set_env("BMI_CONNECTIONS__EXEMPT_CIDRS", "10.0.0.0/8");
+
+            let config = AppConfig::load()?;
+            assert_eq!(config.port, 9000, "legacy env over file");
+            assert_eq!(config.bind, "::1".parse::<IpAddr>()?, "BMI_ env over file");
+            assert_eq!(config.allowed_origins, "https://clinic.example");
+            assert_eq!(config.rate_limit.per_sec, 2.0);
+            assert_eq!(config.rate_limit.burst, 7.0);
+            assert_eq!(config.log_filter, AppConfig::default().log_filter);
+            assert_eq!(config.connection_limits()?.exempt.len(), 1);
+
+            jail.set_env("BMI_PORT", "9001");
+            assert_eq!(AppConfig::load()?.port, 9001, "BMI_ over legacy env");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_service_sections_from_file_and_env() {
+        jail(|jail| {
+            jail.create_file(
+                "bmi.toml",
+                r#"
+                [mock]
+                latency_ms = 300
+
+                [secrets]
+                admin_token = "from-file"
+                "#,
+            )?;
+            jail.create_file("session.key", "s3ssion\n")?;
+            jail.set_env("MOCK_MODE", "1");
+            jail.set_env("MOCK_ERROR_RATE", "0.1");
+            jail.set_env("MAINTENANCE_MODE", "true");
+            jail.set_env("MAINTENANCE_MESSAGE", "42");
+            jail.set_env("READINESS_INFORMATIONAL", "database, disk");
+            jail.set_env("BMI_TOKEN_KEY", "0123");
+            jail.set_env("BMI_TOKEN_MAX_AGE_SECS", "600");
+            jail.set_env("BMI_SECRETS__SESSION_KEY_FILE", "session.key");
+            jail.set_env("BMI_INHERIT_FD", "3");
+
+            let config = AppConfig::load()?;
+            assert!(config.mock.enabled, "MOCK_MODE=1");
+            assert_eq!((config.mock.latency_ms, config.mock.error_rate), (300, 0.1));
+            assert!(config.mock()?.is_some());
+            assert_eq!(config.maintenance().current().unwrap().message, "42");
+            assert_eq!(config.readiness_informational, "database, disk");
+            assert_eq!(config.tokens.max_age_secs, 600);
+            assert_eq!(
+                listener::ListenerMode::from_config(&config)?,
+                listener::ListenerMode::Inherited(3)
+            );
+            assert!(config.token_policy()?.requires_signature());
+
+            let secrets = config.secrets()?;
+            assert_eq!(secrets.admin_token.value.as_deref(), Some("from-file"));
+            assert_eq!(
+                secrets.token_key.value.as_deref(),
+                Some("0123"),
+                "kept verbatim"
+            );
+            assert_eq!(secrets.session_key.value.as_deref(), Some("s3ssion"));
+            assert_eq!(secrets.session_key.source, secrets::Source::File);
+            assert!(
+                !format!("{config:?}").contains("from-file"),
+                "secrets redacted"
+            );
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_example_file_matches_defaults() {
+        let example: AppConfig = Figment::from(Toml::string(include_str!("../bmi.example.toml")))
+            .extract()
+            .unwrap();
+        assert_eq!(example, AppConfig::default());
+    }
+
+    #[test]
+    fn test_config_path_override() {
+        jail(|jail| {
+            jail.create_file("staging.toml", "log_filter = \"bmi_calculator=debug\"")?;
+            jail.set_env("BMI_CONFIG", "staging.toml");
+            assert_eq!(AppConfig::load()?.log_filter, "bmi_calculator=debug");
+
+            jail.set_env("BMI_CONFIG", "missing.toml");
+            let error = AppConfig::load().unwrap_err();
+            assert!(error.to_string().contains("missing.toml"), "{error}");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_invalid_config_aborts() {
+        for (file, env) in [
+            ("port = \"web\"", None),
+            ("port = 70000", None),
+            ("port = [", None),
+            ("", Some(("BMI_PORT", "abc"))),
+            ("", Some(("BMI_LOG_FILTER", "bmi_calculator=loud"))),
+            ("", Some(("BMI_ALLOWED_ORIGINS", "clinic.example"))),
+            ("", Some(("CONN_EXEMPT_CIDRS", "10.0.0.0/99"))),
+            ("[rate_limit]\nburst = 0", None),
+            ("[validation]\nmin_weight_kg = 0", None),
+            ("", Some(("BMI_VALIDATION__MAX_HEIGHT_M", "0.2"))),
+            ("ws_idle_timeout_secs = 0", None),
+            ("[limits]\nbody_bytes = 0", None),
+            ("", Some(("BMI_LIMITS__HANDLER_TIMEOUT_SECS", "0"))),
+            ("[tls]\ncert_path = \"cert.pem\"", None),
+            ("[tls]\nredirect_port = 8080", None),
+            ("", Some(("BMI_TLS__KEY_PATH", "key.pem"))),
+            ("", Some(("MOCK_MODE", "yes"))),
+            ("[mock]\nerror_rate = 2", None),
+            ("[mock]\nenabled = true\nfixtures_dir = \"missing\"", None),
+            ("", Some(("BMI_TOKEN_MAX_AGE_SECS", "0"))),
+            ("", Some(("BMI_INHERIT_FD", "three"))),
+            ("[listener]\ninherit_fd = -1", None),
+            ("[listener]\ninherit_fd = 3", Some(("BMI_REUSEPORT", "1"))),
+            ("", Some(("ADMIN_TOKEN_FILE", "missing.txt"))),
+            (
+                "[secrets]\ntoken_key = \"a\"",
+                Some(("BMI_TOKEN_KEY_FILE", "bmi.toml")),
+            ),
+        ] {
+            jail(|jail| {
+                jail.create_file("bmi.toml", file)?;
+                if let Some((name, value)) = env {
+                    jail.set_env(name, value);
+                }
+                assert!(AppConfig::load().is_err(), "{file:?} {env:?}");
+                Ok(())
+            });
+        }
+    }
+}
@@ -0,0 +1,655 @@
+//! Connection-level guards in the accept loop.
+//!
+//! Replaces `axum::serve` with a loop that enforces:
+//! - `CONN_MAX_TOTAL`: concurrent connections across all clients (default 1024)
+//! - `CONN_MAX_PER_IP`: concurrent connections per client IP (default 64)
+//! - `CONN_HEADER_TIMEOUT_SECS`: time allowed for a client to send its
+//!   request headers, including the first byte (default 10)
+//! - `CONN_EXEMPT_CIDRS`: comma-separated networks (e.g. health checkers)
+//!   exempt from the per-IP cap
+//!
+//! The same limits can be set under `[connections]` in `bmi.toml`, see
+//! [`crate::config`].
+//!
+//! Rejected connections are closed immediately. Counters are served at
+//! `GET /api/admin/connections`.
+//!
+//! When the shutdown future passed to [`serve`] completes, the loop stops
+//! accepting, asks open connections to finish their current request, and
+//! returns once they have or the drain timeout expires.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    middleware::AddExtension,
+    response::{IntoResponse, Response},
+    Extension, Json, Router,
+};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::{
+        conn::auto,
+        graceful::{GracefulShutdown, Watcher},
+    },
+    service::TowerToHyperService,
+};
+use serde::Serialize;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+};
+use tokio_rustls::TlsAcceptor;
+use tower::Layer;
+use tracing::{event, Level};
+
+use crate::{admin, telemetry, AppState};
+
+/// Connection limits applied by [`serve`].
+#[derive(Clone, Debug)]
+pub struct Limits {
+    /// Maximum concurrent connections overall.
+    pub max_total: usize,
+    /// Maximum concurrent connections from one IP.
+    pub max_per_ip: usize,
+    /// Deadline for receiving complete request headers.
+    pub header_timeout: Duration,
+    /// Networks exempt from the per-IP cap.
+    pub exempt: Vec<Cidr>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_total: 1024,
+            max_per_ip: 64,
+            header_timeout: Duration::from_secs(10),
+            exempt: Vec::new(),
+        }
+    }
+}
+
+/// An IP network such as `10.0.0.0/8` or `fd00::/8`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parses `address/prefix`; a bare address is a single-host network.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the address or prefix length is invalid.
+    pub fn parse(text: &str) -> Result<Self> {
+        let (address, prefix) = text.split_once('/').unwrap_or((text, ""));
+        let network: IpAddr = address
+            .parse()
+            .with_context(|| format!("invalid CIDR address {text:?}"))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = if prefix.is_empty() {
+            max
+        } else {
+            prefix
+                .parse()
+                .with_context(|| format!("invalid CIDR prefix {text:?}"))?
+        };
+        if prefix > max {
+            bail!("CIDR prefix out of range in {text:?}");
+        }
+        Ok(Self { network, prefix })
+    }
+
+    /// Returns whether `ip` falls inside this network.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Counters for accepted, rejected and timed-out connections.
+#[derive(Debug, Default)]
+struct Counters {
+    accepted: AtomicU64,
+    rejected_total_cap: AtomicU64,
+    rejected_per_ip_cap: AtomicU64,
+    timed_out: AtomicU64,
+}
+
+/// Open connections, overall and per IP.
+#[derive(Debug, Default)]
+struct Open {
+    total: usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+/// Limits, live connection tracking and counters shared with the admin API.
+#[derive(Debug, Default)]
+pub struct Connections {
+    limits: Limits,
+    open: Mutex<Open>,
+    counters: Counters,
+}
+
+/// Why a connection was refused.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Rejection {
+    TotalCap,
+    PerIpCap,
+}
+
+/// Slot held by a connection for its lifetime; released on drop.
+pub(crate) struct Permit {
+    connections: Arc<Connections>,
+    ip: IpAddr,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut open = self.connections.lock();
+        open.total = open.total.saturating_sub(1);
+        if let Some(count) = open.per_ip.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                open.per_ip.remove(&self.ip);
+            }
+        }
+    }
+}
+
+impl Connections {
+    /// Creates a tracker enforcing `limits`.
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the configured limits.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Returns a snapshot of the counters.
+    pub fn stats(&self) -> ConnectionStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ConnectionStats {
+            open: self.lock().total,
+            accepted: load(&self.counters.accepted),
+            rejected_total_cap: load(&self.counters.rejected_total_cap),
+            rejected_per_ip_cap: load(&self.counters.rejected_per_ip_cap),
+            timed_out: load(&self.counters.timed_out),
+        }
+    }
+
+    /// Admits a connection from `ip` if the caps allow it.
+    pub(crate) fn admit(self: &Arc<Self>, ip: IpAddr) -> Result<Permit, Rejection> {
+        let ip = ip.to_canonical();
+        let exempt = self.limits.exempt.iter().any(|cidr| cidr.contains(ip));
+        let mut open = self.lock();
+
+        let rejection = if open.total >= self.limits.max_total {
+            self.counters
+                .rejected_total_cap
+                .fetch_add(1, Ordering::Relaxed);
+            telemetry::connection_rejected("total_cap");
+            Some(Rejection::TotalCap)
+        } else if !exempt && open.per_ip.get(&ip).copied().unwrap_or(0) >= self.limits.max_per_ip {
+            self.counters
+                .rejected_per_ip_cap
+                .fetch_add(1, Ordering::Relaxed);
+            telemetry::connection_rejected("per_ip_cap");
+            Some(Rejection::PerIpCap)
+        } else {
+            None
+        };
+        if let Some(rejection) = rejection {
+            return Err(rejection);
+        }
+
+        open.total += 1;
+        *open.per_ip.entry(ip).or_default() += 1;
+        self.counters.accepted.fetch_add(1, Ordering::Relaxed);
+        Ok(Permit {
+            connections: Arc::clone(self),
+            ip,
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Open> {
+        self.open
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Response body for `GET /api/admin/connections`.
+#[derive(Debug, Serialize)]
+pub struct ConnectionStats {
+    /// Connections currently open.
+    pub open: usize,
+    /// Connections admitted since startup.
+    pub accepted: u64,
+    /// Connections refused because the total cap was reached.
+    pub rejected_total_cap: u64,
+    /// Connections refused because the client IP was at its cap.
+    pub rejected_per_ip_cap: u64,
+    /// Connections closed for not sending headers in time.
+    pub timed_out: u64,
+}
+
+/// Serves connection counters to operators.
+///
+/// # Errors
+///
+/// Returns HTTP 401/403 if the admin token is missing or not configured.
+pub async fn stats_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(rejection) = admin::authorize(state.admin_token.as_deref(), &headers) {
+        return rejection.into_response();
+    }
+    Json(state.connections.stats()).into_response()
+}
+
+/// Service handed to hyper for one connection.
+type ConnectionService = TowerToHyperService<AddExtension<Router, ConnectInfo<SocketAddr>>>;
+
+/// Accepts connections on `listener` and serves `app` within the limits,
+/// over TLS when `tls` is set.
+///
+/// Runs until `shutdown` completes, then drains open connections for at most
+/// `drain_timeout`.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    connections: Arc<Connections>,
+    shutdown: impl Future<Output = ()>,
+    drain_timeout: Duration,
+    tls: Option<TlsAcceptor>,
+) {
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .header_read_timeout(connections.limits.header_timeout);
+    let builder = Arc::new(builder);
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            () = &mut shutdown => break,
+        };
+        let (stream, peer) = match accepted {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                // Typically EMFILE; back off instead of spinning.
+                event!(
+                    name: "app.connection.accept_failed",
+                    Level::ERROR,
+                    error = %error,
+                    "Failed to accept connection: {{error}}"
+                );
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let permit = match connections.admit(peer.ip()) {
+            Ok(permit) => permit,
+            Err(rejection) => {
+                event!(
+                    name: "app.connection.rejected",
+                    Level::WARN,
+                    peer = %peer.ip(),
+                    reason = ?rejection,
+                    "Rejected connection from {{peer}}: {{reason}}"
+                );
+                continue;
+            }
+        };
+
+        let builder = Arc::clone(&builder);
+        let connections = Arc::clone(&connections);
+        // Exposes the peer address to middleware such as the rate limiter
+        let service = TowerToHyperService::new(Extension(ConnectInfo(peer)).layer(app.clone()));
+        let watcher = graceful.watcher();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let timeout = connections.limits.header_timeout;
+
+            // Protocol detection waits for the first bytes, before hyper's
+            // header timer starts, so idle sockets are bounded here.
+            let mut first = [0u8; 1];
+            if !matches!(
+                tokio::time::timeout(timeout, stream.peek(&mut first)).await,
+                Ok(Ok(_))
+            ) {
+                connections.timed_out(peer.ip());
+                return;
+            }
+
+            let Some(acceptor) = tls else {
+                drive(&builder, stream, service, watcher, &connections, peer).await;
+                return;
+            };
+            match tokio::time::timeout(timeout, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    drive(&builder, stream, service, watcher, &connections, peer).await;
+                }
+                Ok(Err(error)) => event!(
+                    name: "app.tls.handshake_failed",
+                    Level::DEBUG,
+                    peer = %peer.ip(),
+                    error = %error,
+                    "TLS handshake with {{peer}} failed: {{error}}"
+                ),
+                Err(_) => connections.timed_out(peer.ip()),
+            }
+        });
+    }
+
+    drop(listener);
+    let open = graceful.count();
+    event!(
+        name: "app.shutdown.draining",
+        Level::INFO,
+        open,
+        drain_timeout_secs = drain_timeout.as_secs_f64(),
+        "Stopped accepting; draining {{open}} connections"
+    );
+    match tokio::time::timeout(drain_timeout, graceful.shutdown()).await {
+        Ok(()) => event!(
+            name: "app.shutdown.drained",
+            Level::INFO,
+            "All connections closed"
+        ),
+        Err(_) => event!(
+            name: "app.shutdown.forced",
+            Level::WARN,
+            open = connections.stats().open,
+            "Drain timeout expired with {{open}} connections still open"
+        ),
+    }
+}
+
+/// Serves HTTP on an accepted, possibly decrypted, stream until it closes.
+async fn drive<I>(
+    builder: &auto::Builder<TokioExecutor>,
+    stream: I,
+    service: ConnectionService,
+    watcher: Watcher,
+    connections: &Connections,
+    peer: SocketAddr,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+    if let Err(error) = watcher.watch(connection).await {
+        let timed_out = error
+            .downcast_ref::<hyper::Error>()
+            .is_some_and(hyper::Error::is_timeout);
+        if timed_out {
+            connections.timed_out(peer.ip());
+        }
+    }
+}
+
+impl Connections {
+    /// Counts and logs a connection closed before its headers arrived.
+    pub(crate) fn timed_out(&self, peer: IpAddr) {
+        self.counters.timed_out.fetch_add(1, Ordering::Relaxed);
+        telemetry::connection_timed_out();
+        event!(
+            name: "app.connection.timed_out",
+            Level::INFO,
+            peer = %peer,
+            "Closed connection from {{peer}}: headers not received in time"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::{oneshot, Notify},
+        task::JoinHandle,
+    };
+
+    async fn start(limits: Limits) -> (SocketAddr, Arc<Connections>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(Connections::new(limits));
+        let app = crate::build_router(AppState::default());
+        tokio::spawn(serve(
+            listener,
+            app,
+            Arc::clone(&connections),
+            std::future::pending(),
+            Duration::ZERO,
+            None,
+        ));
+        (addr, connections)
+    }
+
+    async fn get(addr: SocketAddr) -> std::io::Result<String> {
+        get_path(addr, "/api/changelog").await
+    }
+
+    async fn get_path(addr: SocketAddr, path: &str) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        let request =
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    /// Serves a `/slow` route taking `delay` until the returned sender fires.
+    async fn start_slow(
+        delay: Duration,
+        drain_timeout: Duration,
+    ) -> (SocketAddr, Arc<Notify>, oneshot::Sender<()>, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let started = Arc::new(Notify::new());
+        let app = Router::new().route(
+            "/slow",
+            axum::routing::get({
+                let started = Arc::clone(&started);
+                move || async move {
+                    started.notify_one();
+                    tokio::time::sleep(delay).await;
+                    "done"
+                }
+            }),
+        );
+        let (trigger, shutdown) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            app,
+            Arc::new(Connections::new(Limits::default())),
+            async {
+                let _ = shutdown.await;
+            },
+            drain_timeout,
+            None,
+        ));
+        (addr, started, trigger, server)
+    }
+
+    /// Opens a connection and waits until the server has admitted it.
+    async fn idle(addr: SocketAddr, connections: &Connections) -> TcpStream {
+        let before = connections.stats().accepted + connections.stats().rejected_per_ip_cap;
+        let stream = TcpStream::connect(addr).await.unwrap();
+        while connections.stats().accepted + connections.stats().rejected_per_ip_cap == before {
+            tokio::task::yield_now().await;
+        }
+        stream
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let v4 = Cidr::parse("10.1.0.0/16").unwrap();
+        assert!(v4.contains("10.1.200.3".parse().unwrap()));
+        assert!(v4.contains("::ffff:10.1.0.1".parse().unwrap()));
+        assert!(!v4.contains("10.2.0.1".parse().unwrap()));
+
+        let host = Cidr::parse("127.0.0.1").unwrap();
+        assert!(host.contains("127.0.0.1".parse().unwrap()));
+        assert!(!host.contains("127.0.0.2".parse().unwrap()));
+
+        assert!(Cidr::parse("0.0.0.0/0")
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+        assert!(Cidr::parse("fd00::/8")
+            .unwrap()
+            .contains("fd12::1".parse().unwrap()));
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("banana/8").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_per_ip_cap_rejects_extra_idle_sockets() {
+        let (addr, connections) = start(Limits {
+            max_per_ip: 2,
+            ..Limits::default()
+        })
+        .await;
+
+        let _first = idle(addr, &connections).await;
+        let _second = idle(addr, &connections).await;
+        let mut third = idle(addr, &connections).await;
+
+        let mut buf = [0u8; 1];
+        assert_eq!(third.read(&mut buf).await.unwrap_or(0), 0);
+        assert_eq!(connections.stats().rejected_per_ip_cap, 1);
+    }
+
+    #[tokio::test]
+    async fn test_exempt_cidr_bypasses_per_ip_cap() {
+        let (addr, connections) = start(Limits {
+            max_per_ip: 1,
+            exempt: vec![Cidr::parse("127.0.0.0/8").unwrap()],
+            ..Limits::default()
+        })
+        .await;
+
+        let _idle = idle(addr, &connections).await;
+        let response = get(addr).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert_eq!(connections.stats().rejected_per_ip_cap, 0);
+    }
+
+    #[tokio::test]
+    async fn test_total_cap_frees_slots_when_connections_close() {
+        let (addr, connections) = start(Limits {
+            max_total: 1,
+            ..Limits::default()
+        })
+        .await;
+
+        let held = idle(addr, &connections).await;
+        let mut refused = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 1];
+        assert_eq!(refused.read(&mut buf).await.unwrap_or(0), 0);
+        assert_eq!(connections.stats().rejected_total_cap, 1);
+
+        drop(held);
+        while connections.stats().open > 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(get(addr).await.unwrap().starts_with("HTTP/1.1 200"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_clients_are_disconnected() {
+        let (addr, connections) = start(Limits {
+            header_timeout: Duration::from_millis(100),
+            ..Limits::default()
+        })
+        .await;
+
+        let mut silent = TcpStream::connect(addr).await.unwrap();
+        let mut partial = TcpStream::connect(addr).await.unwrap();
+        partial
+            .write_all(b"GET / HTTP/1.1\r\nHost: loc")
+            .await
+            .unwrap();
+
+        let mut buf = Vec::new();
+        silent.read_to_end(&mut buf).await.unwrap();
+        partial.read_to_end(&mut buf).await.unwrap();
+        while connections.stats().timed_out < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(get(addr).await.unwrap().starts_with("HTTP/1.1 200"));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_lets_in_flight_requests_finish() {
+        let (addr, started, trigger, server) =
+            start_slow(Duration::from_millis(300), Duration::from_secs(5)).await;
+
+        let request = tokio::spawn(get_path(addr, "/slow"));
+        started.notified().await;
+        trigger.send(()).unwrap();
+
+        let response = request.await.unwrap().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("done"), "{response}");
+        tokio::time::timeout(Duration::from_secs(1), server)
+            .await
+            .expect("server drained")
+            .unwrap();
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_drain_timeout_forces_exit() {
+        let (addr, started, trigger, server) =
+            start_slow(Duration::from_secs(60), Duration::from_millis(100)).await;
+
+        let _request = tokio::spawn(get_path(addr, "/slow"));
+        started.notified().await;
+        trigger.send(()).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(2), server)
+            .await
+            .expect("server gave up draining")
+            .unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+//! Cross-origin policy for the page and API.
+//!
+//! `BMI_ALLOWED_ORIGINS` lists the origins browsers may call us from,
+//! comma-separated (e.g. `https://clinic.example,https://kiosk.example`).
+//! Those origins get `GET` and `POST` with a `Content-Type` header; any other
+//! origin gets no CORS headers. Unset or `*` keeps the permissive policy for
+//! demos. Also settable as `allowed_origins` in `bmi.toml`.
+
+use anyhow::{bail, Context, Result};
+use axum::http::{header::CONTENT_TYPE, HeaderValue, Method, Uri};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Origins allowed to make cross-origin requests.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Origins {
+    /// Any origin, any method, any header.
+    #[default]
+    Any,
+    /// Only these exact origins.
+    List(Vec<HeaderValue>),
+}
+
+impl Origins {
+    /// Parses a comma-separated origin list; empty or `*` means any.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if an entry is not `scheme://host[:port]`.
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if value.is_empty() || value == "*" {
+            return Ok(Self::Any);
+        }
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .map(|origin| {
+                parse_origin(origin)
+                    .with_context(|| format!("allowed_origins: invalid origin {origin:?}"))
+            })
+            .collect::<Result<_>>()
+            .map(Self::List)
+    }
+
+    /// Builds the layer enforcing this policy.
+    pub fn layer(&self) -> CorsLayer {
+        match self {
+            Self::Any => CorsLayer::permissive(),
+            Self::List(origins) => CorsLayer::new()
+                .allow_origin(AllowOrigin::list(origins.iter().cloned()))
+                .allow_methods([Method::GET, Method::POST])
+                .allow_headers([CONTENT_TYPE]),
+        }
+    }
+}
+
+/// Checks that `origin` is exactly what browsers send in `Origin`.
+fn parse_origin(origin: &str) -> Result<HeaderValue> {
+    let uri: Uri = origin.parse().context("not a URL")?;
+    if !matches!(uri.scheme_str(), Some("http" | "https")) {
+        bail!("scheme must be http or https");
+    }
+    if uri.host().is_none_or(str::is_empty) {
+        bail!("missing host");
+    }
+    if uri
+        .path_and_query()
+        .is_some_and(|path| path.as_str() != "/")
+        || origin.ends_with('/')
+    {
+        bail!("must not have a path, query or trailing slash");
+    }
+    HeaderValue::from_str(origin).context("not a valid header value")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{
+            header::{
+                ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+                ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+            },
+            StatusCode,
+        },
+        response::Response,
+    };
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn preflight(origins: &str, origin: &str) -> Response {
+        let state = crate::AppState {
+            cors: Arc::new(Origins::parse(origins).unwrap()),
+            ..crate::AppState::default()
+        };
+        let request = Request::options("/api/calculate")
+            .header(ORIGIN, origin)
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+            .body(Body::empty())
+            .unwrap();
+        crate::build_router(state).oneshot(request).await.unwrap()
+    }
+
+    #[test]
+    fn test_parse_origins() {
+        assert_eq!(Origins::parse("").unwrap(), Origins::Any);
+        assert_eq!(Origins::parse(" * ").unwrap(), Origins::Any);
+        assert_eq!(
+            Origins::parse("https://a.example, http://localhost:8080").unwrap(),
+            Origins::List(vec![
+                HeaderValue::from_static("https://a.example"),
+                HeaderValue::from_static("http://localhost:8080"),
+            ])
+        );
+        for invalid in [
+            "a.example",
+            "ftp://a.example",
+            "https://a.example/",
+            "https://a.example/app",
+            "https://a.example?x=1",
+            "https://",
+        ] {
+            assert!(Origins::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preflight_allowed_origin() {
+        let response = preflight("https://clinic.example", "https://clinic.example").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://clinic.example"
+        );
+        let methods = response.headers()[ACCESS_CONTROL_ALLOW_METHODS]
+            .to_str()
+            .unwrap();
+        assert!(methods.contains("POST") && !methods.contains("DELETE"));
+    }
+
+    #[tokio::test]
+    async fn test_preflight_disallowed_origin() {
+        let response = preflight("https://clinic.example", "https://evil.example").await;
+
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unset_stays_permissive() {
+        let response = preflight("*", "https://anyone.example").await;
+
+        assert_eq!(response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    }
+}
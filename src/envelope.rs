@@ -0,0 +1,194 @@
+//! Legacy response envelope for older integrators.
+//!
+//! When a request carries `X-Response-Envelope: legacy`, API responses are
+//! rewritten as `{"success": true, "data": ...}` or
+//! `{"success": false, "error": {...}}`. Handlers are unaware of the mode;
+//! the mapping happens entirely in [`legacy_envelope`].
+
+use axum::{
+    body::{self, Body},
+    extract::Request,
+    http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+use tracing::{event, Level};
+
+/// Request header that opts into the legacy envelope.
+pub const ENVELOPE_HEADER: &str = "x-response-envelope";
+
+/// Header value selecting the legacy envelope shape.
+const LEGACY_MODE: &str = "legacy";
+
+/// Wraps API responses in the legacy envelope when the client asks for it.
+///
+/// Requests without the opt-in header pass through untouched. Non-text
+/// bodies (e.g. protobuf) are never rewritten.
+pub async fn legacy_envelope(request: Request, next: Next) -> Response {
+    let wants_legacy = request
+        .headers()
+        .get(ENVELOPE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case(LEGACY_MODE));
+
+    let response = next.run(request).await;
+    if !wants_legacy {
+        return response;
+    }
+
+    wrap(response).await
+}
+
+/// Rewrites a response body into the legacy envelope shape.
+async fn wrap(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let content_type = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    let bytes = match body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            event!(
+                name: "envelope.body.read_failed",
+                Level::ERROR,
+                error = %err,
+                "Failed to buffer response for legacy envelope: {{error}}"
+            );
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let payload = if bytes.is_empty() {
+        Value::Null
+    } else if is_json(&content_type) {
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+    } else if content_type.starts_with("text/") {
+        Value::String(String::from_utf8_lossy(&bytes).into_owned())
+    } else {
+        // Binary encodings are passed through unchanged.
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let status = parts.status;
+    let envelope = if status.is_success() {
+        json!({ "success": true, "data": payload })
+    } else {
+        json!({ "success": false, "error": error_object(status, payload) })
+    };
+
+    // A 204 cannot carry the envelope body, so legacy clients get a 200.
+    if status == StatusCode::NO_CONTENT {
+        parts.status = StatusCode::OK;
+    }
+    parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+    let mut wrapped = Json(envelope).into_response();
+    *wrapped.status_mut() = parts.status;
+    *wrapped.headers_mut() = parts.headers;
+    wrapped
+}
+
+/// Returns true for `application/json` and `+json` media types.
+fn is_json(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+/// Normalizes an error body into the envelope's `error` object.
+fn error_object(status: StatusCode, payload: Value) -> Value {
+    match payload {
+        Value::Object(mut map) => match map.remove("error") {
+            Some(Value::Object(mut error)) => {
+                error
+                    .entry("status")
+                    .or_insert_with(|| status.as_u16().into());
+                Value::Object(error)
+            }
+            Some(error) => error,
+            None => Value::Object(map),
+        },
+        Value::String(message) => json!({ "status": status.as_u16(), "message": message }),
+        _ => json!({
+            "status": status.as_u16(),
+            "message": status.canonical_reason().unwrap_or_default(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, routing::get, Router};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn send(router: Router, request: Request) -> (StatusCode, Value) {
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn calculate(body: &str, legacy: bool) -> Request {
+        let mut builder = Request::post("/api/calculate").header(CONTENT_TYPE, "application/json");
+        if legacy {
+            builder = builder.header(ENVELOPE_HEADER, "legacy");
+        }
+        builder.body(Body::from(body.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_success_is_wrapped_when_requested() {
+        let request = calculate(r#"{"weight_kg": 70.0, "height_m": 1.75}"#, true);
+        let (status, body) = send(crate::build_router(crate::AppState::default()), request).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"]["category"], "Normal weight");
+    }
+
+    #[tokio::test]
+    async fn test_default_shape_is_unchanged() {
+        let request = calculate(r#"{"weight_kg": 70.0, "height_m": 1.75}"#, false);
+        let (_, body) = send(crate::build_router(crate::AppState::default()), request).await;
+
+        assert!(body.get("success").is_none());
+        assert_eq!(body["category"], "Normal weight");
+    }
+
+    #[tokio::test]
+    async fn test_error_is_wrapped() {
+        let request = calculate(r#"{"weight_kg": "heavy"}"#, true);
+        let (status, body) = send(crate::build_router(crate::AppState::default()), request).await;
+
+        assert!(status.is_client_error());
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"]["status"], status.as_u16());
+        assert_eq!(body["error"]["code"], "malformed_payload");
+        assert!(body["error"]["message"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_no_content_becomes_wrapped_ok() {
+        let router = Router::new()
+            .route("/empty", get(|| async { StatusCode::NO_CONTENT }))
+            .layer(middleware::from_fn(legacy_envelope));
+        let request = Request::get("/empty")
+            .header(ENVELOPE_HEADER, "Legacy")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(router, request).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "success": true, "data": null }));
+    }
+}
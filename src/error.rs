@@ -0,0 +1,361 @@
+//! Errors produced by the BMI calculation pipeline.
+//!
+//! Every calculation handler reports failures as a [`BmiError`], rendered as
+//! `{"error": {"code": "invalid_height", "message": "..."}}` with a status
+//! matching the failure (400 for invalid or conflicting fields, 413 for
+//! oversized bodies, 406 for unsupported `Accept` types, 422 for undecodable
+//! payloads and implausible values, 503 for shed or timed-out requests, 500
+//! for unexpected errors).
+
+use std::fmt;
+
+use axum::{
+    extract::rejection::JsonRejection,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::validation::FieldViolation;
+
+/// A failed calculation request.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BmiError {
+    /// Weight is missing, not positive, or out of range.
+    InvalidWeight(String),
+    /// Height is missing, not positive, or out of range.
+    InvalidHeight(String),
+    /// Fields that cannot be combined were sent together.
+    ConflictingFields(String),
+    /// `strict_units` rejected a value that looks like another unit.
+    SuspectedUnitMismatch(String),
+    /// The inputs are valid on their own but the BMI is not a finite number.
+    OutOfRange(String),
+    /// `precision` is outside the supported range.
+    InvalidPrecision(String),
+    /// Measurements are outside the plausible bounds (see `validation`).
+    Validation(String, Vec<FieldViolation>),
+    /// Query parameters could not be parsed.
+    InvalidQuery(String),
+    /// A measurement token could not be decoded.
+    MalformedToken(String),
+    /// A measurement token is older than the configured max age.
+    ExpiredToken(String),
+    /// A measurement token's signature is missing or does not match.
+    TamperedToken(String),
+    /// The request body could not be decoded.
+    MalformedPayload(String),
+    /// The request body or batch exceeds its size limit.
+    PayloadTooLarge(String),
+    /// The requested resource is not enabled on this server.
+    NotFound(String),
+    /// No media type listed in `Accept` can be produced.
+    NotAcceptable(String),
+    /// The client sent more requests than its rate limit allows.
+    RateLimited(String),
+    /// The server is shedding load to stay within its memory budget.
+    Overloaded(String),
+    /// The handler did not respond within the configured deadline.
+    Timeout(String),
+    /// Something unexpected went wrong on our side.
+    Internal(String),
+}
+
+impl BmiError {
+    /// Stable machine-readable code for clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidWeight(_) => "invalid_weight",
+            Self::InvalidHeight(_) => "invalid_height",
+            Self::ConflictingFields(_) => "conflicting_fields",
+            Self::SuspectedUnitMismatch(_) => "suspected_unit_mismatch",
+            Self::OutOfRange(_) => "out_of_range",
+            Self::InvalidPrecision(_) => "invalid_precision",
+            Self::Validation(..) => "validation_failed",
+            Self::InvalidQuery(_) => "invalid_query",
+            Self::MalformedToken(_) => "malformed_token",
+            Self::ExpiredToken(_) => "expired_token",
+            Self::TamperedToken(_) => "tampered_token",
+            Self::MalformedPayload(_) => "malformed_payload",
+            Self::PayloadTooLarge(_) => "payload_too_large",
+            Self::NotFound(_) => "not_found",
+            Self::NotAcceptable(_) => "not_acceptable",
+            Self::RateLimited(_) => "rate_limited",
+            Self::Overloaded(_) => "overloaded",
+            Self::Timeout(_) => "timeout",
+            Self::Internal(_) => "internal_error",
+        }
+    }
+
+    /// HTTP status for this error.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::MalformedPayload(_) | Self::Validation(..) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Overloaded(_) | Self::Timeout(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    /// Human-readable description.
+    pub fn message(&self) -> &str {
+        match self {
+            Self::InvalidWeight(message)
+            | Self::InvalidHeight(message)
+            | Self::ConflictingFields(message)
+            | Self::SuspectedUnitMismatch(message)
+            | Self::OutOfRange(message)
+            | Self::InvalidPrecision(message)
+            | Self::Validation(message, _)
+            | Self::InvalidQuery(message)
+            | Self::MalformedToken(message)
+            | Self::ExpiredToken(message)
+            | Self::TamperedToken(message)
+            | Self::MalformedPayload(message)
+            | Self::PayloadTooLarge(message)
+            | Self::NotFound(message)
+            | Self::NotAcceptable(message)
+            | Self::RateLimited(message)
+            | Self::Overloaded(message)
+            | Self::Timeout(message)
+            | Self::Internal(message) => message,
+        }
+    }
+
+    /// The same error with `message` in place of its own.
+    pub(crate) fn with_message(self, message: String) -> Self {
+        match self {
+            Self::InvalidWeight(_) => Self::InvalidWeight(message),
+            Self::InvalidHeight(_) => Self::InvalidHeight(message),
+            Self::ConflictingFields(_) => Self::ConflictingFields(message),
+            Self::SuspectedUnitMismatch(_) => Self::SuspectedUnitMismatch(message),
+            Self::OutOfRange(_) => Self::OutOfRange(message),
+            Self::InvalidPrecision(_) => Self::InvalidPrecision(message),
+            Self::Validation(_, fields) => Self::Validation(message, fields),
+            Self::InvalidQuery(_) => Self::InvalidQuery(message),
+            Self::MalformedToken(_) => Self::MalformedToken(message),
+            Self::ExpiredToken(_) => Self::ExpiredToken(message),
+            Self::TamperedToken(_) => Self::TamperedToken(message),
+            Self::MalformedPayload(_) => Self::MalformedPayload(message),
+            Self::PayloadTooLarge(_) => Self::PayloadTooLarge(message),
+            Self::NotFound(_) => Self::NotFound(message),
+            Self::NotAcceptable(_) => Self::NotAcceptable(message),
+            Self::RateLimited(_) => Self::RateLimited(message),
+            Self::Overloaded(_) => Self::Overloaded(message),
+            Self::Timeout(_) => Self::Timeout(message),
+            Self::Internal(_) => Self::Internal(message),
+        }
+    }
+
+    /// The `{"code", "message"}` object, without the `error` wrapper.
+    pub fn body(&self) -> ErrorDetail {
+        ErrorDetail {
+            code: self.code(),
+            message: self.message().to_string(),
+            fields: match self {
+                Self::Validation(_, fields) => fields.clone(),
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+/// JSON body of every failed calculation response.
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "error": { "code": "invalid_weight", "message": "Weight must be a positive number" }
+}))]
+pub struct ErrorResponse {
+    /// What went wrong.
+    pub error: ErrorDetail,
+}
+
+/// Code and message of a [`BmiError`], also used per item in batches.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct ErrorDetail {
+    /// Stable machine-readable code, such as `invalid_weight`.
+    pub code: &'static str,
+    /// Human-readable explanation.
+    pub message: String,
+    /// Each field out of bounds, for `validation_failed`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldViolation>,
+}
+
+impl fmt::Display for BmiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for BmiError {}
+
+impl From<JsonRejection> for BmiError {
+    fn from(rejection: JsonRejection) -> Self {
+        if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            Self::PayloadTooLarge(rejection.body_text())
+        } else {
+            Self::MalformedPayload(rejection.body_text())
+        }
+    }
+}
+
+impl From<BmiCalcError> for BmiError {
+    fn from(error: BmiCalcError) -> Self {
+        let message = error.to_string();
+        match error {
+            BmiCalcError::Weight(_) => Self::InvalidWeight(message),
+            BmiCalcError::Height(_) => Self::InvalidHeight(message),
+            BmiCalcError::Bmi(_) => Self::OutOfRange(message),
+        }
+    }
+}
+
+impl IntoResponse for BmiError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse { error: self.body() };
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+/// Degenerate input to [`calculate_bmi`](crate::calculate_bmi) or
+/// [`categorize_bmi`](crate::categorize_bmi).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BmiCalcError {
+    /// Weight is zero, negative, or not finite.
+    Weight(f64),
+    /// Height is zero, negative, or not finite.
+    Height(f64),
+    /// The BMI is not a positive finite number, e.g. after an overflow.
+    Bmi(f64),
+}
+
+impl fmt::Display for BmiCalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Weight(_) => f.write_str("Weight must be a positive number"),
+            Self::Height(_) => f.write_str("Height must be a positive number"),
+            Self::Bmi(bmi) => write!(f, "BMI {bmi} is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for BmiCalcError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::header::CONTENT_TYPE};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn send(request: Request) -> (StatusCode, serde_json::Value) {
+        let response = crate::build_router(crate::AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn post(body: &str) -> Request {
+        Request::post("/api/calculate")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_status_mapping() {
+        let message = String::new;
+        assert_eq!(
+            BmiError::InvalidWeight(message()).status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            BmiError::MalformedPayload(message()).status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            BmiError::NotFound(message()).status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            BmiError::Internal(message()).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn test_each_failure_mode() {
+        let cases = [
+            (
+                r#"{"height_m": 1.75}"#,
+                StatusCode::BAD_REQUEST,
+                "invalid_weight",
+            ),
+            (
+                r#"{"weight_kg": 70, "height_m": 0}"#,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+            ),
+            (
+                r#"{"weight_kg": 70}"#,
+                StatusCode::BAD_REQUEST,
+                "invalid_height",
+            ),
+            (
+                r#"{"weight_kg": 70, "height_m": 1.75, "height_cm": 175}"#,
+                StatusCode::BAD_REQUEST,
+                "conflicting_fields",
+            ),
+            (
+                r#"{"weight_kg": 70, "height_m": 175, "strict_units": true}"#,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+            ),
+            (
+                r#"{"weight_kg": 1e308, "height_m": 1e-10}"#,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+            ),
+            (
+                r#"{"weight_kg": "heavy", "height_m": 1.75}"#,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "malformed_payload",
+            ),
+            (
+                "not json",
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "malformed_payload",
+            ),
+        ];
+
+        for (body, status, code) in cases {
+            let (actual, json) = send(post(body)).await;
+            assert_eq!(actual, status, "{body}");
+            assert_eq!(json["error"]["code"], code, "{body}");
+            assert!(json["error"]["message"].is_string(), "{body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_errors_share_the_shape() {
+        let (status, json) = send(
+            Request::get("/api/calculate?weight_kg=abc&height_m=1.75")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "invalid_query");
+    }
+}
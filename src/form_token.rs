@@ -0,0 +1,310 @@
+//! Single-use form tokens that absorb double submissions.
+//!
+//! The main page embeds a server-issued token (more are available from
+//! `GET /api/form-token`). The first `/api/calculate` request carrying a
+//! token computes the result and caches it; replays of the same token within
+//! the TTL get the cached response instead of a fresh calculation. Unknown
+//! or expired tokens behave as if no token was sent.
+//!
+//! Unlike an `Idempotency-Key`, tokens are minted by the server and can only
+//! be redeemed once.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use tracing::{event, Level};
+
+use crate::{AppState, BmiResponse};
+
+/// Default lifetime of an issued token.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Default bound on outstanding tokens to keep memory bounded.
+pub const MAX_TOKENS: usize = 10_000;
+
+/// Lifecycle of a single token.
+#[derive(Debug)]
+enum Slot {
+    /// Issued but not yet used.
+    Issued,
+    /// Used once; replays receive this response.
+    Redeemed(Box<BmiResponse>),
+}
+
+#[derive(Debug)]
+struct Entry {
+    expires_at: Instant,
+    slot: Slot,
+}
+
+/// Store of outstanding form tokens.
+#[derive(Debug)]
+pub struct FormTokens {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl Default for FormTokens {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl FormTokens {
+    /// Creates an empty store whose tokens live for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_capacity(ttl, MAX_TOKENS)
+    }
+
+    /// Creates an empty store keeping at most `capacity` outstanding tokens.
+    pub fn with_capacity(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the configured token lifetime.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Issues a fresh token.
+    pub fn issue(&self) -> String {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let now = Instant::now();
+        let mut entries = self.lock();
+
+        entries.retain(|_, entry| entry.expires_at > now);
+        if entries.len() >= self.capacity {
+            if let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(
+            token.clone(),
+            Entry {
+                expires_at: now + self.ttl,
+                slot: Slot::Issued,
+            },
+        );
+        token
+    }
+
+    /// Runs `compute` at most once per token and replays its result.
+    ///
+    /// Successful results consume the token; failures leave it redeemable so
+    /// the user can correct their input. Without a valid token, `compute`
+    /// simply runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error produced by `compute`.
+    pub fn redeem<E>(
+        &self,
+        token: Option<&str>,
+        compute: impl FnOnce() -> Result<BmiResponse, E>,
+    ) -> Result<BmiResponse, E> {
+        let Some(token) = token else {
+            return compute();
+        };
+
+        let mut entries = self.lock();
+        let entry = match entries.get_mut(token) {
+            Some(entry) if entry.expires_at > Instant::now() => entry,
+            _ => {
+                drop(entries);
+                return compute();
+            }
+        };
+
+        match &entry.slot {
+            Slot::Redeemed(response) => {
+                event!(
+                    name: "bmi.form_token.replayed",
+                    Level::INFO,
+                    "Replayed cached response for a reused form token"
+                );
+                Ok(response.as_ref().clone())
+            }
+            Slot::Issued => {
+                let response = compute()?;
+                entry.slot = Slot::Redeemed(Box::new(response.clone()));
+                Ok(response)
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Response body for `GET /api/form-token`.
+#[derive(Debug, Serialize)]
+pub struct FormTokenResponse {
+    /// Token to send as `form_token` with the next calculation.
+    pub form_token: String,
+    /// Seconds until the token expires.
+    pub expires_in_secs: u64,
+}
+
+/// Issues a form token for the JavaScript frontend.
+pub async fn issue_handler(State(state): State<AppState>) -> Json<FormTokenResponse> {
+    Json(FormTokenResponse {
+        form_token: state.form_tokens.issue(),
+        expires_in_secs: state.form_tokens.ttl().as_secs(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BmiError;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{header::CONTENT_TYPE, StatusCode},
+    };
+    use http_body_util::BodyExt;
+    use std::{cell::Cell, sync::Arc};
+    use tower::ServiceExt;
+
+    fn response(bmi: f64) -> BmiResponse {
+        BmiResponse {
+            bmi,
+            bmi_rounded: bmi,
+            category: "Normal weight".to_string(),
+            category_code: "normal_weight".to_string(),
+            category_detailed: "Normal weight".to_string(),
+            classification: crate::bmi::Classification::Standard,
+            units: crate::units::UnitSystem::Metric,
+            weight_kg: 70.0,
+            healthy_weight_range: crate::bmi::healthy_weight_range(
+                1.75,
+                crate::units::UnitSystem::Metric,
+                crate::bmi::Classification::Standard,
+            ),
+            bmi_prime: None,
+            ponderal_index: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn ok(bmi: f64) -> Result<BmiResponse, BmiError> {
+        Ok(response(bmi))
+    }
+
+    #[test]
+    fn test_redeem_once_then_replay() {
+        let tokens = FormTokens::default();
+        let token = tokens.issue();
+        let calls = Cell::new(0);
+        let compute = |bmi| {
+            calls.set(calls.get() + 1);
+            ok(bmi)
+        };
+
+        let first = tokens.redeem(Some(&token), || compute(22.0)).unwrap();
+        let replay = tokens.redeem(Some(&token), || compute(30.0)).unwrap();
+
+        assert_eq!(calls.get(), 1);
+        assert_eq!(first.bmi, 22.0);
+        assert_eq!(replay.bmi, 22.0);
+    }
+
+    #[test]
+    fn test_failed_calculation_keeps_token() {
+        let tokens = FormTokens::default();
+        let token = tokens.issue();
+
+        let failed = tokens.redeem(Some(&token), || {
+            Err(BmiError::InvalidWeight("invalid".to_string()))
+        });
+        assert!(failed.is_err());
+
+        let retried = tokens.redeem(Some(&token), || ok(22.0)).unwrap();
+        assert_eq!(retried.bmi, 22.0);
+    }
+
+    #[test]
+    fn test_expired_and_unknown_tokens_behave_as_absent() {
+        let tokens = FormTokens::new(Duration::ZERO);
+        let token = tokens.issue();
+
+        tokens.redeem(Some(&token), || ok(22.0)).unwrap();
+        let second = tokens.redeem(Some(&token), || ok(30.0)).unwrap();
+        assert_eq!(second.bmi, 30.0);
+
+        let unknown = tokens.redeem(Some("nope"), || ok(25.0)).unwrap();
+        assert_eq!(unknown.bmi, 25.0);
+    }
+
+    #[tokio::test]
+    async fn test_replayed_request_returns_first_response() {
+        let state = AppState {
+            form_tokens: Arc::new(FormTokens::default()),
+            ..AppState::default()
+        };
+        let router = crate::build_router(state);
+
+        let issued = router
+            .clone()
+            .oneshot(Request::get("/api/form-token").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let bytes = issued.into_body().collect().await.unwrap().to_bytes();
+        let token = serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()["form_token"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let calculate = |weight: f64| {
+            Request::post("/api/calculate")
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(format!(
+                    r#"{{"weight_kg": {weight}, "height_m": 1.75, "form_token": "{token}"}}"#
+                )))
+                .unwrap()
+        };
+
+        let mut bmis = Vec::new();
+        for weight in [70.0, 100.0] {
+            let response = router.clone().oneshot(calculate(weight)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            bmis.push(body["bmi"].as_f64().unwrap());
+        }
+
+        assert_eq!(bmis[0], bmis[1]);
+        assert!((bmis[0] - 22.857).abs() < 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_main_page_embeds_token() {
+        let response = crate::build_router(AppState::default())
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let page = String::from_utf8(bytes.to_vec()).unwrap();
+
+        assert!(page.contains(r#"id="formToken""#));
+        assert!(!page.contains(crate::page::FORM_TOKEN_SLOT));
+    }
+}
@@ -0,0 +1,319 @@
+//! Liveness and readiness probes for container orchestration.
+//!
+//! `GET /healthz` answers 200 while the process is serving at all.
+//! `GET /readyz` answers 503 until startup completes and again once shutdown
+//! begins, so load balancers stop routing before connections close. Both
+//! routes sit outside CORS, maintenance and auth middleware.
+//!
+//! Subsystems add named checks with [`Health::register`]; `/readyz` runs them
+//! on every probe, so a check that starts failing flips readiness until it
+//! recovers. Checks named in `readiness_informational` (comma-separated,
+//! `READINESS_INFORMATIONAL`) are reported but never gate. `?verbose=1` lists each check with its latency.
+
+use std::{
+    collections::HashSet,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::AppState;
+
+/// Longest a single check may take before it counts as failed.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Future returned by a readiness check; `Err` carries the reason.
+pub type CheckFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// A readiness check, called once per probe.
+pub type CheckFn = Arc<dyn Fn() -> CheckFuture + Send + Sync>;
+
+/// A registered readiness check.
+#[derive(Clone)]
+struct Check {
+    name: String,
+    gating: bool,
+    probe: CheckFn,
+}
+
+/// Result of one check in a `/readyz?verbose=1` response.
+#[derive(Debug, Serialize)]
+pub struct CheckReport {
+    /// Name given at registration.
+    pub name: String,
+    /// `"ok"` or `"failed"`.
+    pub status: &'static str,
+    /// Whether a failure makes the service not ready.
+    pub gating: bool,
+    /// Time the check took, in milliseconds.
+    pub latency_ms: f64,
+    /// Failure reason, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Lifecycle phase reported by `/readyz`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// Still binding the listener or loading configuration.
+    Starting,
+    /// Accepting traffic.
+    Ready,
+    /// Draining before exit.
+    Stopping,
+}
+
+impl Phase {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Ready,
+            2 => Self::Stopping,
+            _ => Self::Starting,
+        }
+    }
+}
+
+/// Process start time, lifecycle phase and subsystem checks.
+pub struct Health {
+    started: Instant,
+    phase: AtomicU8,
+    informational: HashSet<String>,
+    checks: RwLock<Vec<Check>>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new(HashSet::new())
+    }
+}
+
+impl Health {
+    /// Creates the state; checks named in `informational` never gate.
+    pub fn new(informational: HashSet<String>) -> Self {
+        Self {
+            started: Instant::now(),
+            phase: AtomicU8::new(Phase::Starting as u8),
+            informational,
+            checks: RwLock::default(),
+        }
+    }
+
+    /// Adds a named check run on every `/readyz` probe.
+    pub fn register(&self, name: &str, probe: CheckFn) {
+        let check = Check {
+            name: name.to_string(),
+            gating: !self.informational.contains(name),
+            probe,
+        };
+        self.checks
+            .write()
+            .expect("health checks lock poisoned")
+            .push(check);
+    }
+
+    /// Runs every check, in registration order.
+    pub async fn run_checks(&self) -> Vec<CheckReport> {
+        let checks = self
+            .checks
+            .read()
+            .expect("health checks lock poisoned")
+            .clone();
+        let mut reports = Vec::with_capacity(checks.len());
+        for check in checks {
+            let started = Instant::now();
+            let outcome = match tokio::time::timeout(CHECK_TIMEOUT, (check.probe)()).await {
+                Ok(outcome) => outcome,
+                Err(_) => Err(format!("timed out after {}s", CHECK_TIMEOUT.as_secs())),
+            };
+            metrics::gauge!("readiness_check_up", "check" => check.name.clone())
+                .set(if outcome.is_ok() { 1.0 } else { 0.0 });
+            reports.push(CheckReport {
+                name: check.name,
+                status: if outcome.is_ok() { "ok" } else { "failed" },
+                gating: check.gating,
+                latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+                error: outcome.err(),
+            });
+        }
+        reports
+    }
+
+    /// Current lifecycle phase.
+    pub fn phase(&self) -> Phase {
+        Phase::from_u8(self.phase.load(Ordering::Relaxed))
+    }
+
+    /// Moves to `phase`.
+    pub fn set_phase(&self, phase: Phase) {
+        self.phase.store(phase as u8, Ordering::Relaxed);
+    }
+
+    /// Whole seconds since the state was created.
+    pub fn uptime_s(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+}
+
+/// Handles `GET /healthz`.
+pub async fn healthz_handler(State(state): State<AppState>) -> Json<Value> {
+    Json(json!({ "status": "ok", "uptime_s": state.health.uptime_s() }))
+}
+
+/// Query parameters for `GET /readyz`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ReadyQuery {
+    /// Set to `1` to include per-check results.
+    #[serde(default)]
+    pub verbose: Option<String>,
+}
+
+/// Handles `GET /readyz`.
+///
+/// # Errors
+///
+/// Returns HTTP 503 while starting or stopping (`status` is the phase), or
+/// when a gating check fails (`status` is `"failing"`).
+pub async fn readyz_handler(
+    State(state): State<AppState>,
+    query: Option<Query<ReadyQuery>>,
+) -> (StatusCode, Json<Value>) {
+    let phase = state.health.phase();
+    let checks = state.health.run_checks().await;
+    let gating_failed = checks
+        .iter()
+        .any(|check| check.gating && check.error.is_some());
+
+    let (status, label) = match phase {
+        Phase::Ready if gating_failed => (StatusCode::SERVICE_UNAVAILABLE, json!("failing")),
+        Phase::Ready => (StatusCode::OK, json!(phase)),
+        Phase::Starting | Phase::Stopping => (StatusCode::SERVICE_UNAVAILABLE, json!(phase)),
+    };
+    let verbose =
+        query.is_some_and(|Query(query)| matches!(query.verbose.as_deref(), Some("1" | "true")));
+    let body = if verbose {
+        json!({ "status": label, "checks": checks })
+    } else {
+        json!({ "status": label })
+    };
+    (status, Json(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, ORIGIN},
+        response::Response,
+    };
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn get(state: &AppState, uri: &str) -> Response {
+        crate::build_router(state.clone())
+            .oneshot(
+                Request::get(uri)
+                    .header(ORIGIN, "https://example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn json(response: Response) -> Value {
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_healthz_reports_uptime() {
+        let response = get(&AppState::default(), "/healthz").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+        let body = json(response).await;
+        assert_eq!(body["status"], "ok");
+        assert!(body["uptime_s"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_readyz_follows_phase() {
+        let state = AppState::default();
+        for (phase, status) in [
+            (Phase::Starting, StatusCode::SERVICE_UNAVAILABLE),
+            (Phase::Ready, StatusCode::OK),
+            (Phase::Stopping, StatusCode::SERVICE_UNAVAILABLE),
+        ] {
+            state.health.set_phase(phase);
+            let response = get(&state, "/readyz").await;
+
+            assert_eq!(response.status(), status, "{phase:?}");
+            assert_eq!(json(response).await["status"], json!(phase));
+        }
+    }
+
+    fn check(result: Result<(), &'static str>) -> CheckFn {
+        Arc::new(move || Box::pin(async move { result.map_err(String::from) }))
+    }
+
+    #[tokio::test]
+    async fn test_gating_and_informational_checks() {
+        let health = Health::new(HashSet::from(["webhooks".to_string()]));
+        health.set_phase(Phase::Ready);
+        health.register("storage", check(Ok(())));
+        health.register("webhooks", check(Err("worker stalled")));
+        let state = AppState {
+            health: Arc::new(health),
+            ..AppState::default()
+        };
+
+        let response = get(&state, "/readyz?verbose=1").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json(response).await;
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["checks"][1]["status"], "failed");
+        assert_eq!(body["checks"][1]["gating"], false);
+        assert_eq!(body["checks"][1]["error"], "worker stalled");
+        assert!(body["checks"][0]["latency_ms"].is_f64());
+
+        state
+            .health
+            .register("redis", check(Err("connection refused")));
+        let response = get(&state, "/readyz").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = json(response).await;
+        assert_eq!(body["status"], "failing");
+        assert!(body.get("checks").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_probes_bypass_maintenance() {
+        let state = AppState::default();
+        state.health.set_phase(Phase::Ready);
+        state.maintenance.set(Some(crate::maintenance::Window {
+            message: "Upgrading".to_string(),
+            estimated_end: None,
+        }));
+
+        assert_eq!(get(&state, "/healthz").await.status(), StatusCode::OK);
+        assert_eq!(get(&state, "/readyz").await.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,247 @@
+//! Weight ranges per BMI category for a given height.
+//!
+//! `GET /api/hints?height_m=1.75&unit=kg` backs the live hint under the
+//! weight field ("At 1.75 m, Normal weight is 56.7–76.6 kg"). Results depend
+//! only on the query, so responses are cacheable.
+
+use axum::{
+    extract::{rejection::QueryRejection, Query},
+    http::header::CACHE_CONTROL,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{bmi::Classification, error::BmiError, units};
+
+/// Cache policy for hint responses; identical queries always match.
+const CACHE_POLICY: &str = "public, max-age=86400";
+
+/// Weight unit for hint ranges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeightUnit {
+    /// Kilograms.
+    #[default]
+    Kg,
+    /// Pounds.
+    Lb,
+}
+
+impl WeightUnit {
+    /// Unit symbol for display.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Kg => "kg",
+            Self::Lb => "lb",
+        }
+    }
+
+    fn convert_kg(self, kg: f64) -> f64 {
+        match self {
+            Self::Kg => kg,
+            Self::Lb => kg / units::KG_PER_LB,
+        }
+    }
+}
+
+/// Query parameters for `GET /api/hints`; exactly one height is required.
+#[derive(Debug, Deserialize)]
+pub struct HintsQuery {
+    /// Height in meters.
+    pub height_m: Option<f64>,
+    /// Height in centimeters.
+    pub height_cm: Option<f64>,
+    /// Height in inches.
+    pub height_in: Option<f64>,
+    /// Unit for the returned weights (defaults to kilograms).
+    #[serde(default)]
+    pub unit: WeightUnit,
+    /// Threshold table for the categories (defaults to `standard`).
+    #[serde(default)]
+    pub classification: Classification,
+}
+
+/// Weight range of one category, rounded to one decimal.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Range {
+    /// Category name, as returned by `/api/calculate`.
+    pub category: &'static str,
+    /// Lowest weight in the category; `None` for the first one.
+    pub min: Option<f64>,
+    /// Weight where the next category starts; `None` for the last one.
+    pub max: Option<f64>,
+}
+
+/// Response body for `GET /api/hints`.
+#[derive(Debug, Serialize)]
+pub struct HintsResponse {
+    /// Height the ranges were computed for, in meters.
+    pub height_m: f64,
+    /// Unit of `min` and `max`.
+    pub unit: WeightUnit,
+    /// One entry per category, lightest first.
+    pub ranges: Vec<Range>,
+    /// Human-readable summary of the normal range.
+    pub hint: String,
+}
+
+/// Computes category weight ranges for `height_m` under `classification`.
+pub fn ranges(height_m: f64, unit: WeightUnit, classification: Classification) -> Vec<Range> {
+    let weight_at = |bmi: f64| {
+        let weight = unit.convert_kg(bmi * height_m * height_m);
+        (weight * 10.0).round() / 10.0
+    };
+
+    let mut lower = None;
+    classification
+        .categories()
+        .iter()
+        .map(|&(category, upper)| {
+            let max = upper.is_finite().then(|| weight_at(upper));
+            let range = Range {
+                category,
+                min: lower,
+                max,
+            };
+            lower = max;
+            range
+        })
+        .collect()
+}
+
+/// Formats the normal-weight range for display under the weight field.
+pub fn normal_hint(height_m: f64, unit: WeightUnit, classification: Classification) -> String {
+    let normal = ranges(height_m, unit, classification)
+        .into_iter()
+        .find(|range| range.category == "Normal weight")
+        .expect("every classification includes Normal weight");
+    format!(
+        "At {height_m:.2} m, Normal weight is {:.1}–{:.1} {}",
+        normal.min.unwrap_or_default(),
+        normal.max.unwrap_or_default(),
+        unit.symbol()
+    )
+}
+
+/// Serves category weight ranges for a height.
+///
+/// # Errors
+///
+/// Returns HTTP 400 with a JSON [`BmiError`] if the query cannot be parsed
+/// or not exactly one positive height is given.
+pub async fn hints_handler(
+    query: Result<Query<HintsQuery>, QueryRejection>,
+) -> Result<Response, BmiError> {
+    let Query(query) = query.map_err(|rejection| BmiError::InvalidQuery(rejection.body_text()))?;
+    let height_m = match (query.height_m, query.height_cm, query.height_in) {
+        (Some(meters), None, None) => meters,
+        (None, Some(centimeters), None) => centimeters / 100.0,
+        (None, None, Some(inches)) => inches * units::M_PER_IN,
+        (None, None, None) => {
+            return Err(BmiError::InvalidHeight(
+                "One of height_m, height_cm or height_in is required".to_string(),
+            ))
+        }
+        _ => {
+            return Err(BmiError::ConflictingFields(
+                "Send only one of height_m, height_cm or height_in".to_string(),
+            ))
+        }
+    };
+    if !height_m.is_finite() || height_m <= 0.0 {
+        return Err(BmiError::InvalidHeight(
+            "Height must be a positive number".to_string(),
+        ));
+    }
+
+    Ok((
+        [(CACHE_CONTROL, CACHE_POLICY)],
+        Json(HintsResponse {
+            height_m,
+            unit: query.unit,
+            ranges: ranges(height_m, query.unit, query.classification),
+            hint: normal_hint(height_m, query.unit, query.classification),
+        }),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::StatusCode};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_ranges_in_kilograms() {
+        let ranges = ranges(1.75, WeightUnit::Kg, Classification::Standard);
+
+        assert_eq!(ranges.len(), crate::CATEGORIES.len());
+        assert_eq!(ranges[0].min, None);
+        assert_eq!(ranges[1].min, Some(56.7));
+        assert_eq!(ranges[1].max, Some(76.6));
+        assert_eq!(ranges[3].min, Some(91.9));
+        assert_eq!(ranges[3].max, None);
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].max, pair[1].min);
+        }
+    }
+
+    #[test]
+    fn test_ranges_in_pounds() {
+        let normal = &ranges(
+            69.0 * units::M_PER_IN,
+            WeightUnit::Lb,
+            Classification::Standard,
+        )[1];
+
+        // CDC lists 125–168 lb as healthy at 5'9"; Overweight starts at 169 lb.
+        assert_eq!(normal.min.map(f64::round), Some(125.0));
+        assert_eq!(normal.max.map(f64::round), Some(169.0));
+    }
+
+    #[test]
+    fn test_normal_hint() {
+        assert_eq!(
+            normal_hint(1.75, WeightUnit::Kg, Classification::Standard),
+            "At 1.75 m, Normal weight is 56.7–76.6 kg"
+        );
+        assert_eq!(
+            normal_hint(1.75, WeightUnit::Kg, Classification::AsianPacific),
+            "At 1.75 m, Normal weight is 56.7–70.4 kg"
+        );
+    }
+
+    async fn get(uri: &str) -> Response {
+        crate::build_router(crate::AppState::default())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hints_endpoint_is_cacheable() {
+        let response = get("/api/hints?height_cm=175&unit=lb").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CACHE_CONTROL], CACHE_POLICY);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["unit"], "lb");
+        assert_eq!(body["ranges"][1]["category"], "Normal weight");
+        assert!(body["hint"].as_str().unwrap().ends_with(" lb"));
+    }
+
+    #[tokio::test]
+    async fn test_hints_endpoint_validates_height() {
+        for uri in [
+            "/api/hints",
+            "/api/hints?height_m=1.75&height_cm=175",
+            "/api/hints?height_m=0",
+        ] {
+            assert_eq!(get(uri).await.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+}
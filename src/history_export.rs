@@ -0,0 +1,335 @@
+//! CSV download of the caller's history.
+//!
+//! `GET /api/history/export?format=csv` streams the session's stored
+//! calculations oldest first as RFC 4180 CSV with a
+//! `timestamp,weight_kg,height_m,bmi,category` header. Rows are read on the
+//! blocking pool in pages and sent in small chunks, so memory stays flat
+//! however long the history is, and a client that stops reading holds up
+//! only its own download. A database error mid-way aborts the response instead of
+//! ending it as if complete. Since a streamed export cannot be resumed, it
+//! is sent with `Accept-Ranges: none` and `Range` headers are ignored.
+
+use std::{borrow::Cow, fmt::Write as _};
+
+use anyhow::anyhow;
+use axum::{
+    body::{Body, Bytes},
+    extract::{rejection::QueryRejection, Query, State},
+    http::header::{ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::{SecondsFormat, Utc};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{event, Level};
+
+use crate::{
+    error::BmiError,
+    session::Session,
+    storage::{Record, Storage},
+    AppState,
+};
+
+/// The header row, CRLF-terminated like every row.
+const HEADER: &str = "timestamp,weight_kg,height_m,bmi,category\r\n";
+
+/// Bytes gathered before a chunk is sent.
+const CHUNK_BYTES: usize = 8 * 1024;
+
+/// Chunks buffered between the database and a slow client.
+const CHANNEL_CHUNKS: usize = 4;
+
+/// Query parameters for `GET /api/history/export`.
+#[derive(Debug, Default, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// Output format; only `csv` (the default) is supported.
+    pub format: Option<String>,
+}
+
+/// Quotes `field` if it holds a comma, quote or line break (RFC 4180).
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Appends one record as a CSV row.
+fn write_row(out: &mut String, record: &Record) {
+    let calculation = &record.calculation;
+    let _ = write!(
+        out,
+        "{},{},{},{},{}\r\n",
+        calculation
+            .recorded_at
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        calculation.weight_kg,
+        calculation.height_m,
+        calculation.bmi,
+        escape(&calculation.category)
+    );
+}
+
+/// Reads `session`'s rows into `sender`, one chunk at a time.
+fn produce(
+    storage: &dyn Storage,
+    session: Option<&str>,
+    sender: &mpsc::Sender<Result<Bytes, std::io::Error>>,
+) {
+    let mut chunk = String::from(HEADER);
+    let mut gone = false;
+    let outcome = match session {
+        Some(session) => storage.each_for_session(session, &mut |record| {
+            write_row(&mut chunk, &record);
+            if chunk.len() >= CHUNK_BYTES {
+                let full = Bytes::from(std::mem::take(&mut chunk));
+                if sender.blocking_send(Ok(full)).is_err() {
+                    gone = true;
+                    return Err(anyhow!("client disconnected"));
+                }
+            }
+            Ok(())
+        }),
+        // Without a session cookie there is nothing of the caller's to list
+        None => Ok(()),
+    };
+    match outcome {
+        Ok(()) => {
+            let _ = sender.blocking_send(Ok(Bytes::from(chunk)));
+        }
+        Err(_) if gone => {}
+        Err(error) => {
+            event!(
+                name: "app.storage.read_failed",
+                Level::WARN,
+                error = format!("{error:#}"),
+                "History export aborted: {{error}}"
+            );
+            let _ = sender.blocking_send(Err(std::io::Error::other("history read failed")));
+        }
+    }
+}
+
+/// Handles `GET /api/history/export`.
+///
+/// # Errors
+///
+/// Returns HTTP 404 with a JSON [`BmiError`] body when no database is
+/// configured, and HTTP 400 for a format other than `csv`.
+#[utoipa::path(
+    get,
+    path = "/api/history/export",
+    tag = "history",
+    summary = "Download stored calculations",
+    description = "The calling session's calculations as CSV, oldest first.",
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "CSV with a header row", content_type = "text/csv", body = String),
+        (status = 400, description = "Unsupported format", body = crate::error::ErrorResponse),
+        (status = 404, description = "History is not enabled", body = crate::error::ErrorResponse),
+    )
+)]
+pub async fn export_handler(
+    State(state): State<AppState>,
+    session: Option<Extension<Session>>,
+    query: Result<Query<ExportQuery>, QueryRejection>,
+) -> Response {
+    let Some(storage) = state.storage.clone() else {
+        return BmiError::NotFound(
+            "History is not enabled on this server (set BMI_DATABASE_URL)".to_string(),
+        )
+        .into_response();
+    };
+    let Query(query) = match query {
+        Ok(query) => query,
+        Err(rejection) => return BmiError::InvalidQuery(rejection.body_text()).into_response(),
+    };
+    match query.format.as_deref() {
+        None | Some("csv") => {}
+        Some(other) => {
+            return BmiError::InvalidQuery(format!("unsupported format {other:?}; use csv"))
+                .into_response()
+        }
+    }
+
+    let session = session.map(|Extension(Session(id))| id);
+    let (sender, receiver) = mpsc::channel(CHANNEL_CHUNKS);
+    tokio::task::spawn_blocking(move || produce(storage.as_ref(), session.as_deref(), &sender));
+    let chunks = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    });
+
+    let filename = format!("bmi-history-{}.csv", Utc::now().format("%Y-%m-%d"));
+    (
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (ACCEPT_RANGES, "none".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        session::COOKIE_NAME,
+        storage::{Calculation, SqliteStorage},
+        units::UnitSystem,
+    };
+    use axum::{
+        extract::Request,
+        http::{
+            header::{COOKIE, RANGE},
+            StatusCode,
+        },
+    };
+    use chrono::TimeZone;
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn calculation(row: u32, category: &str) -> Calculation {
+        Calculation {
+            recorded_at: Utc
+                .timestamp_opt(1_760_000_000 + i64::from(row), 0)
+                .unwrap(),
+            units: UnitSystem::Metric,
+            weight_kg: 60.0 + f64::from(row) / 10.0,
+            height_m: 1.75,
+            bmi: 19.6 + f64::from(row) / 30.0,
+            category: category.to_string(),
+        }
+    }
+
+    async fn export(state: &AppState, session: &str, query: &str) -> (StatusCode, Response) {
+        let cookie = format!("{COOKIE_NAME}={}", state.session_key.sign(session));
+        let request = Request::get(format!("/api/history/export{query}"))
+            .header(COOKIE, cookie)
+            .header(RANGE, "bytes=10-")
+            .body(Body::empty())
+            .unwrap();
+        let response = crate::build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        (response.status(), response)
+    }
+
+    #[test]
+    fn test_escape_follows_rfc_4180() {
+        assert_eq!(escape("Normal weight"), "Normal weight");
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[tokio::test]
+    async fn test_streams_session_rows_as_csv() {
+        let storage = SqliteStorage::open("sqlite::memory:").unwrap();
+        for row in 0..300 {
+            storage
+                .insert(Some("tester"), &calculation(row, "Normal weight"))
+                .unwrap();
+            storage
+                .insert(Some("someone else"), &calculation(row, "Obese"))
+                .unwrap();
+        }
+        storage
+            .insert(Some("tester"), &calculation(300, "Odd, \"quoted\"\nname"))
+            .unwrap();
+        let state = AppState {
+            storage: Some(Arc::new(storage)),
+            ..AppState::default()
+        };
+
+        let (status, response) = export(&state, "tester", "?format=csv").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/csv; charset=utf-8");
+        assert_eq!(response.headers()[ACCEPT_RANGES], "none");
+        let disposition = response.headers()[CONTENT_DISPOSITION].to_str().unwrap();
+        assert!(disposition.starts_with("attachment; filename=\"bmi-history-"));
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+
+        let mut reader = csv::Reader::from_reader(bytes.as_ref());
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec!["timestamp", "weight_kg", "height_m", "bmi", "category"]
+        );
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 301);
+        assert_eq!(&rows[0][0], "2025-10-09T08:53:20Z");
+        assert_eq!(rows[0][1].parse::<f64>().unwrap(), 60.0);
+        assert_eq!(rows[299][1].parse::<f64>().unwrap(), 60.0 + 299.0 / 10.0);
+        assert!(rows[..300].iter().all(|row| &row[4] == "Normal weight"));
+        assert_eq!(&rows[300][4], "Odd, \"quoted\"\nname");
+    }
+
+    #[tokio::test]
+    async fn test_unread_export_does_not_block_calculations() {
+        let storage = SqliteStorage::open("sqlite::memory:").unwrap();
+        for row in 0..3000 {
+            storage
+                .insert(Some("tester"), &calculation(row, "Normal weight"))
+                .unwrap();
+        }
+        let state = AppState {
+            storage: Some(Arc::new(storage)),
+            ..AppState::default()
+        };
+
+        // Far more rows than the channel buffers, and nobody reading them
+        let (status, stalled) = export(&state, "tester", "").await;
+        assert_eq!(status, StatusCode::OK);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let cookie = format!("{COOKIE_NAME}={}", state.session_key.sign("tester"));
+        let request = Request::post("/api/calculate")
+            .header(COOKIE, cookie)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"weight_kg": 70, "height_m": 1.75}"#))
+            .unwrap();
+        let calculated = tokio::time::timeout(
+            std::time::Duration::from_secs(2),
+            crate::build_router(state.clone()).oneshot(request),
+        )
+        .await
+        .expect("calculation waited for the export");
+        assert_eq!(calculated.unwrap().status(), StatusCode::OK);
+
+        // The stalled export still completes, with or without the new row
+        let bytes = stalled.into_body().collect().await.unwrap().to_bytes();
+        let rows = csv::Reader::from_reader(bytes.as_ref()).records().count();
+        assert!((3000..=3001).contains(&rows), "{rows}");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_disabled_history_and_other_formats() {
+        let (status, response) = export(&AppState::default(), "tester", "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"]["code"], "not_found");
+
+        let state = AppState {
+            storage: Some(Arc::new(SqliteStorage::open("sqlite::memory:").unwrap())),
+            ..AppState::default()
+        };
+        assert_eq!(
+            export(&state, "tester", "?format=xlsx").await.0,
+            StatusCode::BAD_REQUEST
+        );
+        let (status, response) = export(&state, "tester", "").await;
+        assert_eq!(status, StatusCode::OK);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, HEADER);
+    }
+}
@@ -0,0 +1,680 @@
+//! HTTP surface: shared state, the calculation handlers and the router.
+
+use anyhow::Result;
+use axum::{
+    extract::{
+        rejection::QueryRejection, DefaultBodyLimit, FromRequest, Json, Query, Request, State,
+    },
+    http::{HeaderMap, Uri},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use std::{sync::Arc, time::Duration};
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate, SizeAbove},
+    CompressionLayer,
+};
+use tracing::{event, Level};
+
+#[cfg(feature = "proto")]
+use crate::proto;
+#[cfg(feature = "xml")]
+use crate::xml;
+use crate::{
+    assets, batch, bmi, capabilities, changelog, config, connection, cors, envelope, error,
+    form_token, health, hints, history_export, i18n, limits, live_stats, maintenance,
+    measurement_token, memory, mock, negotiate, openapi, page, plain, process_bmi_request_with,
+    rate_limit, request_id, session, status, storage, telemetry, timings, validation, value, ws,
+    BmiRequest, BmiResponse,
+};
+
+/// Shared application state available to handlers and middleware.
+#[derive(Clone, Default)]
+pub struct AppState {
+    /// Maintenance mode switch consulted by every API request.
+    pub maintenance: Arc<maintenance::Maintenance>,
+    /// Token guarding `/api/admin` routes; `None` disables them.
+    pub admin_token: Option<Arc<str>>,
+    /// Single-use tokens that deduplicate repeated form submissions.
+    pub form_tokens: Arc<form_token::FormTokens>,
+    /// Canned-response mode for frontend development; `None` in production.
+    pub mock: Option<Arc<mock::Mock>>,
+    /// Connection limits and counters used by the accept loop.
+    pub connections: Arc<connection::Connections>,
+    /// Signing key and max age for QR measurement tokens.
+    pub measurement_tokens: Arc<measurement_token::TokenPolicy>,
+    /// Start time and lifecycle phase reported by the probes.
+    pub health: Arc<health::Health>,
+    /// Prometheus registry served at `/metrics`; `None` disables the route.
+    pub metrics: Option<metrics_exporter_prometheus::PrometheusHandle>,
+    /// Per-client token buckets for `/api/*`; `None` disables limiting.
+    pub rate_limiter: Option<Arc<rate_limit::RateLimiter>>,
+    /// Origins allowed to call us from a browser.
+    pub cors: Arc<cors::Origins>,
+    /// Memory budget sizing and pressure state.
+    pub memory: Arc<memory::Memory>,
+    /// Recent request figures and the cached public status.
+    pub status: Arc<status::StatusPage>,
+    /// Calculation history; `None` disables recording and `/api/history`.
+    pub storage: Option<Arc<dyn storage::Storage>>,
+    /// Key signing the anonymous session cookies that scope the history.
+    pub session_key: Arc<session::SessionKey>,
+    /// Plausibility bounds applied to every calculation.
+    pub bounds: Arc<validation::Bounds>,
+    /// The page, stylesheet and script (`static_dir` or embedded).
+    pub assets: Arc<assets::Assets>,
+    /// `/api/ws` connection limits.
+    pub ws: Arc<ws::Settings>,
+    /// Calculation figures streamed by `/api/stats/stream`.
+    pub live_stats: Arc<live_stats::LiveStats>,
+    /// Body caps and handler deadline.
+    pub limits: Arc<limits::Limits>,
+}
+
+impl AppState {
+    /// Builds state from `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a secret file is unreadable or set alongside its
+    /// inline value, if mock mode, connection limit, rate limit or CORS origin
+    /// settings are invalid, or if the history database cannot be opened.
+    pub fn new(config: &config::AppConfig) -> Result<Self> {
+        let secrets = config.secrets()?;
+        event!(
+            name: "app.config.secrets",
+            Level::INFO,
+            admin_token = %secrets.admin_token.source,
+            token_key = %secrets.token_key.source,
+            session_key = %secrets.session_key.source,
+            "Secrets loaded (admin_token: {{admin_token}}, token_key: {{token_key}}, session_key: {{session_key}})"
+        );
+
+        let memory = memory::Memory::new(config.memory_budget_mb);
+        let sizing = memory.sizing();
+        let storage = match &config.database_url {
+            Some(url) => {
+                Some(Arc::new(storage::SqliteStorage::open(url)?) as Arc<dyn storage::Storage>)
+            }
+            None => None,
+        };
+        let session_key = match secrets.session_key.value {
+            Some(key) => session::SessionKey::new(key),
+            None => {
+                if storage.is_some() {
+                    event!(
+                        name: "app.session.ephemeral_key",
+                        Level::WARN,
+                        "secrets.session_key is not set; using a random key, so history sessions end on restart"
+                    );
+                }
+                session::SessionKey::default()
+            }
+        };
+        let mut limits = config.connection_limits()?;
+        limits.max_total = limits.max_total.min(sizing.connections);
+
+        Ok(Self {
+            maintenance: Arc::new(config.maintenance()),
+            admin_token: secrets.admin_token.value.map(Arc::from),
+            form_tokens: Arc::new(form_token::FormTokens::with_capacity(
+                form_token::DEFAULT_TTL,
+                sizing.form_tokens,
+            )),
+            mock: config.mock()?.map(Arc::new),
+            connections: Arc::new(connection::Connections::new(limits)),
+            measurement_tokens: Arc::new(config.token_policy()?),
+            health: Arc::new(config.health()),
+            metrics: Some(telemetry::install()?),
+            rate_limiter: config.rate_limiter()?.map(Arc::new),
+            cors: Arc::new(cors::Origins::parse(&config.allowed_origins)?),
+            memory: Arc::new(memory),
+            status: Arc::new(status::StatusPage::new(Duration::from_secs(
+                config.status_cache_secs,
+            ))),
+            storage,
+            session_key: Arc::new(session_key),
+            bounds: Arc::new(config.validation_bounds()?),
+            assets: Arc::new(assets::Assets::new(&config.static_dir)),
+            ws: Arc::new(ws::Settings {
+                idle_timeout: Duration::from_secs(config.ws_idle_timeout_secs),
+            }),
+            live_stats: Arc::default(),
+            limits: Arc::new(config.request_limits()?),
+        })
+    }
+}
+
+/// Handles BMI calculation requests.
+///
+/// Validates input, calculates BMI, and returns categorized result.
+///
+/// # Examples
+///
+/// POST /api/calculate
+/// ```json
+/// {
+///   "weight_kg": 70.0,
+///   "height_m": 1.75
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns a [`BmiError`](error::BmiError) as JSON:
+/// - HTTP 400 if weight or height are invalid or fields conflict
+///   (`height_m` with `height_cm`, mixed units)
+/// - HTTP 422 if the JSON payload is malformed
+///
+/// A reused `form_token` returns the first response instead of recomputing.
+/// Admins sending `X-Debug-Timings: true` also get a `timings` block.
+/// `Accept: text/plain` gets a single line instead of JSON.
+#[utoipa::path(
+    post,
+    path = "/api/calculate",
+    tag = "calculation",
+    summary = "Calculate BMI",
+    description = "A reused `form_token` returns the first response instead of recomputing. `Accept: text/plain` answers `BMI: 22.9 (Normal weight)`.",
+    request_body = BmiRequest,
+    responses(
+        (status = 200, description = "BMI and category", body = BmiResponse),
+        (status = 200, description = "BMI and category as one line", content_type = "text/plain", body = String),
+        (status = 400, description = "Invalid or conflicting fields", body = error::ErrorResponse),
+        (status = 406, description = "`Accept` rules out JSON and plain text", body = error::ErrorResponse),
+        (status = 422, description = "Malformed JSON payload, or measurements out of bounds (`fields` lists each)", body = error::ErrorResponse),
+    )
+)]
+pub(crate) async fn calculate_bmi_handler(
+    State(state): State<AppState>,
+    request: Request,
+) -> Response {
+    let mut timings = timings::Timings::new(timings::requested(
+        state.admin_token.as_deref(),
+        request.headers(),
+    ));
+    let session = request.extensions().get::<session::Session>().cloned();
+    let locale = i18n::Locale::requested(request.headers(), request.uri().query());
+    let format = match negotiate::Format::requested(
+        request.headers(),
+        request.uri().query(),
+        CALCULATE_FORMATS,
+    ) {
+        Ok(format) => format,
+        Err(error) => return i18n::respond(locale, i18n::localize_error(error, locale)),
+    };
+    let payload = timings
+        .measure_async("deserialization", read_request(request))
+        .await;
+    calculate_json(&state, session.as_ref(), locale, format, payload, timings).await
+}
+
+/// Decodes a calculation request body: JSON, or XML when declared so.
+async fn read_request(request: Request) -> Result<BmiRequest, error::BmiError> {
+    #[cfg(feature = "xml")]
+    if xml::is_xml(request.headers()) {
+        return xml::read_request(request).await;
+    }
+    let Json(payload) = Json::<BmiRequest>::from_request(request, &()).await?;
+    Ok(payload)
+}
+
+/// Formats `/api/calculate` answers in, the default first.
+pub(crate) const CALCULATE_FORMATS: &[negotiate::Format] = &[
+    negotiate::Format::Json,
+    negotiate::Format::Text,
+    #[cfg(feature = "xml")]
+    negotiate::Format::Xml,
+];
+
+/// Renders a successful calculation in `format`.
+fn render(format: negotiate::Format, response: BmiResponse, precision: Option<u8>) -> Response {
+    match format {
+        negotiate::Format::Json => Json(response).into_response(),
+        negotiate::Format::Text => bmi::text_line(&response, precision).into_response(),
+        #[cfg(feature = "xml")]
+        negotiate::Format::Xml => {
+            xml::encoded(axum::http::StatusCode::OK, &xml::BmiResult::from(response))
+        }
+    }
+}
+
+/// Calculates a decoded payload and renders the response in `format`.
+///
+/// Fresh results are added to `session`'s history, in English; form-token
+/// replays are not. Labels and validation messages are then translated to
+/// `locale`. Timings are only reported in JSON.
+pub(crate) async fn calculate_json(
+    state: &AppState,
+    session: Option<&session::Session>,
+    locale: i18n::Locale,
+    format: negotiate::Format,
+    payload: Result<BmiRequest, error::BmiError>,
+    mut timings: timings::Timings,
+) -> Response {
+    let rejected = |error: error::BmiError| {
+        i18n::respond(locale, format.error(i18n::localize_error(error, locale)))
+    };
+    let payload = match payload {
+        Ok(payload) => payload,
+        Err(error) => return rejected(error),
+    };
+    let mut response = match calculate_fresh(state, session, &payload, &mut timings).await {
+        Ok(response) => response,
+        Err(error) => return rejected(error),
+    };
+    i18n::localize(&mut response, locale);
+    if format != negotiate::Format::Json || !timings.is_enabled() {
+        return i18n::respond(locale, render(format, response, payload.precision));
+    }
+
+    let mut body = timings.measure("serialization", || {
+        serde_json::to_value(&response).expect("BmiResponse serializes")
+    });
+    body["timings"] = timings.finish();
+    i18n::respond(locale, Json(body))
+}
+
+/// Calculates `payload`, redeeming its form token.
+///
+/// Fresh results are recorded through [`record_fresh`]; a replayed token
+/// returns the first response without recording it again.
+///
+/// # Errors
+///
+/// Returns the [`BmiError`](error::BmiError) of the first calculation if it
+/// was rejected.
+pub(crate) async fn calculate_fresh(
+    state: &AppState,
+    session: Option<&session::Session>,
+    payload: &BmiRequest,
+    timings: &mut timings::Timings,
+) -> Result<BmiResponse, error::BmiError> {
+    let mut fresh = false;
+    let response = state
+        .form_tokens
+        .redeem(payload.form_token.as_deref(), || {
+            fresh = true;
+            bmi::process_timed(payload, &state.bounds, timings)
+        })?;
+    if fresh {
+        record_fresh(state, session, payload, &response).await;
+    }
+    Ok(response)
+}
+
+/// Adds a freshly computed `response` to `session`'s history and to the
+/// live statistics; callers skip form-token replays.
+pub(crate) async fn record_fresh(
+    state: &AppState,
+    session: Option<&session::Session>,
+    request: &BmiRequest,
+    response: &BmiResponse,
+) {
+    storage::record(state, session, request, response).await;
+    state.live_stats.record(response);
+}
+
+/// Handles `GET /api/calculate` with the request fields as query parameters.
+///
+/// Uses the same validation and calculation as the POST route, for clients
+/// such as spreadsheets and dashboards that can only issue GETs. Form tokens
+/// are not redeemed since GETs have no side effects.
+///
+/// # Examples
+///
+/// GET /api/calculate?weight_kg=70&height_m=1.75
+///
+/// # Errors
+///
+/// Returns HTTP 400 with a JSON [`BmiError`](error::BmiError) body if a
+/// parameter is missing, not a number, or fails validation, and HTTP 422 if
+/// a measurement is out of bounds.
+#[utoipa::path(
+    get,
+    path = "/api/calculate",
+    tag = "calculation",
+    summary = "Calculate BMI from query parameters",
+    description = "Same fields and validation as the POST route, for clients that can only issue GETs.",
+    params(BmiRequest),
+    responses(
+        (status = 200, description = "BMI and category", body = BmiResponse),
+        (status = 400, description = "Missing, non-numeric or invalid parameter", body = error::ErrorResponse),
+        (status = 406, description = "`Accept` rules out JSON and plain text", body = error::ErrorResponse),
+        (status = 422, description = "Measurements out of bounds (`fields` lists each)", body = error::ErrorResponse),
+    )
+)]
+pub(crate) async fn calculate_query_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    uri: Uri,
+    query: Result<Query<BmiRequest>, QueryRejection>,
+) -> Response {
+    let locale = i18n::Locale::requested(&headers, uri.query());
+    let format = match negotiate::Format::requested(&headers, uri.query(), CALCULATE_FORMATS) {
+        Ok(format) => format,
+        Err(error) => return i18n::respond(locale, i18n::localize_error(error, locale)),
+    };
+    let outcome = query
+        .map_err(|rejection| error::BmiError::InvalidQuery(rejection.body_text()))
+        .and_then(|Query(payload)| {
+            let response = process_bmi_request_with(&payload, &state.bounds)?;
+            Ok((payload.precision, response))
+        });
+    match outcome {
+        Ok((precision, mut response)) => {
+            state.live_stats.record(&response);
+            i18n::localize(&mut response, locale);
+            i18n::respond(locale, render(format, response, precision))
+        }
+        Err(error) => i18n::respond(locale, format.error(i18n::localize_error(error, locale))),
+    }
+}
+
+/// Builds the application router with all routes and middleware.
+pub fn build_router(state: AppState) -> Router {
+    let api = Router::new()
+        .route("/api/calculate", calculate_route())
+        .route(
+            "/api/calculate/batch",
+            post(batch::batch_handler)
+                .layer(DefaultBodyLimit::max(state.limits.batch_body_bytes))
+                .layer(middleware::from_fn_with_state(state.clone(), memory::shed)),
+        )
+        .route("/api/calculate/value", get(value::value_handler))
+        .route(
+            "/api/calculate/t/:token",
+            get(measurement_token::token_handler),
+        )
+        .route("/api/capabilities", get(capabilities::capabilities_handler))
+        .route("/api/changelog", get(changelog::changelog_handler))
+        .route("/api/form-token", get(form_token::issue_handler))
+        .route("/api/hints", get(hints::hints_handler))
+        .route("/api/ws", get(ws::ws_handler))
+        .route("/api/stats/stream", get(live_stats::stream_handler))
+        .route("/api/history", get(storage::history_handler))
+        .route("/api/history/export", get(history_export::export_handler))
+        .route("/api/admin/maintenance", post(maintenance::update_handler))
+        .route("/api/admin/connections", get(connection::stats_handler))
+        .route_layer(middleware::from_fn_with_state(state.clone(), mock::respond))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            maintenance::guard,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit,
+        ))
+        .route_layer(middleware::from_fn(envelope::legacy_envelope));
+
+    let app = Router::new()
+        .route("/", get(page::root_handler))
+        .route("/index.html", get(page::root_handler))
+        .route("/calculate", post(page::submit_handler))
+        .route(
+            "/plain",
+            get(plain::form_handler).post(plain::submit_handler),
+        )
+        .merge(api)
+        .merge(openapi::routes())
+        .fallback(assets::static_handler)
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            limits::deadline,
+        ))
+        .layer(DefaultBodyLimit::max(state.limits.body_bytes))
+        .route_layer(middleware::from_fn(telemetry::track))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            status::record,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            session::issue,
+        ))
+        .layer(state.cors.layer())
+        // Probes are added after the layers so CORS and auth never apply
+        .route("/healthz", get(health::healthz_handler))
+        .route("/readyz", get(health::readyz_handler))
+        .route("/metrics", get(telemetry::metrics_handler))
+        .route("/status", get(status::status_page_handler))
+        .route("/api/status", get(status::status_json_handler))
+        .with_state(state)
+        .layer(compression());
+
+    // Outermost, so every log event of a request carries its ID
+    request_id::apply(app)
+}
+
+/// Smallest response body worth compressing, in bytes.
+const COMPRESS_MIN_BYTES: u16 = 1024;
+
+/// Compresses responses with gzip or brotli, as `Accept-Encoding` allows.
+///
+/// Bodies under [`COMPRESS_MIN_BYTES`], images and event streams (which
+/// would be buffered) are sent as is; WebSocket upgrades have no body.
+fn compression() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new().compress_when(
+        SizeAbove::new(COMPRESS_MIN_BYTES)
+            .and(NotForContentType::GRPC)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::SSE),
+    )
+}
+
+/// Selects the `/api/calculate` handler for the enabled features.
+#[cfg(not(feature = "proto"))]
+fn calculate_route() -> axum::routing::MethodRouter<AppState> {
+    post(calculate_bmi_handler).get(calculate_query_handler)
+}
+
+/// Selects the `/api/calculate` handler for the enabled features.
+#[cfg(feature = "proto")]
+fn calculate_route() -> axum::routing::MethodRouter<AppState> {
+    post(proto::calculate_handler).get(calculate_query_handler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    async fn get(uri: &str) -> (StatusCode, serde_json::Value) {
+        use axum::{body::Body, extract::Request};
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        let response = build_router(AppState::default())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    async fn get_encoded(uri: &str, accept_encoding: &str) -> Response {
+        use axum::{body::Body, extract::Request};
+        use tower::ServiceExt;
+
+        build_router(AppState::default())
+            .oneshot(
+                Request::get(uri)
+                    .header("accept-encoding", accept_encoding)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    /// Blanks the page's form token, which differs on every render.
+    fn without_token(page: &str) -> String {
+        let start = page.find(r#"name="form_token" value=""#).unwrap() + 25;
+        let end = start + page[start..].find('"').unwrap();
+        format!("{}{}", &page[..start], &page[end..])
+    }
+
+    #[tokio::test]
+    async fn test_page_is_compressed_on_request() {
+        use http_body_util::BodyExt;
+        use std::io::Read;
+
+        let response = get_encoded("/", "gzip").await;
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        let compressed = response.into_body().collect().await.unwrap().to_bytes();
+        let mut page = String::new();
+        flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_string(&mut page)
+            .unwrap();
+
+        let plain = get_encoded("/", "identity").await;
+        assert!(plain.headers().get("content-encoding").is_none());
+        let plain = plain.into_body().collect().await.unwrap().to_bytes();
+        let plain = String::from_utf8(plain.to_vec()).unwrap();
+        assert!(compressed.len() < plain.len());
+        assert_eq!(without_token(&page), without_token(&plain));
+
+        let response = get_encoded("/styles.css", "br;q=1, gzip;q=0.5").await;
+        assert_eq!(response.headers()["content-encoding"], "br");
+    }
+
+    #[tokio::test]
+    async fn test_small_and_streamed_responses_are_not_compressed() {
+        for uri in [
+            "/api/calculate?weight_kg=70&height_m=1.75",
+            "/api/stats/stream",
+        ] {
+            let response = get_encoded(uri, "gzip, br").await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            assert!(
+                response.headers().get("content-encoding").is_none(),
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calculate_via_query_parameters() {
+        let (status, body) = get("/api/calculate?weight_kg=70&height_m=1.75").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["category"], "Normal weight");
+        assert!((body["bmi"].as_f64().unwrap() - 22.857).abs() < 0.01);
+        assert_eq!(body["bmi_rounded"], 22.9);
+        assert!(body.get("bmi_prime").is_none());
+
+        let (status, body) = get("/api/calculate?weight_kg=70&height_m=1.75&precision=2").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["bmi_rounded"], 22.86);
+
+        let (_, body) = get("/api/calculate?weight_kg=70&height_m=1.75&extended=true").await;
+        assert_eq!(
+            body["bmi_prime"]["interpretation"],
+            "Within the normal range"
+        );
+        assert!(body["ponderal_index"]["value"].is_f64());
+
+        let (status, body) = get("/api/calculate?units=imperial&weight_lb=154&height_in=69").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["units"], "imperial");
+    }
+
+    #[tokio::test]
+    async fn test_query_errors_are_json() {
+        for uri in [
+            "/api/calculate?weight_kg=abc&height_m=1.75",
+            "/api/calculate?weight_kg=70",
+            "/api/calculate?weight_kg=70&height_m=1.75&precision=5",
+        ] {
+            let (status, body) = get(uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+            assert!(body["error"]["code"].is_string(), "{uri}: {body}");
+            assert!(body["error"]["message"].is_string(), "{uri}: {body}");
+        }
+
+        let (status, body) = get("/api/calculate?weight_kg=-70&height_m=1.75").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "validation_failed");
+        assert_eq!(body["error"]["fields"][0]["value"], -70.0);
+    }
+
+    async fn negotiated(request: Request) -> (StatusCode, String, String) {
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        let response = build_router(AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let content_type = response.headers()[axum::http::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (
+            status,
+            content_type,
+            String::from_utf8(bytes.to_vec()).unwrap(),
+        )
+    }
+
+    fn post_accepting(accept: &str, body: &str) -> Request {
+        Request::post("/api/calculate")
+            .header(axum::http::header::CONTENT_TYPE, "application/json")
+            .header(axum::http::header::ACCEPT, accept)
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_plain_text_is_negotiated() {
+        let body = r#"{"weight_kg": 70, "height_m": 1.75}"#;
+        let (status, content_type, text) = negotiated(post_accepting("text/plain", body)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(content_type.starts_with("text/plain"), "{content_type}");
+        assert_eq!(text, "BMI: 22.9 (Normal weight)");
+
+        let (_, content_type, _) = negotiated(post_accepting("application/json", body)).await;
+        assert_eq!(content_type, "application/json");
+
+        let (_, _, text) = negotiated(
+            Request::get("/api/calculate?weight_kg=70&height_m=1.75&precision=2&lang=fr")
+                .header(axum::http::header::ACCEPT, "text/*")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(text, "BMI: 22.86 (Poids normal)");
+
+        let (status, content_type, text) =
+            negotiated(post_accepting("text/plain", r#"{"weight_kg": 70}"#)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(content_type.starts_with("text/plain"), "{content_type}");
+        assert!(!text.starts_with('{'), "{text}");
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_accept_is_not_acceptable() {
+        let (status, content_type, text) = negotiated(post_accepting(
+            "text/html",
+            r#"{"weight_kg": 70, "height_m": 1.75}"#,
+        ))
+        .await;
+        assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(content_type, "application/json");
+        let body: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(body["error"]["code"], "not_acceptable");
+
+        let (status, _, _) = negotiated(
+            Request::get("/api/calculate?weight_kg=70&height_m=1.75")
+                .header(axum::http::header::ACCEPT, "text/html")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+    }
+}
@@ -0,0 +1,405 @@
+//! Localized category labels and validation messages.
+//!
+//! `/api/calculate` answers in the locale picked from a `lang` query
+//! parameter, else the best supported `Accept-Language` entry, else English.
+//! Only human-readable text changes: `category_code` and error `code`s stay
+//! stable for clients, and stored history keeps the English labels.
+
+use axum::{
+    http::{
+        header::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE},
+        HeaderMap, HeaderValue,
+    },
+    response::{IntoResponse, Response},
+};
+
+use crate::{error::BmiError, BmiResponse};
+
+/// A supported response language.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// English, the fallback.
+    #[default]
+    En,
+    /// French.
+    Fr,
+    /// German.
+    De,
+    /// Spanish.
+    Es,
+}
+
+impl Locale {
+    /// Every supported locale, fallback first.
+    pub const ALL: [Self; 4] = [Self::En, Self::Fr, Self::De, Self::Es];
+
+    /// Language tag as sent in `Content-Language`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Fr => "fr",
+            Self::De => "de",
+            Self::Es => "es",
+        }
+    }
+
+    /// Matches a language tag such as `fr-CH` by its primary subtag.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?;
+        Self::ALL
+            .into_iter()
+            .find(|locale| primary.eq_ignore_ascii_case(locale.as_str()))
+    }
+
+    /// Picks the locale for a request: `lang` in `query`, then
+    /// `Accept-Language`, then English.
+    pub fn requested(headers: &HeaderMap, query: Option<&str>) -> Self {
+        let lang = query
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+            .find_map(|pair| pair.strip_prefix("lang="))
+            .and_then(Self::from_tag);
+        lang.or_else(|| {
+            headers
+                .get_all(ACCEPT_LANGUAGE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .find_map(negotiate)
+        })
+        .unwrap_or_default()
+    }
+}
+
+/// Returns the supported locale with the highest quality in an
+/// `Accept-Language` value; earlier entries win ties.
+pub fn negotiate(header: &str) -> Option<Locale> {
+    let mut best: Option<(Locale, f32)> = None;
+    for entry in header.split(',') {
+        let mut parts = entry.split(';');
+        let tag = parts.next().unwrap_or_default();
+        let quality = match parts.find_map(|part| part.trim().strip_prefix("q=")) {
+            Some(quality) => match quality.trim().parse::<f32>() {
+                Ok(quality) => quality,
+                Err(_) => continue,
+            },
+            None => 1.0,
+        };
+        let Some(locale) = Locale::from_tag(tag) else {
+            continue;
+        };
+        if quality > 0.0 && best.is_none_or(|(_, current)| quality > current) {
+            best = Some((locale, quality));
+        }
+    }
+    best.map(|(locale, _)| locale)
+}
+
+/// English label, stable code, then French, German and Spanish labels.
+const CATEGORIES: [(&str, &str, [&str; 3]); 10] = [
+    (
+        "Underweight",
+        "underweight",
+        ["Insuffisance pondérale", "Untergewicht", "Bajo peso"],
+    ),
+    (
+        "Normal weight",
+        "normal_weight",
+        ["Poids normal", "Normalgewicht", "Peso normal"],
+    ),
+    (
+        "Overweight",
+        "overweight",
+        ["Surpoids", "Übergewicht", "Sobrepeso"],
+    ),
+    ("Obese", "obese", ["Obésité", "Adipositas", "Obesidad"]),
+    (
+        "Severe thinness",
+        "severe_thinness",
+        ["Maigreur sévère", "Starkes Untergewicht", "Delgadez severa"],
+    ),
+    (
+        "Moderate thinness",
+        "moderate_thinness",
+        [
+            "Maigreur modérée",
+            "Mäßiges Untergewicht",
+            "Delgadez moderada",
+        ],
+    ),
+    (
+        "Mild thinness",
+        "mild_thinness",
+        ["Maigreur légère", "Leichtes Untergewicht", "Delgadez leve"],
+    ),
+    (
+        "Obese class I",
+        "obese_class_1",
+        [
+            "Obésité de classe I",
+            "Adipositas Grad I",
+            "Obesidad de clase I",
+        ],
+    ),
+    (
+        "Obese class II",
+        "obese_class_2",
+        [
+            "Obésité de classe II",
+            "Adipositas Grad II",
+            "Obesidad de clase II",
+        ],
+    ),
+    (
+        "Obese class III",
+        "obese_class_3",
+        [
+            "Obésité de classe III",
+            "Adipositas Grad III",
+            "Obesidad de clase III",
+        ],
+    ),
+];
+
+/// Error code, then French, German and Spanish messages.
+const MESSAGES: [(&str, [&str; 3]); 9] = [
+    (
+        "invalid_weight",
+        [
+            "Le poids est manquant ou invalide",
+            "Das Gewicht fehlt oder ist ungültig",
+            "El peso falta o no es válido",
+        ],
+    ),
+    (
+        "invalid_height",
+        [
+            "La taille est manquante ou invalide",
+            "Die Größe fehlt oder ist ungültig",
+            "La altura falta o no es válida",
+        ],
+    ),
+    (
+        "conflicting_fields",
+        [
+            "Certains champs ne peuvent pas être combinés",
+            "Einige Felder können nicht kombiniert werden",
+            "Algunos campos no se pueden combinar",
+        ],
+    ),
+    (
+        "suspected_unit_mismatch",
+        [
+            "Une valeur semble être dans une autre unité",
+            "Ein Wert scheint eine andere Einheit zu verwenden",
+            "Un valor parece estar en otra unidad",
+        ],
+    ),
+    (
+        "out_of_range",
+        [
+            "L'IMC calculé est hors limites",
+            "Der berechnete BMI liegt außerhalb des gültigen Bereichs",
+            "El IMC calculado está fuera de rango",
+        ],
+    ),
+    (
+        "validation_failed",
+        [
+            "Certaines valeurs sont hors des limites plausibles",
+            "Einige Werte liegen außerhalb plausibler Grenzen",
+            "Algunos valores están fuera de los límites plausibles",
+        ],
+    ),
+    (
+        "invalid_precision",
+        [
+            "La précision doit être comprise entre 0 et 4",
+            "Die Genauigkeit muss zwischen 0 und 4 liegen",
+            "La precisión debe estar entre 0 y 4",
+        ],
+    ),
+    (
+        "invalid_query",
+        [
+            "Paramètres de requête invalides",
+            "Ungültige Abfrageparameter",
+            "Parámetros de consulta no válidos",
+        ],
+    ),
+    (
+        "malformed_payload",
+        [
+            "Le corps de la requête est illisible",
+            "Der Anfragetext ist fehlerhaft",
+            "El cuerpo de la solicitud no es válido",
+        ],
+    ),
+];
+
+/// Index into the translation arrays; `None` for English.
+fn column(locale: Locale) -> Option<usize> {
+    match locale {
+        Locale::En => None,
+        Locale::Fr => Some(0),
+        Locale::De => Some(1),
+        Locale::Es => Some(2),
+    }
+}
+
+fn entry(label: &str) -> Option<&'static (&'static str, &'static str, [&'static str; 3])> {
+    CATEGORIES.iter().find(|(english, _, _)| *english == label)
+}
+
+/// Stable machine-readable code of an English category label.
+pub fn category_code(label: &str) -> &'static str {
+    entry(label).map_or("unknown", |(_, code, _)| code)
+}
+
+/// `label` in `locale`; unknown labels are returned unchanged.
+pub fn category(label: &str, locale: Locale) -> String {
+    match (entry(label), column(locale)) {
+        (Some((_, _, translations)), Some(column)) => translations[column].to_string(),
+        _ => label.to_string(),
+    }
+}
+
+/// Translates the category labels of `response`.
+pub fn localize(response: &mut BmiResponse, locale: Locale) {
+    response.category = category(&response.category, locale);
+    response.category_detailed = category(&response.category_detailed, locale);
+}
+
+/// Replaces a validation error's message with its translation; errors
+/// without one, and every English error, keep their detailed message.
+pub fn localize_error(error: BmiError, locale: Locale) -> BmiError {
+    let translation = column(locale).and_then(|column| {
+        MESSAGES
+            .iter()
+            .find(|(code, _)| *code == error.code())
+            .map(|(_, messages)| messages[column])
+    });
+    match translation {
+        Some(message) => error.with_message(message.to_string()),
+        None => error,
+    }
+}
+
+/// `response` with a `Content-Language` header for `locale`.
+pub fn respond(locale: Locale, response: impl IntoResponse) -> Response {
+    let mut response = response.into_response();
+    response
+        .headers_mut()
+        .insert(CONTENT_LANGUAGE, HeaderValue::from_static(locale.as_str()));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{header::CONTENT_TYPE, StatusCode},
+    };
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_negotiate_honours_quality_values() {
+        for (header, expected) in [
+            ("fr-CH, fr;q=0.9, en;q=0.8", Some(Locale::Fr)),
+            ("en;q=0.8, de;q=0.9", Some(Locale::De)),
+            ("it, es;q=0.5, *;q=0.1", Some(Locale::Es)),
+            ("de;q=0, fr;q=0.1", Some(Locale::Fr)),
+            ("ES-mx", Some(Locale::Es)),
+            ("fr;q=abc, de;q=0.2", Some(Locale::De)),
+            ("it, ja", None),
+            ("", None),
+        ] {
+            assert_eq!(negotiate(header), expected, "{header}");
+        }
+    }
+
+    #[test]
+    fn test_lang_query_overrides_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("de"));
+
+        assert_eq!(Locale::requested(&headers, None), Locale::De);
+        assert_eq!(
+            Locale::requested(&headers, Some("weight_kg=70&lang=es")),
+            Locale::Es
+        );
+        assert_eq!(Locale::requested(&headers, Some("lang=xx")), Locale::De);
+        assert_eq!(Locale::requested(&HeaderMap::new(), None), Locale::En);
+    }
+
+    #[test]
+    fn test_every_category_has_a_code_and_translations() {
+        let labels = crate::bmi::DETAILED_CATEGORIES
+            .iter()
+            .chain(&crate::CATEGORIES)
+            .map(|(label, _)| *label);
+        for label in labels {
+            assert_ne!(category_code(label), "unknown", "{label}");
+            for locale in Locale::ALL {
+                assert!(!category(label, locale).is_empty(), "{label}");
+            }
+        }
+        assert_eq!(category("Normal weight", Locale::Fr), "Poids normal");
+        assert_eq!(category("Overweight", Locale::En), "Overweight");
+    }
+
+    async fn post(uri: &str, accept_language: &str, body: &str) -> (StatusCode, String, Value) {
+        let request = Request::post(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT_LANGUAGE, accept_language)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = crate::build_router(crate::AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let language = response.headers()[CONTENT_LANGUAGE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, language, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_calculation_is_localized() {
+        let body = r#"{"weight_kg": 70, "height_m": 1.75}"#;
+        let (status, language, json) =
+            post("/api/calculate", "fr-CH, fr;q=0.9, en;q=0.8", body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(language, "fr");
+        assert_eq!(json["category"], "Poids normal");
+        assert_eq!(json["category_code"], "normal_weight");
+
+        let (_, language, json) = post("/api/calculate?lang=de", "fr", body).await;
+        assert_eq!(language, "de");
+        assert_eq!(json["category"], "Normalgewicht");
+
+        let (_, language, json) = post("/api/calculate", "ja", body).await;
+        assert_eq!(language, "en");
+        assert_eq!(json["category"], "Normal weight");
+
+        let (status, _, json) = post(
+            "/api/calculate",
+            "es",
+            r#"{"weight_kg": -1, "height_m": 1.75}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json["error"]["code"], "validation_failed");
+        assert_eq!(
+            json["error"]["message"],
+            "Algunos valores están fuera de los límites plausibles"
+        );
+        assert_eq!(json["error"]["fields"][0]["field"], "weight_kg");
+    }
+}
@@ -0,0 +1,89 @@
+// Rust guideline compliant 2025-12-02
+
+//! BMI Calculator Web Application.
+//!
+//! A simple web application that calculates Body Mass Index (BMI) based on
+//! weight in kilograms and height in meters (SI units).
+//!
+//! # Architecture
+//!
+//! - Backend: Axum web framework with Tokio async runtime
+//! - Frontend: Leptos reactive UI framework (CSR mode)
+//! - API: RESTful endpoint for BMI calculation
+//!
+//! The calculation itself lives in [`bmi`] and has no HTTP dependencies;
+//! [`build_router`] assembles the routes and [`run`] serves them.
+//!
+//! # Examples
+//!
+//! ```
+//! use bmi_calculator::{calculate_bmi, categorize_bmi};
+//!
+//! let bmi = calculate_bmi(70.0, 1.75)?;
+//! assert_eq!(categorize_bmi(bmi)?, "Normal weight");
+//! # Ok::<(), bmi_calculator::error::BmiCalcError>(())
+//! ```
+//!
+//! Run the application:
+//! ```sh
+//! cargo run --release
+//! ```
+//!
+//! Access at: http://localhost:3000
+
+mod admin;
+mod assets;
+pub mod backup;
+mod batch;
+pub mod bmi;
+mod capabilities;
+mod changelog;
+pub mod cli;
+pub mod config;
+mod connection;
+mod cors;
+mod envelope;
+pub mod error;
+mod form_token;
+mod health;
+mod hints;
+mod history_export;
+mod html;
+mod http;
+mod i18n;
+mod limits;
+mod listener;
+mod live_stats;
+mod maintenance;
+pub mod measurement_token;
+mod memory;
+mod mock;
+mod negotiate;
+mod openapi;
+mod page;
+mod plain;
+#[cfg(feature = "proto")]
+mod proto;
+mod rate_limit;
+mod request_id;
+mod secrets;
+mod server;
+mod session;
+mod status;
+mod storage;
+mod telemetry;
+mod timings;
+mod tls;
+pub mod units;
+pub mod validation;
+mod value;
+mod ws;
+#[cfg(feature = "xml")]
+mod xml;
+
+pub use bmi::{
+    calculate_bmi, categorize_bmi, process_bmi_request, process_bmi_request_with, BmiRequest,
+    BmiResponse, CATEGORIES,
+};
+pub use http::{build_router, AppState};
+pub use server::run;
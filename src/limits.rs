@@ -0,0 +1,157 @@
+//! Request body caps and a deadline for handlers.
+//!
+//! Bodies are read at most [`Limits::body_bytes`] (16 KiB) deep, or
+//! [`Limits::batch_body_bytes`] on `POST /api/calculate/batch`, through
+//! axum's `DefaultBodyLimit`; past that the extractor stops reading and the
+//! route answers `413 payload_too_large` in the usual error format, so a
+//! huge upload costs no more memory than the cap.
+//!
+//! [`deadline`] gives every handler [`Limits::handler_timeout`] (10 seconds)
+//! to produce its response head. A handler that takes longer is dropped and
+//! the client gets `503 timeout`. Streamed bodies (history export, event
+//! streams) are not covered once their head is sent.
+
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{event, Level};
+
+use crate::{batch, error::BmiError, AppState};
+
+/// Configured caps (see `[limits]`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limits {
+    /// Largest body read on single-request routes, in bytes.
+    pub body_bytes: usize,
+    /// Largest body read on the batch route, in bytes.
+    pub batch_body_bytes: usize,
+    /// Time a handler gets to respond.
+    pub handler_timeout: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            body_bytes: 16 * 1024,
+            batch_body_bytes: batch::MAX_BODY_BYTES,
+            handler_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Answers `503 timeout` when the handler misses the deadline.
+pub async fn deadline(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_owned();
+    let timeout = state.limits.handler_timeout;
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            event!(
+                name: "http.handler.timed_out",
+                Level::WARN,
+                path = %path,
+                timeout_secs = timeout.as_secs_f64(),
+                "Handler for {{path}} exceeded {{timeout_secs}}s"
+            );
+            BmiError::Timeout(format!(
+                "The request took longer than {} seconds",
+                timeout.as_secs_f64()
+            ))
+            .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        http::{header::CONTENT_TYPE, StatusCode},
+        middleware,
+        routing::get,
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn send(router: Router, request: Request) -> (StatusCode, serde_json::Value) {
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    fn post(uri: &str, body: String) -> Request {
+        Request::post(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oversized_bodies_get_a_structured_413() {
+        let padding = " ".repeat(Limits::default().body_bytes);
+        let body = format!(r#"{{"weight_kg": 70, "height_m": 1.75}}{padding}"#);
+
+        let router = crate::build_router(AppState::default());
+        let (status, json) = send(router, post("/api/calculate", body.clone())).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(json["error"]["code"], "payload_too_large");
+
+        // The batch route has its own, larger cap
+        let entries = vec![r#"{"weight_kg": 70, "height_m": 1.75}"#; 1000].join(",");
+        let router = crate::build_router(AppState::default());
+        let (status, _) = send(router, post("/api/calculate/batch", format!("[{entries}]"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let state = AppState {
+            limits: Arc::new(Limits {
+                body_bytes: 1024 * 1024,
+                ..Limits::default()
+            }),
+            ..AppState::default()
+        };
+        let (status, _) = send(crate::build_router(state), post("/api/calculate", body)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_slow_handlers_time_out() {
+        let state = AppState {
+            limits: Arc::new(Limits {
+                handler_timeout: Duration::from_millis(50),
+                ..Limits::default()
+            }),
+            ..AppState::default()
+        };
+        let router = Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    "done"
+                }),
+            )
+            .route("/fast", get(|| async { "done" }))
+            .route_layer(middleware::from_fn_with_state(state.clone(), deadline))
+            .with_state(state);
+
+        let (status, json) = send(
+            router.clone(),
+            Request::get("/slow").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["error"]["code"], "timeout");
+
+        let (status, _) = send(router, Request::get("/fast").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
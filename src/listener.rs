@@ -0,0 +1,240 @@
+//! TCP listener setup, including zero-downtime handover modes.
+//!
+//! Three bind modes are supported:
+//! - `Exclusive` (default): a plain bind; a second process cannot share the port.
+//! - `ReusePort` (`listener.reuseport`, `BMI_REUSEPORT=1` or `--reuseport`,
+//!   unix only): binds with `SO_REUSEPORT` so old and new processes can
+//!   overlap during a deploy.
+//! - `Inherited` (`listener.inherit_fd`, `BMI_INHERIT_FD=<N>` or
+//!   `--inherit-fd <N>`, unix only): adopts an already-listening socket
+//!   passed by a supervisor.
+
+use std::net::SocketAddr;
+
+use anyhow::{bail, Context, Result};
+use tokio::net::TcpListener;
+
+use crate::config::AppConfig;
+
+/// Pending-connection queue length for sockets we create ourselves.
+const BACKLOG: i32 = 1024;
+
+/// How the server obtains its listening socket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListenerMode {
+    /// Plain exclusive bind.
+    Exclusive,
+    /// Bind with `SO_REUSEPORT` so several processes can share the port.
+    ReusePort,
+    /// Use a listening socket inherited on this file descriptor.
+    Inherited(i32),
+}
+
+impl ListenerMode {
+    /// Human-readable mode name for logs.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Exclusive => "exclusive",
+            Self::ReusePort => "reuseport",
+            Self::Inherited(_) => "inherited-fd",
+        }
+    }
+
+    /// Resolves the mode from `[listener]`, where the `serve` flags land.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the descriptor is negative, or if both handover
+    /// modes are requested at once.
+    pub fn from_config(config: &AppConfig) -> Result<Self> {
+        let section = &config.listener;
+        if section.inherit_fd.is_some_and(|fd| fd < 0) {
+            bail!("listener.inherit_fd must not be negative");
+        }
+        Self::resolve(section.inherit_fd, section.reuseport)
+    }
+
+    /// Combines the settings into a mode.
+    fn resolve(fd: Option<i32>, reuseport: bool) -> Result<Self> {
+        match (fd, reuseport) {
+            (Some(_), true) => {
+                bail!("listener.inherit_fd (--inherit-fd) and listener.reuseport (--reuseport) are mutually exclusive")
+            }
+            (Some(fd), false) => Ok(Self::Inherited(fd)),
+            (None, true) => Ok(Self::ReusePort),
+            (None, false) => Ok(Self::Exclusive),
+        }
+    }
+}
+
+/// Creates the server's listening socket for `addr` in the given mode.
+///
+/// For [`ListenerMode::Inherited`], `addr` is ignored and the socket's own
+/// address is used.
+///
+/// # Errors
+///
+/// Returns error if:
+/// - The address is already in use (exclusive mode)
+/// - The platform does not support the requested mode
+/// - The inherited descriptor is not a valid listening socket
+pub async fn bind(addr: SocketAddr, mode: ListenerMode) -> Result<TcpListener> {
+    match mode {
+        ListenerMode::Exclusive => TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("cannot bind {addr}")),
+        ListenerMode::ReusePort => bind_reuseport(addr),
+        ListenerMode::Inherited(fd) => inherit(fd),
+    }
+}
+
+#[cfg(unix)]
+fn bind_reuseport(addr: SocketAddr) -> Result<TcpListener> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_reuse_port(true)?;
+    socket.set_nonblocking(true)?;
+    socket
+        .bind(&addr.into())
+        .with_context(|| format!("cannot bind {addr} with SO_REUSEPORT"))?;
+    socket.listen(BACKLOG)?;
+
+    TcpListener::from_std(socket.into()).context("cannot register reuseport listener")
+}
+
+#[cfg(not(unix))]
+fn bind_reuseport(_addr: SocketAddr) -> Result<TcpListener> {
+    bail!("SO_REUSEPORT is only supported on unix platforms")
+}
+
+#[cfg(unix)]
+fn inherit(fd: i32) -> Result<TcpListener> {
+    use std::os::fd::FromRawFd;
+
+    if fd < 0 {
+        bail!("invalid inherited file descriptor {fd}");
+    }
+
+    // SAFETY: the supervisor hands this descriptor to us for exclusive use
+    // as a listening TCP socket; nothing else in the process owns it.
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+    listener
+        .local_addr()
+        .with_context(|| format!("file descriptor {fd} is not a TCP socket"))?;
+    listener.set_nonblocking(true)?;
+
+    TcpListener::from_std(listener).context("cannot register inherited listener")
+}
+
+#[cfg(not(unix))]
+fn inherit(_fd: i32) -> Result<TcpListener> {
+    bail!("--inherit-fd is only supported on unix platforms")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_mode() {
+        assert_eq!(
+            ListenerMode::resolve(None, false).unwrap(),
+            ListenerMode::Exclusive
+        );
+        assert_eq!(
+            ListenerMode::resolve(None, true).unwrap(),
+            ListenerMode::ReusePort
+        );
+        assert_eq!(
+            ListenerMode::resolve(Some(3), false).unwrap(),
+            ListenerMode::Inherited(3)
+        );
+        assert!(ListenerMode::resolve(Some(3), true).is_err());
+    }
+
+    #[test]
+    fn test_parsed_flags_select_the_mode() {
+        use clap::Parser;
+
+        let args = |line: &[&str]| {
+            let cli = crate::cli::Cli::try_parse_from(
+                std::iter::once("bmi_calculator").chain(line.iter().copied()),
+            )
+            .unwrap();
+            let mut config = AppConfig::default();
+            cli.serve.apply(&mut config);
+            ListenerMode::from_config(&config)
+        };
+        assert_eq!(
+            args(&["--inherit-fd=3"]).unwrap(),
+            ListenerMode::Inherited(3)
+        );
+        assert_eq!(
+            args(&["--inherit-fd", "4"]).unwrap(),
+            ListenerMode::Inherited(4)
+        );
+        assert_eq!(args(&["--reuseport"]).unwrap(), ListenerMode::ReusePort);
+        assert!(args(&["--reuseport", "--inherit-fd=3"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exclusive_bind_rejects_second_process() {
+        let first = bind("127.0.0.1:0".parse().unwrap(), ListenerMode::Exclusive)
+            .await
+            .unwrap();
+        let addr = first.local_addr().unwrap();
+
+        assert!(bind(addr, ListenerMode::Exclusive).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_overlapping_reuseport_instances_both_serve() {
+        use axum::{routing::get, Router};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let first = bind("127.0.0.1:0".parse().unwrap(), ListenerMode::ReusePort)
+            .await
+            .unwrap();
+        let addr = first.local_addr().unwrap();
+        let second = bind(addr, ListenerMode::ReusePort).await.unwrap();
+
+        for (listener, name) in [(first, "old"), (second, "new")] {
+            let app = Router::new().route("/", get(move || async move { name }));
+            tokio::spawn(async move { axum::serve(listener, app).await });
+        }
+
+        let mut served = std::collections::HashSet::new();
+        for _ in 0..64 {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+
+            assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+            served.insert(response.rsplit("\r\n").next().unwrap().to_string());
+        }
+
+        assert_eq!(served.len(), 2, "only {served:?} received traffic");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_inherited_descriptor_is_adopted() {
+        use std::os::fd::IntoRawFd;
+
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = std_listener.local_addr().unwrap();
+        let fd = std_listener.into_raw_fd();
+
+        let listener = bind("0.0.0.0:1".parse().unwrap(), ListenerMode::Inherited(fd))
+            .await
+            .unwrap();
+        assert_eq!(listener.local_addr().unwrap(), addr);
+    }
+}
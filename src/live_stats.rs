@@ -0,0 +1,244 @@
+//! Live usage figures pushed over server-sent events.
+//!
+//! `GET /api/stats/stream` answers `text/event-stream`: a `snapshot` event
+//! with the current figures on connect, then a `calculation` event each time
+//! `/api/calculate` or the page's form computes a result (form-token replays
+//! and WebSocket previews excluded). Each event carries the total count, the
+//! last category and the average BMI of the last [`WINDOW`] calculations.
+//!
+//! Events go through a `tokio::sync::broadcast` channel, so publishing never
+//! waits for subscribers: one that falls more than [`CHANNEL_CAPACITY`]
+//! events behind skips the ones it missed. A keep-alive comment is sent every
+//! [`KEEP_ALIVE`] so idle proxies leave the stream open.
+
+use std::{collections::VecDeque, convert::Infallible, sync::Mutex, time::Duration};
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::{stream, Stream, StreamExt};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{AppState, BmiResponse};
+
+/// Calculations the rolling average covers.
+pub const WINDOW: usize = 100;
+
+/// Events buffered per subscriber before the oldest are dropped.
+pub const CHANNEL_CAPACITY: usize = 64;
+
+/// Interval between keep-alive comments.
+pub const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Figures sent in every event.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Snapshot {
+    /// Calculations since the server started.
+    pub total: u64,
+    /// Category of the latest calculation, in English.
+    pub last_category: Option<String>,
+    /// Mean BMI of the latest calculations, to one decimal.
+    pub rolling_average_bmi: Option<f64>,
+    /// How many calculations the average covers, at most [`WINDOW`].
+    pub window: usize,
+}
+
+#[derive(Debug, Default)]
+struct Figures {
+    total: u64,
+    last_category: Option<String>,
+    recent: VecDeque<f64>,
+}
+
+impl Figures {
+    fn snapshot(&self) -> Snapshot {
+        let average = (!self.recent.is_empty())
+            .then(|| self.recent.iter().sum::<f64>() / self.recent.len() as f64);
+        Snapshot {
+            total: self.total,
+            last_category: self.last_category.clone(),
+            rolling_average_bmi: average.map(|average| (average * 10.0).round() / 10.0),
+            window: self.recent.len(),
+        }
+    }
+}
+
+/// Running figures and their subscribers.
+#[derive(Debug)]
+pub struct LiveStats {
+    figures: Mutex<Figures>,
+    events: broadcast::Sender<Snapshot>,
+}
+
+impl Default for LiveStats {
+    fn default() -> Self {
+        Self {
+            figures: Mutex::new(Figures::default()),
+            events: broadcast::channel(CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl LiveStats {
+    /// Counts `response` and notifies subscribers, without waiting on them.
+    pub fn record(&self, response: &BmiResponse) {
+        let snapshot = {
+            let mut figures = self.figures.lock().unwrap_or_else(|e| e.into_inner());
+            figures.total += 1;
+            figures.last_category = Some(response.category.clone());
+            if figures.recent.len() == WINDOW {
+                figures.recent.pop_front();
+            }
+            figures.recent.push_back(response.bmi);
+            figures.snapshot()
+        };
+        // Fails only when nobody is listening
+        let _ = self.events.send(snapshot);
+    }
+
+    /// Current figures.
+    pub fn snapshot(&self) -> Snapshot {
+        self.figures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .snapshot()
+    }
+
+    /// Receives every later snapshot.
+    pub fn subscribe(&self) -> broadcast::Receiver<Snapshot> {
+        self.events.subscribe()
+    }
+}
+
+/// Serves `GET /api/stats/stream`.
+pub async fn stream_handler(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // Subscribe first so no calculation falls between snapshot and stream
+    let receiver = state.live_stats.subscribe();
+    let current = state.live_stats.snapshot();
+    let updates = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(snapshot) => return Some((snapshot, receiver)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .map(|snapshot| event("calculation", &snapshot));
+    let events = stream::once(async move { event("snapshot", &current) }).chain(updates);
+    Sse::new(events).keep_alive(KeepAlive::new().interval(KEEP_ALIVE))
+}
+
+fn event(name: &str, snapshot: &Snapshot) -> Result<Event, Infallible> {
+    Ok(Event::default()
+        .event(name)
+        .json_data(snapshot)
+        .expect("snapshots serialize"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::header::CONTENT_TYPE};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    fn calculate(weight_kg: f64) -> Request {
+        Request::post("/api/calculate")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(format!(
+                r#"{{"weight_kg": {weight_kg}, "height_m": 1.75}}"#
+            )))
+            .unwrap()
+    }
+
+    /// Reads the next `count` events as (name, data) pairs.
+    async fn events(body: &mut Body, count: usize) -> Vec<(String, serde_json::Value)> {
+        let mut text = String::new();
+        while text.matches("\n\n").count() < count {
+            let frame = body.frame().await.unwrap().unwrap();
+            if let Ok(data) = frame.into_data() {
+                text.push_str(std::str::from_utf8(&data).unwrap());
+            }
+        }
+        text.split("\n\n")
+            .filter(|block| block.starts_with("event:"))
+            .map(|block| {
+                let field = |name: &str| {
+                    block
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .unwrap()
+                        .trim()
+                        .to_string()
+                };
+                let data = serde_json::from_str(&field("data:")).unwrap();
+                (field("event:"), data)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_calculations_are_streamed() {
+        let state = AppState::default();
+        let response = crate::build_router(state.clone())
+            .oneshot(
+                Request::get("/api/stats/stream")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/event-stream");
+        let mut body = response.into_body();
+
+        let initial = events(&mut body, 1).await;
+        assert_eq!(initial[0].0, "snapshot");
+        assert_eq!(initial[0].1["total"], 0);
+
+        for weight_kg in [70.0, 100.0] {
+            let response = crate::build_router(state.clone())
+                .oneshot(calculate(weight_kg))
+                .await
+                .unwrap();
+            assert!(response.status().is_success());
+        }
+
+        let received = events(&mut body, 2).await;
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].0, "calculation");
+        assert_eq!(received[0].1["total"], 1);
+        assert_eq!(received[0].1["last_category"], "Normal weight");
+        assert_eq!(received[1].1["total"], 2);
+        assert_eq!(received[1].1["last_category"], "Obese");
+        assert_eq!(received[1].1["rolling_average_bmi"], 27.8);
+        assert_eq!(received[1].1["window"], 2);
+    }
+
+    #[test]
+    fn test_slow_subscribers_lose_events_instead_of_blocking() {
+        let stats = LiveStats::default();
+        let mut receiver = stats.subscribe();
+        let response = crate::process_bmi_request(&crate::BmiRequest {
+            weight_kg: Some(70.0),
+            height_m: Some(1.75),
+            ..crate::BmiRequest::default()
+        })
+        .unwrap();
+
+        for _ in 0..CHANNEL_CAPACITY + WINDOW {
+            stats.record(&response);
+        }
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(broadcast::error::TryRecvError::Lagged(_))
+        ));
+        assert_eq!(receiver.try_recv().unwrap().total, WINDOW as u64 + 1);
+        assert_eq!(stats.snapshot().total, (CHANNEL_CAPACITY + WINDOW) as u64);
+        assert_eq!(stats.snapshot().window, WINDOW);
+    }
+}
@@ -0,0 +1,304 @@
+//! Maintenance mode for planned downtime.
+//!
+//! While a maintenance window is active the listener keeps accepting
+//! connections, but API routes answer with a problem+json 503 and the main
+//! page shows a banner. The window is toggled at runtime through
+//! `POST /api/admin/maintenance` or at boot with `[maintenance] enabled = true`
+//! (`MAINTENANCE_MODE=1`).
+
+use std::sync::RwLock;
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{CONTENT_TYPE, RETRY_AFTER},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{event, Level};
+
+use crate::{admin, html, AppState};
+
+/// `Retry-After` value used when no end time was announced.
+const DEFAULT_RETRY_AFTER_SECS: i64 = 60;
+
+/// Message shown when the operator did not provide one.
+pub(crate) const DEFAULT_MESSAGE: &str = "The service is undergoing scheduled maintenance";
+
+/// An active maintenance window.
+#[derive(Clone, Debug)]
+pub struct Window {
+    /// Explanation shown to API clients and in the UI banner.
+    pub message: String,
+    /// Expected end of the window, if announced.
+    pub estimated_end: Option<DateTime<Utc>>,
+}
+
+/// Shared maintenance switch read by every API request.
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    window: RwLock<Option<Window>>,
+}
+
+impl Maintenance {
+    /// Creates the switch, optionally starting in maintenance.
+    pub fn new(initial: Option<Window>) -> Self {
+        Self {
+            window: RwLock::new(initial),
+        }
+    }
+
+    /// Returns the active window, if any.
+    pub fn current(&self) -> Option<Window> {
+        self.window
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Replaces the active window; `None` resumes normal operation.
+    pub fn set(&self, window: Option<Window>) {
+        *self
+            .window
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = window;
+    }
+}
+
+/// Rejects API requests with 503 while maintenance is active.
+///
+/// Admin routes stay reachable so the mode can be switched off again.
+pub async fn guard(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if request.uri().path().starts_with(admin::ADMIN_PREFIX) {
+        return next.run(request).await;
+    }
+
+    match state.maintenance.current() {
+        Some(window) => unavailable(&window),
+        None => next.run(request).await,
+    }
+}
+
+/// Builds the problem+json 503 response for an active window.
+fn unavailable(window: &Window) -> Response {
+    let retry_after = window
+        .estimated_end
+        .map(|end| (end - Utc::now()).num_seconds().max(1))
+        .unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+
+    let body = json!({
+        "type": "about:blank",
+        "title": "Service Unavailable",
+        "status": StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+        "detail": window.message,
+        "estimated_end": window.estimated_end,
+    });
+
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [
+            (
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/problem+json"),
+            ),
+            (RETRY_AFTER, HeaderValue::from(retry_after)),
+        ],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+/// Request body for `POST /api/admin/maintenance`.
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceUpdate {
+    /// Whether maintenance should be active.
+    pub enabled: bool,
+    /// Optional message for clients and the UI banner.
+    pub message: Option<String>,
+    /// Optional expected duration, used for `Retry-After` and the end time.
+    pub duration_secs: Option<u64>,
+}
+
+/// Current maintenance state returned by the admin endpoint.
+#[derive(Debug, Serialize)]
+pub struct MaintenanceStatus {
+    /// Whether maintenance is active.
+    pub enabled: bool,
+    /// Active message, if any.
+    pub message: Option<String>,
+    /// Announced end time, if any.
+    pub estimated_end: Option<DateTime<Utc>>,
+}
+
+/// Switches maintenance mode on or off without restarting.
+///
+/// # Errors
+///
+/// Returns HTTP 401/403 if the admin token is missing or not configured.
+pub async fn update_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(update): Json<MaintenanceUpdate>,
+) -> Response {
+    if let Err(rejection) = admin::authorize(state.admin_token.as_deref(), &headers) {
+        return rejection.into_response();
+    }
+
+    let window = update.enabled.then(|| Window {
+        message: update
+            .message
+            .unwrap_or_else(|| DEFAULT_MESSAGE.to_string()),
+        estimated_end: update
+            .duration_secs
+            .and_then(|secs| i64::try_from(secs).ok())
+            .map(|secs| Utc::now() + chrono::Duration::seconds(secs)),
+    });
+    state.maintenance.set(window.clone());
+
+    event!(
+        name: "app.maintenance.toggled",
+        Level::WARN,
+        enabled = update.enabled,
+        "Maintenance mode set to {{enabled}}"
+    );
+
+    Json(MaintenanceStatus {
+        enabled: window.is_some(),
+        message: window.as_ref().map(|w| w.message.clone()),
+        estimated_end: window.and_then(|w| w.estimated_end),
+    })
+    .into_response()
+}
+
+/// Renders the HTML banner shown on the main page during maintenance.
+pub fn banner(window: &Window) -> String {
+    format!(
+        r#"<div class="maintenance" role="status">{}</div>"#,
+        html::escape(&window.message)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn state() -> AppState {
+        AppState {
+            admin_token: Some(Arc::from("secret")),
+            ..AppState::default()
+        }
+    }
+
+    async fn send(state: &AppState, request: Request) -> Response {
+        crate::build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    fn toggle(body: &str, token: &str) -> Request {
+        Request::post("/api/admin/maintenance")
+            .header(CONTENT_TYPE, "application/json")
+            .header("authorization", format!("Bearer {token}"))
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn calculate() -> Request {
+        Request::post("/api/calculate")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"weight_kg": 70.0, "height_m": 1.75}"#))
+            .unwrap()
+    }
+
+    async fn text(response: Response) -> String {
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_toggle_maintenance_mode() {
+        let state = state();
+
+        let response = send(
+            &state,
+            toggle(
+                r#"{"enabled": true, "message": "DB upgrade", "duration_secs": 600}"#,
+                "secret",
+            ),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&state, calculate()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/problem+json");
+        let retry_after: i64 = response.headers()[RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=600).contains(&retry_after));
+        let problem: serde_json::Value = serde_json::from_str(&text(response).await).unwrap();
+        assert_eq!(problem["status"], 503);
+        assert_eq!(problem["detail"], "DB upgrade");
+        assert!(problem["estimated_end"].is_string());
+
+        let page = text(send(&state, Request::get("/").body(Body::empty()).unwrap()).await).await;
+        assert!(page.contains(r#"<div class="maintenance" role="status">DB upgrade</div>"#));
+
+        let response = send(&state, toggle(r#"{"enabled": false}"#, "secret")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&state, calculate()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let page = text(send(&state, Request::get("/").body(Body::empty()).unwrap()).await).await;
+        assert!(!page.contains(r#"class="maintenance""#));
+    }
+
+    #[tokio::test]
+    async fn test_boot_time_window_without_end_uses_default_retry() {
+        let state = AppState {
+            maintenance: Arc::new(Maintenance::new(Some(Window {
+                message: DEFAULT_MESSAGE.to_string(),
+                estimated_end: None,
+            }))),
+            ..AppState::default()
+        };
+
+        let response = send(&state, calculate()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers()[RETRY_AFTER],
+            DEFAULT_RETRY_AFTER_SECS.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_toggle_requires_admin_token() {
+        let state = state();
+        let response = send(&state, toggle(r#"{"enabled": true}"#, "wrong")).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(state.maintenance.current().is_none());
+    }
+
+    #[test]
+    fn test_banner_escapes_message() {
+        let window = Window {
+            message: "<script>".to_string(),
+            estimated_end: None,
+        };
+        assert!(banner(&window).contains("&lt;script&gt;"));
+    }
+}
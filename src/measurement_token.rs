@@ -0,0 +1,406 @@
+//! Compact measurement tokens for QR codes printed by kiosks.
+//!
+//! `GET /api/calculate/t/{token}` calculates from a base64url token instead
+//! of query parameters. The token is a fixed-width big-endian record, not
+//! JSON, so it stays short enough for a small QR code:
+//!
+//! | Bytes  | Field                                                  |
+//! |--------|--------------------------------------------------------|
+//! | 0      | Format version (1)                                     |
+//! | 1      | Flags: bit 0 imperial, bit 1 signed                    |
+//! | 2..6   | Weight in hundredths of a kilogram or pound            |
+//! | 6..10  | Height in ten-thousandths of a meter or hundredths of an inch |
+//! | 10..14 | Issued at, Unix seconds                                |
+//! | 14..30 | HMAC-SHA256 of bytes 0..14, truncated (signed only)    |
+//!
+//! `secrets.token_key` (`BMI_TOKEN_KEY`) signs and requires signatures;
+//! `tokens.max_age_secs` (default 86400) bounds token age; tokens issued more
+//! than five minutes ahead of the server clock are rejected.
+//! Tokens are minted with `bmi_calculator token --weight-kg 70 --height-m 1.75`.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{error::BmiError, process_bmi_request_with, units, AppState, BmiRequest};
+
+/// Current encoding version, stored in the first byte.
+const VERSION: u8 = 1;
+
+/// Flag bit set when weight and height are pounds and inches.
+const FLAG_IMPERIAL: u8 = 0b01;
+
+/// Flag bit set when a signature follows the record.
+const FLAG_SIGNED: u8 = 0b10;
+
+/// Length of the unsigned record.
+const RECORD_LEN: usize = 14;
+
+/// Length of the truncated signature.
+const SIGNATURE_LEN: usize = 16;
+
+/// How far `issued_at` may run ahead of the server clock, in seconds.
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+
+/// Default `BMI_TOKEN_MAX_AGE_SECS`.
+pub(crate) const DEFAULT_MAX_AGE: Duration = Duration::from_secs(86_400);
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Measurements carried by a token, in the units of `units`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measurement {
+    /// Unit system of `weight` and `height`.
+    pub units: units::UnitSystem,
+    /// Weight in kilograms (metric) or pounds (imperial).
+    pub weight: f64,
+    /// Height in meters (metric) or inches (imperial).
+    pub height: f64,
+}
+
+impl Measurement {
+    /// Scale factors from weight and height to their stored integers.
+    fn scales(self) -> (f64, f64) {
+        match self.units {
+            units::UnitSystem::Metric => (100.0, 10_000.0),
+            units::UnitSystem::Imperial => (100.0, 100.0),
+        }
+    }
+
+    /// Builds the equivalent calculation request.
+    fn to_request(self) -> BmiRequest {
+        match self.units {
+            units::UnitSystem::Metric => BmiRequest {
+                weight_kg: Some(self.weight),
+                height_m: Some(self.height),
+                ..BmiRequest::default()
+            },
+            units::UnitSystem::Imperial => BmiRequest {
+                units: units::UnitSystem::Imperial,
+                weight_lb: Some(self.weight),
+                height_in: Some(self.height),
+                ..BmiRequest::default()
+            },
+        }
+    }
+}
+
+/// Encodes `measurement` issued at `issued_at` (Unix seconds) as a token,
+/// signed when `key` is given.
+///
+/// # Errors
+///
+/// Returns a [`BmiError`] if weight or height is not positive or does not
+/// fit the fixed-width fields.
+pub fn encode_measurement_token(
+    measurement: Measurement,
+    issued_at: u64,
+    key: Option<&[u8]>,
+) -> Result<String, BmiError> {
+    let (weight_scale, height_scale) = measurement.scales();
+    let weight = scaled(measurement.weight, weight_scale)
+        .ok_or_else(|| BmiError::InvalidWeight("Weight must be a positive number".to_string()))?;
+    let height = scaled(measurement.height, height_scale)
+        .ok_or_else(|| BmiError::InvalidHeight("Height must be a positive number".to_string()))?;
+    let issued_at = u32::try_from(issued_at)
+        .map_err(|_| BmiError::Internal("Timestamp does not fit the token".to_string()))?;
+
+    let mut flags = 0;
+    if measurement.units == units::UnitSystem::Imperial {
+        flags |= FLAG_IMPERIAL;
+    }
+    if key.is_some() {
+        flags |= FLAG_SIGNED;
+    }
+    let mut bytes = Vec::with_capacity(RECORD_LEN + SIGNATURE_LEN);
+    bytes.extend([VERSION, flags]);
+    bytes.extend(weight.to_be_bytes());
+    bytes.extend(height.to_be_bytes());
+    bytes.extend(issued_at.to_be_bytes());
+    if let Some(key) = key {
+        let signature = mac(key, &bytes).finalize().into_bytes();
+        bytes.extend(&signature[..SIGNATURE_LEN]);
+    }
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Rounds `value * scale` to a positive `u32`, or `None` if it cannot fit.
+fn scaled(value: f64, scale: f64) -> Option<u32> {
+    let scaled = (value * scale).round();
+    (scaled >= 1.0 && scaled <= f64::from(u32::MAX)).then_some(scaled as u32)
+}
+
+/// Starts an HMAC over `bytes`.
+fn mac(key: &[u8], bytes: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(bytes);
+    mac
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Verification settings for incoming tokens.
+#[derive(Clone, Debug)]
+pub struct TokenPolicy {
+    /// Signing key; when set, unsigned tokens are rejected.
+    key: Option<Vec<u8>>,
+    /// Oldest token accepted.
+    max_age: Duration,
+}
+
+impl Default for TokenPolicy {
+    fn default() -> Self {
+        Self {
+            key: None,
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+}
+
+impl TokenPolicy {
+    /// Creates a policy with an optional signing key.
+    pub fn new(key: Option<Vec<u8>>, max_age: Duration) -> Self {
+        Self { key, max_age }
+    }
+
+    /// Returns true if unsigned tokens are rejected.
+    pub fn requires_signature(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Decodes and verifies `token` at time `now` (Unix seconds).
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`BmiError`] if:
+    /// - The token is not a well-formed record, or was issued more than
+    ///   five minutes in the future (`malformed_token`)
+    /// - The signature is missing, unexpected, or wrong (`tampered_token`)
+    /// - The token is older than the max age (`expired_token`)
+    pub fn decode(&self, token: &str, now: u64) -> Result<Measurement, BmiError> {
+        let malformed = || BmiError::MalformedToken("Token is not a valid measurement".to_string());
+        let bytes = URL_SAFE_NO_PAD.decode(token).map_err(|_| malformed())?;
+        if bytes.len() < RECORD_LEN || bytes[0] != VERSION {
+            return Err(malformed());
+        }
+        let (record, signature) = bytes.split_at(RECORD_LEN);
+        let flags = record[1];
+        let signed = flags & FLAG_SIGNED != 0;
+        let expected_len = if signed { SIGNATURE_LEN } else { 0 };
+        if signature.len() != expected_len || flags & !(FLAG_IMPERIAL | FLAG_SIGNED) != 0 {
+            return Err(malformed());
+        }
+
+        match (&self.key, signed) {
+            (Some(key), true) => mac(key, record)
+                .verify_truncated_left(signature)
+                .map_err(|_| BmiError::TamperedToken("Token signature is invalid".to_string()))?,
+            (Some(_), false) => {
+                return Err(BmiError::TamperedToken("Token must be signed".to_string()))
+            }
+            (None, true) => {
+                return Err(BmiError::TamperedToken(
+                    "Signed tokens cannot be verified without BMI_TOKEN_KEY".to_string(),
+                ))
+            }
+            (None, false) => {}
+        }
+
+        let field = |range: std::ops::Range<usize>| {
+            u32::from_be_bytes(record[range].try_into().expect("four-byte field"))
+        };
+        let issued_at = u64::from(field(10..14));
+        if issued_at > now.saturating_add(MAX_CLOCK_SKEW_SECS) {
+            return Err(BmiError::MalformedToken(
+                "Token is issued in the future".to_string(),
+            ));
+        }
+        if now.saturating_sub(issued_at) > self.max_age.as_secs() {
+            return Err(BmiError::ExpiredToken(format!(
+                "Token is older than {} seconds",
+                self.max_age.as_secs()
+            )));
+        }
+
+        let units = if flags & FLAG_IMPERIAL != 0 {
+            units::UnitSystem::Imperial
+        } else {
+            units::UnitSystem::Metric
+        };
+        let template = Measurement {
+            units,
+            weight: 0.0,
+            height: 0.0,
+        };
+        let (weight_scale, height_scale) = template.scales();
+        Ok(Measurement {
+            weight: f64::from(field(2..6)) / weight_scale,
+            height: f64::from(field(6..10)) / height_scale,
+            ..template
+        })
+    }
+}
+
+/// Handles `GET /api/calculate/t/{token}`.
+///
+/// # Errors
+///
+/// Returns HTTP 400 with a JSON [`BmiError`] if the token is malformed,
+/// tampered with or expired, and HTTP 422 if its measurements fail
+/// validation.
+pub async fn token_handler(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<crate::BmiResponse>, BmiError> {
+    let measurement = state.measurement_tokens.decode(&token, now())?;
+    process_bmi_request_with(&measurement.to_request(), &state.bounds).map(Json)
+}
+
+/// Mints a token for `measurement` (the `token` subcommand).
+///
+/// ```text
+/// bmi_calculator token --weight-kg 70 --height-m 1.75
+/// bmi_calculator token --weight-lb 154 --height-in 69
+/// ```
+///
+/// The token is signed with `key` (`secrets.token_key`) when it is set.
+///
+/// # Errors
+///
+/// Returns error if the measurements cannot be encoded.
+pub fn mint(measurement: Measurement, key: Option<&[u8]>) -> Result<String> {
+    let token = encode_measurement_token(measurement, now(), key)?;
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::StatusCode};
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    const KEY: &[u8] = b"kiosk-secret";
+    const ISSUED_AT: u64 = 1_760_000_000;
+
+    fn metric() -> Measurement {
+        Measurement {
+            units: units::UnitSystem::Metric,
+            weight: 70.0,
+            height: 1.75,
+        }
+    }
+
+    fn signed_policy() -> TokenPolicy {
+        TokenPolicy::new(Some(KEY.to_vec()), DEFAULT_MAX_AGE)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let imperial = Measurement {
+            units: units::UnitSystem::Imperial,
+            weight: 154.25,
+            height: 69.5,
+        };
+        for measurement in [metric(), imperial] {
+            let token = encode_measurement_token(measurement, ISSUED_AT, None).unwrap();
+            assert_eq!(
+                TokenPolicy::default().decode(&token, ISSUED_AT),
+                Ok(measurement)
+            );
+
+            let token = encode_measurement_token(measurement, ISSUED_AT, Some(KEY)).unwrap();
+            assert_eq!(signed_policy().decode(&token, ISSUED_AT), Ok(measurement));
+        }
+
+        let token = encode_measurement_token(metric(), ISSUED_AT, Some(KEY)).unwrap();
+        assert_eq!(token.len(), 40);
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let token = encode_measurement_token(metric(), ISSUED_AT, Some(KEY)).unwrap();
+        let mut bytes = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        bytes[5] ^= 1;
+        let tampered = URL_SAFE_NO_PAD.encode(&bytes);
+        let code =
+            |token: &str, policy: &TokenPolicy| policy.decode(token, ISSUED_AT).unwrap_err().code();
+
+        assert_eq!(code(&tampered, &signed_policy()), "tampered_token");
+        let unsigned = encode_measurement_token(metric(), ISSUED_AT, None).unwrap();
+        assert_eq!(code(&unsigned, &signed_policy()), "tampered_token");
+        let other_key = TokenPolicy::new(Some(b"other".to_vec()), DEFAULT_MAX_AGE);
+        assert_eq!(code(&token, &other_key), "tampered_token");
+        assert_eq!(code("not a token!", &signed_policy()), "malformed_token");
+        assert_eq!(code(&token[..20], &signed_policy()), "malformed_token");
+    }
+
+    #[test]
+    fn test_max_age() {
+        let policy = TokenPolicy::new(Some(KEY.to_vec()), Duration::from_secs(60));
+        let token = encode_measurement_token(metric(), ISSUED_AT, Some(KEY)).unwrap();
+
+        assert!(policy.decode(&token, ISSUED_AT + 60).is_ok());
+        assert_eq!(
+            policy.decode(&token, ISSUED_AT + 61).unwrap_err().code(),
+            "expired_token"
+        );
+    }
+
+    #[test]
+    fn test_future_tokens_are_rejected() {
+        let policy = signed_policy();
+        let skewed = encode_measurement_token(metric(), ISSUED_AT + 300, Some(KEY)).unwrap();
+        assert!(policy.decode(&skewed, ISSUED_AT).is_ok());
+
+        let future = encode_measurement_token(metric(), ISSUED_AT + 3600, Some(KEY)).unwrap();
+        assert_eq!(
+            policy.decode(&future, ISSUED_AT).unwrap_err().code(),
+            "malformed_token"
+        );
+    }
+
+    #[test]
+    fn test_mint_encodes_the_measurement() {
+        let token = mint(metric(), None).unwrap();
+        let decoded = TokenPolicy::default().decode(&token, now()).unwrap();
+        assert_eq!((decoded.weight, decoded.height), (70.0, 1.75));
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_returns_calculation() {
+        let state = AppState {
+            measurement_tokens: Arc::new(signed_policy()),
+            ..AppState::default()
+        };
+        let token = encode_measurement_token(metric(), now(), Some(KEY)).unwrap();
+        let get = |uri: String| {
+            crate::build_router(state.clone())
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        };
+
+        let response = get(format!("/api/calculate/t/{token}")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["category"], "Normal weight");
+
+        let expired = encode_measurement_token(metric(), ISSUED_AT, Some(KEY)).unwrap();
+        let response = get(format!("/api/calculate/t/{expired}")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
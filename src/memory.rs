@@ -0,0 +1,310 @@
+//! Memory budget for constrained deployments.
+//!
+//! `MEMORY_BUDGET_MB` (or `memory_budget_mb` in `bmi.toml`) sizes the bounded
+//! structures from one number: outstanding form tokens, entries per batch
+//! request, and concurrent connections each get a share of the budget, never
+//! more than their unbudgeted defaults. The derivation is logged at startup.
+//!
+//! With a budget, [`spawn_sampler`] reads mimalloc's committed memory into the
+//! `process_memory_bytes` gauge every few seconds. Above [`HIGH_WATER`] of
+//! the budget the batch endpoint answers 503 and a `memory.pressure` event is
+//! logged; once usage falls below [`LOW_WATER`] it serves again.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    extract::{Request, State},
+    http::header::RETRY_AFTER,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{event, Level};
+
+use crate::{batch, connection, error::BmiError, telemetry, AppState};
+
+/// Fraction of the budget above which bulk requests are shed.
+pub const HIGH_WATER: f64 = 0.85;
+
+/// Fraction of the budget below which shedding stops.
+pub const LOW_WATER: f64 = 0.70;
+
+/// How often [`spawn_sampler`] reads allocator stats.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Seconds clients are asked to wait while shedding.
+const SHED_RETRY_AFTER_SECS: u64 = 10;
+
+/// Budget share and rough cost per item of each bounded structure.
+const FORM_TOKEN_SHARE: f64 = 0.05;
+const FORM_TOKEN_BYTES: u64 = 1024;
+const BATCH_SHARE: f64 = 0.25;
+const BATCH_ENTRY_BYTES: u64 = 2048;
+const CONNECTION_SHARE: f64 = 0.25;
+const CONNECTION_BYTES: u64 = 64 * 1024;
+
+/// Capacities derived from the budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sizing {
+    /// Outstanding form tokens kept.
+    pub form_tokens: usize,
+    /// Entries accepted per batch request.
+    pub batch_entries: usize,
+    /// Concurrent connections across all clients.
+    pub connections: usize,
+}
+
+impl Default for Sizing {
+    fn default() -> Self {
+        Self {
+            form_tokens: crate::form_token::MAX_TOKENS,
+            batch_entries: batch::MAX_ENTRIES,
+            connections: connection::Limits::default().max_total,
+        }
+    }
+}
+
+impl Sizing {
+    /// Splits `budget_bytes` across the structures, capped at the defaults.
+    pub fn for_budget(budget_bytes: u64) -> Self {
+        let defaults = Self::default();
+        let share = |fraction: f64, item_bytes: u64, default: usize| {
+            let items = (budget_bytes as f64 * fraction) as u64 / item_bytes;
+            usize::try_from(items)
+                .unwrap_or(usize::MAX)
+                .clamp(1, default)
+        };
+        Self {
+            form_tokens: share(FORM_TOKEN_SHARE, FORM_TOKEN_BYTES, defaults.form_tokens),
+            batch_entries: share(BATCH_SHARE, BATCH_ENTRY_BYTES, defaults.batch_entries),
+            connections: share(CONNECTION_SHARE, CONNECTION_BYTES, defaults.connections),
+        }
+    }
+}
+
+/// Budget, derived sizes and the current pressure state.
+#[derive(Debug, Default)]
+pub struct Memory {
+    budget_bytes: Option<u64>,
+    sizing: Sizing,
+    under_pressure: AtomicBool,
+}
+
+impl Memory {
+    /// Creates the state for a budget in MiB; `None` keeps the defaults and
+    /// never sheds.
+    pub fn new(budget_mb: Option<u64>) -> Self {
+        let budget_bytes = budget_mb.map(|mb| mb.saturating_mul(1024 * 1024));
+        let sizing = budget_bytes.map_or_else(Sizing::default, Sizing::for_budget);
+        Self {
+            budget_bytes,
+            sizing,
+            under_pressure: AtomicBool::new(false),
+        }
+    }
+
+    /// Capacities to build bounded structures with.
+    pub fn sizing(&self) -> Sizing {
+        self.sizing
+    }
+
+    /// Returns true while bulk requests are being shed.
+    pub fn under_pressure(&self) -> bool {
+        self.under_pressure.load(Ordering::Relaxed)
+    }
+
+    /// Logs how the budget was split.
+    pub fn log_sizing(&self) {
+        let Some(budget_bytes) = self.budget_bytes else {
+            return;
+        };
+        let sizing = self.sizing;
+        event!(
+            name: "memory.budget.sizing",
+            Level::INFO,
+            budget_mb = budget_bytes / (1024 * 1024),
+            form_tokens = sizing.form_tokens,
+            batch_entries = sizing.batch_entries,
+            connections = sizing.connections,
+            "Memory budget {{budget_mb}} MiB: {{form_tokens}} form tokens, {{batch_entries}} batch entries, {{connections}} connections"
+        );
+    }
+
+    /// Updates the pressure state from a usage sample.
+    pub fn observe(&self, used_bytes: u64) {
+        let Some(budget_bytes) = self.budget_bytes else {
+            return;
+        };
+        let usage = used_bytes as f64 / budget_bytes as f64;
+        let was = self.under_pressure();
+        let now = if was {
+            usage >= LOW_WATER
+        } else {
+            usage > HIGH_WATER
+        };
+        if now == was {
+            return;
+        }
+        self.under_pressure.store(now, Ordering::Relaxed);
+        let used_mb = used_bytes / (1024 * 1024);
+        if now {
+            event!(
+                name: "memory.pressure",
+                Level::WARN,
+                used_mb,
+                budget_mb = budget_bytes / (1024 * 1024),
+                "Memory at {{used_mb}} MiB of {{budget_mb}} MiB; shedding bulk requests"
+            );
+        } else {
+            event!(
+                name: "memory.pressure.relieved",
+                Level::INFO,
+                used_mb,
+                "Memory back to {{used_mb}} MiB; serving bulk requests again"
+            );
+        }
+    }
+}
+
+/// Memory mimalloc has committed for the process.
+fn committed_bytes() -> u64 {
+    let mut current_commit = 0usize;
+    // SAFETY: every out-parameter is nullable; the only one passed points to
+    // a live local.
+    unsafe {
+        libmimalloc_sys::mi_process_info(
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut current_commit,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+    }
+    current_commit as u64
+}
+
+/// Samples allocator stats while a budget is set.
+pub fn spawn_sampler(memory: Arc<Memory>) {
+    if memory.budget_bytes.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let used = committed_bytes();
+            metrics::gauge!("process_memory_bytes").set(used as f64);
+            memory.observe(used);
+        }
+    });
+}
+
+/// Refuses bulk requests while memory is under pressure.
+pub async fn shed(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if !state.memory.under_pressure() {
+        return next.run(request).await;
+    }
+    telemetry::load_shed();
+    let error = BmiError::Overloaded("Server is low on memory; retry later".to_string());
+    ([(RETRY_AFTER, SHED_RETRY_AFTER_SECS.to_string())], error).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{header::CONTENT_TYPE, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    const MIB: u64 = 1024 * 1024;
+
+    async fn post(state: &AppState, uri: &str, body: &str) -> StatusCode {
+        let request = Request::post(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        crate::build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn test_sizing_follows_budget() {
+        assert_eq!(Memory::new(None).sizing(), Sizing::default());
+        assert_eq!(Memory::new(Some(4096)).sizing(), Sizing::default());
+
+        let small = Memory::new(Some(128)).sizing();
+        assert_eq!(small.connections, 512);
+        assert_eq!(small.form_tokens, 6553);
+        assert_eq!(small.batch_entries, batch::MAX_ENTRIES);
+        let tiny = Memory::new(Some(16)).sizing();
+        assert_eq!(tiny.batch_entries, 2048);
+        assert_eq!(Memory::new(Some(0)).sizing().connections, 1);
+    }
+
+    #[test]
+    fn test_pressure_has_hysteresis() {
+        let memory = Memory::new(Some(100));
+        for (used_mb, expected) in [
+            (50, false),
+            (86, true),
+            (75, true),
+            (69, false),
+            (80, false),
+        ] {
+            memory.observe(used_mb * MIB);
+            assert_eq!(memory.under_pressure(), expected, "{used_mb} MiB");
+        }
+
+        let unbudgeted = Memory::new(None);
+        unbudgeted.observe(u64::MAX);
+        assert!(!unbudgeted.under_pressure());
+    }
+
+    #[tokio::test]
+    async fn test_batch_is_shed_under_pressure() {
+        let state = AppState {
+            memory: Arc::new(Memory::new(Some(64))),
+            ..AppState::default()
+        };
+        let batch = r#"[{"weight_kg": 70, "height_m": 1.75}]"#;
+        let single = r#"{"weight_kg": 70, "height_m": 1.75}"#;
+
+        state.memory.observe(60 * MIB);
+        assert_eq!(
+            post(&state, "/api/calculate/batch", batch).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(post(&state, "/api/calculate", single).await, StatusCode::OK);
+
+        state.memory.observe(40 * MIB);
+        assert_eq!(
+            post(&state, "/api/calculate/batch", batch).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_budget_caps_batch_size() {
+        let state = AppState {
+            memory: Arc::new(Memory::new(Some(1))),
+            ..AppState::default()
+        };
+        let entries = vec![r#"{"weight_kg": 70, "height_m": 1.75}"#; 129].join(",");
+
+        assert_eq!(
+            post(&state, "/api/calculate/batch", &format!("[{entries}]")).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! Mock mode serving canned API responses for frontend development.
+//!
+//! Enabled with `[mock] enabled = true` (`MOCK_MODE=1`) or the `--mock` flag.
+//! The router keeps its production shape; [`respond`] sits next to the
+//! handlers and answers from fixtures instead of running them, so no
+//! calculation or state change takes place. Routes without a fixture (e.g. the static changelog) run normally.
+//!
+//! Knobs in `[mock]`:
+//! - `fixtures_dir` (`MOCK_FIXTURES_DIR`): directory of `<METHOD>_<path>.json`
+//!   overrides, e.g. `POST_api_calculate.json` for `POST /api/calculate`
+//! - `latency_ms` (`MOCK_LATENCY_MS`): artificial delay before every response
+//! - `error_rate` (`MOCK_ERROR_RATE`): fraction of requests (0.0–1.0)
+//!   answered with a 500
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{error::BmiError, AppState};
+
+/// Header added to every API response while mock mode is active.
+pub const MOCK_HEADER: HeaderName = HeaderName::from_static("x-mock-response");
+
+/// Built-in fixtures keyed by `"<METHOD> <path>"`.
+const DEFAULT_FIXTURES: &str = include_str!("mock_fixtures.json");
+
+/// A canned response.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Fixture {
+    /// HTTP status code to return.
+    pub status: u16,
+    /// JSON body to return.
+    pub body: Value,
+}
+
+/// Mock mode settings and fixture set.
+#[derive(Debug)]
+pub struct Mock {
+    fixtures: HashMap<String, Fixture>,
+    latency: Duration,
+    error_rate: f64,
+    requests: AtomicU64,
+}
+
+impl Mock {
+    /// Creates mock mode with the embedded fixtures and no knobs set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the embedded fixture file is malformed; unit tests cover it.
+    pub fn new() -> Self {
+        Self {
+            fixtures: serde_json::from_str(DEFAULT_FIXTURES)
+                .expect("embedded mock_fixtures.json must be valid"),
+            latency: Duration::ZERO,
+            error_rate: 0.0,
+            requests: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the artificial latency added to each response.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the fraction of requests answered with an injected 500.
+    pub fn with_error_rate(mut self, error_rate: f64) -> Self {
+        self.error_rate = error_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Overrides fixtures with `<METHOD>_<path>.json` files from `dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the directory or a fixture file cannot be read or parsed.
+    pub fn with_fixtures_dir(mut self, dir: &Path) -> Result<Self> {
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("cannot read mock fixtures directory {}", dir.display()))?;
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(key) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(fixture_key)
+            else {
+                bail!(
+                    "mock fixture {} must be named <METHOD>_<path>.json",
+                    path.display()
+                );
+            };
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("cannot read mock fixture {}", path.display()))?;
+            let fixture = serde_json::from_str(&text)
+                .with_context(|| format!("invalid mock fixture {}", path.display()))?;
+            self.fixtures.insert(key, fixture);
+        }
+        Ok(self)
+    }
+
+    /// Decides whether the `n`-th request gets an injected error.
+    ///
+    /// Errors are spread evenly so a rate of 0.25 fails exactly one request
+    /// in four, keeping runs reproducible.
+    fn should_fail(&self, n: u64) -> bool {
+        let before = (n as f64 * self.error_rate).floor();
+        let after = ((n + 1) as f64 * self.error_rate).floor();
+        after > before
+    }
+}
+
+impl Default for Mock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Converts a fixture file stem like `POST_api_calculate` to `POST /api/calculate`.
+fn fixture_key(stem: &str) -> Option<String> {
+    let (method, path) = stem.split_once('_')?;
+    if method.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{} /{}",
+        method.to_uppercase(),
+        path.replace('_', "/")
+    ))
+}
+
+/// Answers API requests from fixtures while mock mode is active.
+pub async fn respond(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(mock) = state.mock.as_deref() else {
+        return next.run(request).await;
+    };
+
+    if !mock.latency.is_zero() {
+        tokio::time::sleep(mock.latency).await;
+    }
+
+    let n = mock.requests.fetch_add(1, Ordering::Relaxed);
+    let key = format!("{} {}", request.method(), request.uri().path());
+
+    let mut response = if mock.should_fail(n) {
+        BmiError::Internal("Mock error injected".to_string()).into_response()
+    } else if let Some(fixture) = mock.fixtures.get(&key) {
+        let status = StatusCode::from_u16(fixture.status).unwrap_or(StatusCode::OK);
+        (status, Json(fixture.body.clone())).into_response()
+    } else {
+        next.run(request).await
+    };
+
+    response
+        .headers_mut()
+        .insert(MOCK_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header::CONTENT_TYPE};
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn state(mock: Mock) -> AppState {
+        AppState {
+            mock: Some(Arc::new(mock)),
+            admin_token: Some(Arc::from("secret")),
+            ..AppState::default()
+        }
+    }
+
+    fn calculate() -> Request {
+        Request::post("/api/calculate")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"weight_kg": 500.0, "height_m": 1.2}"#))
+            .unwrap()
+    }
+
+    async fn send(state: &AppState, request: Request) -> (StatusCode, bool, Value) {
+        let response = crate::build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let mocked = response.headers().get(MOCK_HEADER).is_some();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, mocked, body)
+    }
+
+    #[test]
+    fn test_embedded_fixtures_parse() {
+        assert!(Mock::new().fixtures.contains_key("POST /api/calculate"));
+    }
+
+    #[test]
+    fn test_calculate_fixtures_match_the_response_schema() {
+        let production = serde_json::to_value(
+            crate::process_bmi_request(&crate::BmiRequest {
+                weight_kg: Some(70.0),
+                height_m: Some(1.75),
+                ..crate::BmiRequest::default()
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        let fields = |body: &Value| {
+            let mut fields: Vec<String> = body.as_object().unwrap().keys().cloned().collect();
+            fields.sort();
+            fields
+        };
+
+        let mock = Mock::new();
+        for key in ["POST /api/calculate", "GET /api/calculate"] {
+            let body = &mock.fixtures[key].body;
+            if let Err(error) = serde_json::from_value::<crate::BmiResponse>(body.clone()) {
+                panic!("{key}: {error}");
+            }
+            assert_eq!(fields(body), fields(&production), "{key}");
+        }
+    }
+
+    #[test]
+    fn test_fixture_key() {
+        assert_eq!(
+            fixture_key("POST_api_calculate").as_deref(),
+            Some("POST /api/calculate")
+        );
+        assert_eq!(
+            fixture_key("get_api_form-token").as_deref(),
+            Some("GET /api/form-token")
+        );
+        assert_eq!(fixture_key("calculate"), None);
+    }
+
+    #[tokio::test]
+    async fn test_fixture_is_served_instead_of_calculation() {
+        let (status, mocked, body) = send(&state(Mock::new()), calculate()).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(mocked);
+        assert_eq!(body["category"], "Normal weight");
+        assert!((body["bmi"].as_f64().unwrap() - 22.857).abs() < 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_no_side_effects_on_state() {
+        let state = state(Mock::new());
+        let request = Request::post("/api/admin/maintenance")
+            .header(CONTENT_TYPE, "application/json")
+            .header("authorization", "Bearer secret")
+            .body(Body::from(r#"{"enabled": true}"#))
+            .unwrap();
+
+        let (status, mocked, _) = send(&state, request).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(mocked);
+        assert!(state.maintenance.current().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_routes_without_fixture_are_still_marked() {
+        let request = Request::get("/api/changelog").body(Body::empty()).unwrap();
+        let (status, mocked, body) = send(&state(Mock::new()), request).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(mocked);
+        assert!(body["entries"].is_array());
+    }
+
+    #[tokio::test]
+    async fn test_error_rate_is_deterministic() {
+        let state = state(Mock::new().with_error_rate(0.5));
+        let mut statuses = Vec::new();
+        for _ in 0..4 {
+            statuses.push(send(&state, calculate()).await.0);
+        }
+
+        let failures = statuses
+            .iter()
+            .filter(|status| **status == StatusCode::INTERNAL_SERVER_ERROR)
+            .count();
+        assert_eq!(failures, 2);
+    }
+
+    #[tokio::test]
+    async fn test_latency_is_applied() {
+        let state = state(Mock::new().with_latency(Duration::from_millis(50)));
+        let started = std::time::Instant::now();
+        send(&state, calculate()).await;
+
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_fixtures_dir_overrides_defaults() {
+        let dir = std::env::temp_dir().join(format!("bmi-mock-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("POST_api_calculate.json"),
+            r#"{"status": 200, "body": {"bmi": 31.0, "category": "Obese"}}"#,
+        )
+        .unwrap();
+
+        let mock = Mock::new().with_fixtures_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let (_, _, body) = send(&state(mock), calculate()).await;
+
+        assert_eq!(body["category"], "Obese");
+    }
+
+    #[test]
+    fn test_missing_fixtures_dir_is_an_error() {
+        let missing = std::env::temp_dir().join("bmi-mock-does-not-exist");
+        assert!(Mock::new().with_fixtures_dir(&missing).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Response media type negotiation from `Accept`.
+//!
+//! A route lists the [`Format`]s it can produce, in order of preference, and
+//! [`Format::requested`] picks the one the client ranks highest, unless a
+//! `format` query parameter (`json`, `text`, `xml`) names one. Media
+//! ranges (`text/*`, `*/*`) and `q` values follow RFC 9110: the most specific
+//! range matching a format sets its quality, `q=0` rules it out, and ties go
+//! to the route's order. A missing or empty `Accept` gets the first format;
+//! when nothing offered is acceptable the route answers
+//! `406 not_acceptable`, as JSON since the client accepts nothing else.
+
+use axum::{
+    http::{header::ACCEPT, HeaderMap},
+    response::{IntoResponse, Response},
+};
+
+use crate::error::BmiError;
+
+/// A response encoding a route can offer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `application/json`.
+    Json,
+    /// `text/plain`, a human-readable line.
+    Text,
+    /// `application/xml` (see `xml`).
+    #[cfg(feature = "xml")]
+    Xml,
+}
+
+impl Format {
+    /// Media type matched against `Accept`.
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Text => "text/plain",
+            #[cfg(feature = "xml")]
+            Self::Xml => crate::xml::XML_CONTENT_TYPE,
+        }
+    }
+
+    /// Name used by the `format` query parameter.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Text => "text",
+            #[cfg(feature = "xml")]
+            Self::Xml => "xml",
+        }
+    }
+
+    /// Picks the format of `offered` named by `format` in `query`, else the
+    /// one the client prefers.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BmiError::NotAcceptable`] if `format` names no offered
+    /// format, or `Accept` rules out every one.
+    pub fn requested(
+        headers: &HeaderMap,
+        query: Option<&str>,
+        offered: &[Self],
+    ) -> Result<Self, BmiError> {
+        let available = || {
+            offered
+                .iter()
+                .map(|format| format.media_type())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let named = query
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+            .find_map(|pair| pair.strip_prefix("format="));
+        if let Some(name) = named {
+            return offered
+                .iter()
+                .copied()
+                .find(|format| format.name() == name)
+                .ok_or_else(|| {
+                    BmiError::NotAcceptable(format!(
+                        "Unknown format {name:?}; available: {}",
+                        available()
+                    ))
+                });
+        }
+
+        let accept: Vec<&str> = headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        let accept = accept.join(",");
+        select(&accept, offered).ok_or_else(|| {
+            BmiError::NotAcceptable(format!(
+                "Cannot produce {accept}; available: {}",
+                available()
+            ))
+        })
+    }
+
+    /// Renders `error` in this format: the usual JSON body, its message as
+    /// plain text, or an XML `<error>`.
+    pub fn error(self, error: BmiError) -> Response {
+        match self {
+            Self::Json => error.into_response(),
+            Self::Text => (error.status(), error.to_string()).into_response(),
+            #[cfg(feature = "xml")]
+            Self::Xml => crate::xml::error_response(error),
+        }
+    }
+}
+
+/// Quality `accept` gives `media_type`, from its most specific matching
+/// range; `None` if no range matches.
+fn quality(accept: &str, media_type: &str) -> Option<f32> {
+    let (kind, _) = media_type.split_once('/')?;
+    let mut best: Option<(u8, f32)> = None;
+    for entry in accept.split(',') {
+        let mut parts = entry.split(';');
+        let range = parts.next().unwrap_or_default().trim();
+        let specificity = if range.eq_ignore_ascii_case(media_type) {
+            2
+        } else if range
+            .strip_suffix("/*")
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(kind))
+        {
+            1
+        } else if range == "*/*" {
+            0
+        } else {
+            continue;
+        };
+        let quality = match parts.find_map(|part| part.trim().strip_prefix("q=")) {
+            Some(quality) => match quality.trim().parse::<f32>() {
+                Ok(quality) => quality,
+                Err(_) => continue,
+            },
+            None => 1.0,
+        };
+        if best.is_none_or(|(current, _)| specificity > current) {
+            best = Some((specificity, quality));
+        }
+    }
+    best.map(|(_, quality)| quality)
+}
+
+/// Returns the format of `offered` that `accept` ranks highest; the first
+/// one if `accept` is blank.
+pub fn select(accept: &str, offered: &[Format]) -> Option<Format> {
+    if accept.trim().is_empty() {
+        return offered.first().copied();
+    }
+    let mut best: Option<(Format, f32)> = None;
+    for &format in offered {
+        let Some(quality) = quality(accept, format.media_type()) else {
+            continue;
+        };
+        if quality > 0.0 && best.is_none_or(|(_, current)| quality > current) {
+            best = Some((format, quality));
+        }
+    }
+    best.map(|(format, _)| format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFERED: [Format; 2] = [Format::Json, Format::Text];
+
+    #[test]
+    fn test_select_follows_accept() {
+        for (accept, expected) in [
+            ("", Some(Format::Json)),
+            ("application/json", Some(Format::Json)),
+            ("text/plain", Some(Format::Text)),
+            ("TEXT/PLAIN; charset=utf-8", Some(Format::Text)),
+            ("text/*", Some(Format::Text)),
+            ("*/*", Some(Format::Json)),
+            ("application/json;q=0.5, text/plain", Some(Format::Text)),
+            ("text/plain;q=0.9, */*;q=0.1", Some(Format::Text)),
+            ("*/*, application/json;q=0", Some(Format::Text)),
+            ("text/html", None),
+            ("text/html, text/plain;q=0", None),
+        ] {
+            assert_eq!(select(accept, &OFFERED), expected, "{accept:?}");
+        }
+    }
+
+    #[test]
+    fn test_requested_reports_what_is_available() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            Format::requested(&headers, None, &OFFERED),
+            Ok(Format::Json)
+        );
+
+        headers.insert(ACCEPT, "text/html".parse().unwrap());
+        let error = Format::requested(&headers, None, &OFFERED).unwrap_err();
+        assert_eq!(error.code(), "not_acceptable");
+        assert!(error.message().contains("application/json, text/plain"));
+
+        assert_eq!(
+            Format::requested(&headers, Some("weight_kg=70&format=text"), &OFFERED),
+            Ok(Format::Text)
+        );
+        let error = Format::requested(&HeaderMap::new(), Some("format=csv"), &OFFERED);
+        assert_eq!(error.unwrap_err().code(), "not_acceptable");
+    }
+}
@@ -0,0 +1,85 @@
+//! OpenAPI 3 description of the calculation API.
+//!
+//! The document is derived from the same request, response and error types
+//! the handlers use, so it cannot drift from them. It is served at
+//! `GET /api/openapi.json`, with an interactive Swagger UI at `/docs`.
+
+use axum::Router;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Where the generated document is served.
+pub const SPEC_PATH: &str = "/api/openapi.json";
+
+/// Paths and schemas of the calculation API.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "BMI Calculator API",
+        description = "Body Mass Index calculation with WHO categories."
+    ),
+    paths(
+        crate::http::calculate_bmi_handler,
+        crate::http::calculate_query_handler,
+        crate::batch::batch_handler,
+        crate::storage::history_handler,
+        crate::history_export::export_handler,
+        crate::capabilities::capabilities_handler
+    ),
+    tags(
+        (name = "calculation", description = "BMI calculations"),
+        (name = "history", description = "Stored calculations"),
+        (name = "discovery", description = "Server features and limits")
+    )
+)]
+pub struct ApiDoc;
+
+/// Routes serving the document and Swagger UI.
+pub fn routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    SwaggerUi::new("/docs")
+        .url(SPEC_PATH, ApiDoc::openapi())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::StatusCode};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn get(uri: &str) -> (StatusCode, Vec<u8>) {
+        let response = crate::build_router(crate::AppState::default())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_serves_spec_with_calculate_schemas() {
+        let (status, body) = get(SPEC_PATH).await;
+        let spec: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        let calculate = &spec["paths"]["/api/calculate"];
+        assert!(calculate["post"].is_object() && calculate["get"].is_object());
+        let schemas = &spec["components"]["schemas"];
+        let weight = &schemas["BmiRequest"]["properties"]["weight_kg"];
+        assert_eq!(weight["exclusiveMinimum"], 0.0);
+        assert!(schemas["BmiRequest"]["example"].is_object());
+        assert!(schemas["BmiResponse"]["properties"]["category"].is_object());
+        assert!(schemas["ErrorResponse"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_serves_swagger_ui() {
+        let (status, body) = get("/docs/").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(String::from_utf8_lossy(&body).contains("swagger"));
+    }
+}
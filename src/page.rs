@@ -0,0 +1,500 @@
+//! The main page, rendered from the `index.html` template (see `assets`).
+//!
+//! `GET /` fills the template's slots with the category legends, a fresh
+//! form token and, during maintenance, the banner. The form posts to
+//! `POST /calculate`, which with JavaScript on is never reached: the script
+//! intercepts the submission and calls `/api/calculate`. Without it, the
+//! browser sends the fields as `application/x-www-form-urlencoded`; they go
+//! through the same validation, calculation and history recording as the
+//! API, and the page comes back with the values kept, the result below the
+//! form, and each problem next to its field. A form that cannot be read at
+//! all (an unknown classification) gets the page with the error in place of
+//! the result.
+//!
+//! Rendering a submission needs the field and result slots. A template in
+//! `static_dir` that lacks one is answered with a 500 and logged as
+//! `page.render.failed`, rather than dropping the result silently.
+
+use anyhow::{bail, Result};
+use axum::{
+    extract::{rejection::FormRejection, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Extension, Form,
+};
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use crate::{
+    bmi, error::BmiError, html, http, maintenance, process_bmi_request_with, session::Session,
+    AppState, BmiRequest, BmiResponse,
+};
+
+/// Placeholder replaced by the maintenance banner.
+const BANNER_SLOT: &str = "<!-- maintenance-banner -->";
+
+/// Placeholder replaced by the category tables.
+const LEGEND_SLOT: &str = "<!-- category-legend -->";
+
+/// Placeholder replaced by a fresh form token.
+pub(crate) const FORM_TOKEN_SLOT: &str = "__FORM_TOKEN__";
+
+/// Placeholder replaced by the submitted weight.
+const WEIGHT_SLOT: &str = "__WEIGHT_KG__";
+
+/// Placeholder replaced by the submitted height.
+const HEIGHT_SLOT: &str = "__HEIGHT_M__";
+
+/// Placeholder replaced by the weight's error, if any.
+const WEIGHT_ERROR_SLOT: &str = "<!-- weight-error -->";
+
+/// Placeholder replaced by the height's error, if any.
+const HEIGHT_ERROR_SLOT: &str = "<!-- height-error -->";
+
+/// Placeholder replaced by the server-rendered result.
+const RESULT_SLOT: &str = "<!-- form-result -->";
+
+/// Slots a template must have to render a submission.
+const SUBMIT_SLOTS: [&str; 5] = [
+    WEIGHT_SLOT,
+    HEIGHT_SLOT,
+    WEIGHT_ERROR_SLOT,
+    HEIGHT_ERROR_SLOT,
+    RESULT_SLOT,
+];
+
+/// Form fields as submitted.
+///
+/// Measurements are kept as text so invalid input can be echoed back.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageForm {
+    /// Weight in kilograms, as typed.
+    #[serde(default)]
+    pub weight_kg: String,
+    /// Height in meters, as typed.
+    #[serde(default)]
+    pub height_m: String,
+    /// Threshold table to categorize with.
+    #[serde(default)]
+    pub classification: bmi::Classification,
+    /// Token embedded in the page; a resubmission replays the first result.
+    #[serde(default)]
+    pub form_token: Option<String>,
+}
+
+/// What went wrong with a submission, by field.
+#[derive(Debug, Default)]
+struct Problems {
+    weight_kg: Option<String>,
+    height_m: Option<String>,
+    /// Errors not tied to one field.
+    form: Option<String>,
+}
+
+/// Outcome rendered into the page.
+enum Outcome {
+    /// Nothing submitted yet.
+    Empty,
+    /// Successful calculation.
+    Result(Box<BmiResponse>),
+    /// Rejected submission.
+    Invalid(Problems),
+}
+
+/// Serves the main HTML page.
+pub async fn root_handler(State(state): State<AppState>) -> Response {
+    respond(
+        StatusCode::OK,
+        render(&state, &PageForm::default(), &Outcome::Empty).await,
+    )
+}
+
+/// Handles the form submitted without JavaScript and renders the page.
+///
+/// Fresh results are stored in the session's history like those of
+/// `/api/calculate`.
+///
+/// # Errors
+///
+/// Returns the page with the problems inline: HTTP 400 if a field is not a
+/// number, the form's status (e.g. 422 for an unknown classification) if it
+/// cannot be read, otherwise the status `/api/calculate` would answer with
+/// (422 for values out of bounds). HTTP 500 if the template cannot show the
+/// result.
+pub async fn submit_handler(
+    State(state): State<AppState>,
+    session: Option<Extension<Session>>,
+    form: Result<Form<PageForm>, FormRejection>,
+) -> Response {
+    let Form(form) = match form {
+        Ok(form) => form,
+        Err(rejection) => {
+            let problems = Problems {
+                form: Some(rejection.body_text()),
+                ..Problems::default()
+            };
+            let page = render(&state, &PageForm::default(), &Outcome::Invalid(problems)).await;
+            return respond(rejection.status(), page);
+        }
+    };
+    let session = session.map(|Extension(session)| session);
+    let (status, outcome) = match evaluate(&state, session.as_ref(), &form).await {
+        Ok(response) => (StatusCode::OK, Outcome::Result(Box::new(response))),
+        Err((status, problems)) => (status, Outcome::Invalid(problems)),
+    };
+    respond(status, render(&state, &form, &outcome).await)
+}
+
+fn respond(status: StatusCode, page: Result<String>) -> Response {
+    match page {
+        Ok(page) => (status, Html(page)).into_response(),
+        Err(error) => {
+            event!(
+                name: "page.render.failed",
+                Level::ERROR,
+                error = %error,
+                "Could not render the page: {{error}}"
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "The page could not be rendered",
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Parses a measurement typed into the form.
+fn number(text: &str, name: &str) -> Result<f64, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("Enter your {name} as a number"))
+}
+
+/// Runs the submission through the API's calculation and recording.
+async fn evaluate(
+    state: &AppState,
+    session: Option<&Session>,
+    form: &PageForm,
+) -> Result<BmiResponse, (StatusCode, Problems)> {
+    let (weight_kg, height_m) = match (
+        number(&form.weight_kg, "weight"),
+        number(&form.height_m, "height"),
+    ) {
+        (Ok(weight_kg), Ok(height_m)) => (weight_kg, height_m),
+        (weight_kg, height_m) => {
+            let problems = Problems {
+                weight_kg: weight_kg.err(),
+                height_m: height_m.err(),
+                form: None,
+            };
+            return Err((StatusCode::BAD_REQUEST, problems));
+        }
+    };
+    let request = BmiRequest {
+        weight_kg: Some(weight_kg),
+        height_m: Some(height_m),
+        classification: form.classification,
+        ..BmiRequest::default()
+    };
+    let mut fresh = false;
+    let outcome = state.form_tokens.redeem(form.form_token.as_deref(), || {
+        fresh = true;
+        process_bmi_request_with(&request, &state.bounds)
+    });
+    if fresh {
+        if let Ok(response) = &outcome {
+            http::record_fresh(state, session, &request, response).await;
+        }
+    }
+    outcome.map_err(|error| {
+        let status = error.status();
+        let mut problems = Problems::default();
+        match error {
+            BmiError::Validation(_, violations) => {
+                for violation in violations {
+                    let mut message = format!("Must be {}", violation.constraint);
+                    if let Some(hint) = violation.hint {
+                        message = format!("{message}: {hint}");
+                    }
+                    match violation.field {
+                        "weight_kg" => problems.weight_kg = Some(message),
+                        _ => problems.height_m = Some(message),
+                    }
+                }
+            }
+            error => problems.form = Some(error.to_string()),
+        }
+        (status, problems)
+    })
+}
+
+/// One legend per classification; the page shows the selected one.
+fn category_legends() -> String {
+    [
+        bmi::Classification::Standard,
+        bmi::Classification::AsianPacific,
+    ]
+    .into_iter()
+    .map(|classification| {
+        let hidden = if classification == bmi::Classification::default() {
+            ""
+        } else {
+            " hidden"
+        };
+        format!(
+            "<div data-classification=\"{}\"{hidden}>{}</div>",
+            classification.as_str(),
+            bmi::category_legend(classification)
+        )
+    })
+    .collect()
+}
+
+fn field_error(field: &str, message: Option<&String>) -> String {
+    message
+        .map(|message| {
+            format!(
+                r#"<div id="{field}-error" class="field-error" role="alert">{}</div>"#,
+                html::escape(message)
+            )
+        })
+        .unwrap_or_default()
+}
+
+fn result(response: &BmiResponse) -> String {
+    let range = &response.healthy_weight_range;
+    let warnings: String = response
+        .warnings
+        .iter()
+        .map(|warning| {
+            format!(
+                "\n            <div class=\"bmi-secondary\">{}</div>",
+                html::escape(warning)
+            )
+        })
+        .collect();
+    format!(
+        r#"<div id="formResult" class="result show" role="status">
+            <div class="bmi-value">{:.1}</div>
+            <div class="bmi-category">{}</div>
+            <div class="bmi-range">Healthy weight for your height: {:.1}–{:.1} kg</div>{warnings}
+        </div>"#,
+        response.bmi_rounded,
+        html::escape(&response.category_detailed),
+        range.min_kg,
+        range.max_kg,
+    )
+}
+
+/// Renders the page for the given form values and outcome.
+///
+/// # Errors
+///
+/// Returns error if `outcome` is a submission and the template lacks one of
+/// [`SUBMIT_SLOTS`].
+async fn render(state: &AppState, form: &PageForm, outcome: &Outcome) -> Result<String> {
+    let template = state.assets.index().await;
+    if !matches!(outcome, Outcome::Empty) {
+        if let Some(slot) = SUBMIT_SLOTS.iter().find(|slot| !template.contains(**slot)) {
+            bail!("the page template has no {slot} slot");
+        }
+    }
+
+    let banner = state
+        .maintenance
+        .current()
+        .map(|window| maintenance::banner(&window))
+        .unwrap_or_default();
+    let (weight_error, height_error, result) = match outcome {
+        Outcome::Empty => Default::default(),
+        Outcome::Result(response) => (String::new(), String::new(), result(response)),
+        Outcome::Invalid(problems) => (
+            field_error("weight_kg", problems.weight_kg.as_ref()),
+            field_error("height_m", problems.height_m.as_ref()),
+            problems
+                .form
+                .as_ref()
+                .map(|message| {
+                    format!(
+                        r#"<div id="formResult" class="error show" role="alert">{}</div>"#,
+                        html::escape(message)
+                    )
+                })
+                .unwrap_or_default(),
+        ),
+    };
+    let option = format!(r#"<option value="{}""#, form.classification.as_str());
+
+    Ok(template
+        .replace(BANNER_SLOT, &banner)
+        .replace(LEGEND_SLOT, &category_legends())
+        .replace(FORM_TOKEN_SLOT, &state.form_tokens.issue())
+        .replace(WEIGHT_SLOT, &html::escape(&form.weight_kg))
+        .replace(HEIGHT_SLOT, &html::escape(&form.height_m))
+        .replace(WEIGHT_ERROR_SLOT, &weight_error)
+        .replace(HEIGHT_ERROR_SLOT, &height_error)
+        .replace(RESULT_SLOT, &result)
+        .replace(&option, &format!("{option} selected")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use axum::{body::Body, extract::Request, http::header::CONTENT_TYPE};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use crate::assets::Assets;
+
+    async fn send(state: AppState, request: Request) -> (StatusCode, String) {
+        let response = crate::build_router(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    fn submit(body: &str) -> Request {
+        Request::post("/calculate")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_main_page_legend_comes_from_the_category_table() {
+        let (status, page) = send(
+            AppState::default(),
+            Request::get("/").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains(&category_legends()));
+        assert!(page.contains("<div data-classification=\"asian_pacific\" hidden>"));
+        assert!(page.contains(r#"<form id="bmiForm" method="post" action="/calculate">"#));
+        for slot in SUBMIT_SLOTS.iter().chain(&[LEGEND_SLOT, FORM_TOKEN_SLOT]) {
+            assert!(!page.contains(slot), "{slot}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submission_renders_the_result() {
+        let (status, page) = send(
+            AppState::default(),
+            submit("weight_kg=70&height_m=1.75&classification=asian_pacific&form_token="),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains(r#"<div class="bmi-value">22.9</div>"#));
+        assert!(page.contains(r#"<div class="bmi-category">Normal weight</div>"#));
+        assert!(page.contains("Healthy weight for your height: 56.7–70.4 kg"));
+        assert!(page.contains(r#"name="weight_kg" value="70""#));
+        assert!(page.contains(r#"<option value="asian_pacific" selected>"#));
+        assert!(!page.contains("field-error"));
+    }
+
+    #[tokio::test]
+    async fn test_problems_are_shown_next_to_their_fields() {
+        let (status, page) =
+            send(AppState::default(), submit("weight_kg=5000&height_m=1.75")).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let error = page
+            .find(r#"<div id="weight_kg-error" class="field-error" role="alert">Must be between 1 and 700 kg: "#)
+            .unwrap();
+        assert!(error > page.find(r#"id="weight""#).unwrap());
+        assert!(error < page.find(r#"id="height""#).unwrap());
+        assert!(!page.contains("height_m-error"));
+        assert!(!page.contains(r#"class="bmi-value">"#));
+
+        let (status, page) = send(
+            AppState::default(),
+            submit("weight_kg=%3Cb%3E&height_m=abc"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(page.contains("Enter your weight as a number"));
+        assert!(page.contains("Enter your height as a number"));
+        assert!(page.contains(r#"value="&lt;b&gt;""#));
+        assert!(!page.contains("<b>"));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_form_is_rendered_into_the_page() {
+        let (status, page) = send(
+            AppState::default(),
+            submit("weight_kg=70&height_m=1.75&classification=metric"),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let start = page
+            .find(r#"<div id="formResult" class="error show" role="alert">"#)
+            .unwrap();
+        assert!(page[start..].contains("classification"), "{page}");
+        assert!(page.contains(r#"<form id="bmiForm" method="post" action="/calculate">"#));
+    }
+
+    #[tokio::test]
+    async fn test_submissions_are_recorded_in_the_history() {
+        use crate::storage::{SqliteStorage, Storage};
+        use axum::http::header::COOKIE;
+
+        let storage = Arc::new(SqliteStorage::open("sqlite::memory:").unwrap());
+        let state = AppState {
+            storage: Some(Arc::clone(&storage) as Arc<dyn Storage>),
+            ..AppState::default()
+        };
+        let token = state.form_tokens.issue();
+        let cookie = format!(
+            "{}={}",
+            crate::session::COOKIE_NAME,
+            state.session_key.sign("tester")
+        );
+        for _ in 0..2 {
+            let mut request = submit(&format!("weight_kg=70&height_m=1.75&form_token={token}"));
+            request
+                .headers_mut()
+                .insert(COOKIE, cookie.parse().unwrap());
+            assert_eq!(send(state.clone(), request).await.0, StatusCode::OK);
+        }
+
+        // The resubmitted token replays the first result without storing it again
+        let records = storage.history("tester", 10, 0).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].calculation.weight_kg, 70.0);
+        assert_eq!(state.live_stats.snapshot().total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_template_without_slots_is_a_server_error() {
+        let dir = std::env::temp_dir().join(format!("bmi-page-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("index.html"),
+            "<form action=\"/calculate\"></form>",
+        )
+        .unwrap();
+        let state = AppState {
+            assets: Arc::new(Assets::new(&dir)),
+            ..AppState::default()
+        };
+
+        let (status, _) = send(
+            state.clone(),
+            Request::get("/").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = send(state, submit("weight_kg=70&height_m=1.75")).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!body.contains("22.9"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,256 @@
+//! Accessible plain HTML calculator for screen readers and text browsers.
+//!
+//! `GET /plain` renders a semantic, JavaScript-free form that posts back to
+//! `POST /plain`. Results are rendered server-side inside an ARIA live region
+//! and every piece of information is conveyed in text rather than color.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use serde::Deserialize;
+
+use crate::{hints, html, maintenance, process_bmi_request_with, AppState, BmiRequest};
+
+/// Raw form fields as submitted by the browser.
+///
+/// Fields are kept as text so invalid input can be echoed back to the user.
+#[derive(Debug, Default, Deserialize)]
+pub struct PlainForm {
+    /// Weight in kilograms, as typed.
+    #[serde(default)]
+    pub weight_kg: String,
+    /// Height in meters, as typed.
+    #[serde(default)]
+    pub height_m: String,
+}
+
+/// Outcome rendered below the form.
+enum Outcome {
+    /// Nothing submitted yet.
+    Empty,
+    /// Successful calculation.
+    Result { bmi: f64, category: String },
+    /// Validation failure with a human-readable explanation.
+    Error(String),
+}
+
+/// Serves the empty accessible form.
+pub async fn form_handler(State(state): State<AppState>) -> Html<String> {
+    Html(render(&state, &PlainForm::default(), &Outcome::Empty))
+}
+
+/// Handles a form submission and renders the result page.
+///
+/// # Errors
+///
+/// Returns HTTP 400 with the form re-rendered and an inline error if either
+/// field is not a positive number.
+pub async fn submit_handler(
+    State(state): State<AppState>,
+    Form(form): Form<PlainForm>,
+) -> Response {
+    let parsed = form
+        .weight_kg
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .zip(form.height_m.trim().parse::<f64>().ok());
+
+    let outcome = match parsed {
+        Some((weight_kg, height_m)) => {
+            let request = BmiRequest {
+                weight_kg: Some(weight_kg),
+                height_m: Some(height_m),
+                ..BmiRequest::default()
+            };
+            match process_bmi_request_with(&request, &state.bounds) {
+                Ok(response) => Outcome::Result {
+                    bmi: response.bmi,
+                    category: response.category,
+                },
+                Err(error) => Outcome::Error(error.to_string()),
+            }
+        }
+        None => Outcome::Error("Weight and height must be numbers".to_string()),
+    };
+
+    let status = match outcome {
+        Outcome::Error(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::OK,
+    };
+    (status, Html(render(&state, &form, &outcome))).into_response()
+}
+
+/// Renders the full page for the given form values and outcome.
+fn render(state: &AppState, form: &PlainForm, outcome: &Outcome) -> String {
+    let banner = state
+        .maintenance
+        .current()
+        .map(|window| maintenance::banner(&window))
+        .unwrap_or_default();
+
+    let (error, result) = match outcome {
+        Outcome::Empty => (String::new(), String::new()),
+        Outcome::Error(message) => (
+            format!(
+                r#"<p id="form-error" role="alert"><strong>Error:</strong> {}</p>"#,
+                html::escape(message)
+            ),
+            String::new(),
+        ),
+        Outcome::Result { bmi, category } => (
+            String::new(),
+            format!(
+                "<h2>Result</h2>\n<p>Your BMI is <strong>{bmi:.1}</strong>.</p>\n\
+                 <p>Category: <strong>{}</strong>.</p>",
+                html::escape(category)
+            ),
+        ),
+    };
+
+    let described_by = if error.is_empty() {
+        String::new()
+    } else {
+        r#" aria-describedby="form-error""#.to_string()
+    };
+
+    // After a failed round trip, keep the user oriented with the normal range
+    let hint = match (outcome, form.height_m.trim().parse::<f64>()) {
+        (Outcome::Error(_), Ok(height_m)) if height_m.is_finite() && height_m > 0.0 => format!(
+            r#"<p id="weight-hint">{}</p>"#,
+            hints::normal_hint(
+                height_m,
+                hints::WeightUnit::Kg,
+                crate::bmi::Classification::Standard,
+            )
+        ),
+        _ => String::new(),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8">
+<meta name="viewport" content="width=device-width, initial-scale=1.0">
+<title>BMI Calculator (plain HTML)</title>
+<style>
+body {{ font-family: sans-serif; max-width: 40em; margin: 1em auto; padding: 0 1em; line-height: 1.5; }}
+label {{ display: block; margin-top: 1em; }}
+</style>
+</head>
+<body>
+{banner}
+<main>
+<h1>BMI Calculator</h1>
+<p>Calculate your Body Mass Index from your weight in kilograms and height in meters.</p>
+<form method="post" action="/plain">
+<fieldset>
+<legend>Your measurements</legend>
+<label for="weight_kg">Weight in kilograms</label>
+<input type="text" inputmode="decimal" id="weight_kg" name="weight_kg" value="{weight}" required{described_by}>
+{hint}
+<label for="height_m">Height in meters</label>
+<input type="text" inputmode="decimal" id="height_m" name="height_m" value="{height}" required{described_by}>
+</fieldset>
+<p><button type="submit">Calculate BMI</button></p>
+</form>
+{error}
+<section aria-live="polite" aria-atomic="true">
+{result}
+</section>
+<h2>BMI categories (WHO)</h2>
+<ul>
+<li>Underweight: below 18.5</li>
+<li>Normal weight: 18.5 to 24.9</li>
+<li>Overweight: 25 to 29.9</li>
+<li>Obese: 30 or above</li>
+</ul>
+<p><a href="/">Back to the standard calculator</a></p>
+</main>
+</body>
+</html>"#,
+        weight = html::escape(&form.weight_kg),
+        height = html::escape(&form.height_m),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::header::CONTENT_TYPE};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn send(request: Request) -> (StatusCode, String) {
+        let response = crate::build_router(AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    fn submit(body: &str) -> Request {
+        Request::post("/plain")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_form_has_semantic_markup() {
+        let (status, page) = send(Request::get("/plain").body(Body::empty()).unwrap()).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains("<h1>BMI Calculator</h1>"));
+        assert!(page.contains(r#"<label for="weight_kg">"#));
+        assert!(page.contains(r#"id="weight_kg" name="weight_kg""#));
+        assert!(page.contains(r#"<label for="height_m">"#));
+        assert!(page.contains(r#"<form method="post" action="/plain">"#));
+        assert!(page.contains(r#"aria-live="polite""#));
+        assert!(!page.contains("<script"));
+    }
+
+    #[tokio::test]
+    async fn test_submission_renders_result_without_js() {
+        let (status, page) = send(submit("weight_kg=70&height_m=1.75")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains("Your BMI is <strong>22.9</strong>."));
+        assert!(page.contains("Category: <strong>Normal weight</strong>."));
+        assert!(page.contains(r#"value="70""#));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_submission_renders_inline_error() {
+        let (status, page) = send(submit("weight_kg=abc&height_m=1.75")).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(page.contains(r#"role="alert""#));
+        assert!(page.contains(r#"aria-describedby="form-error""#));
+        assert!(page.contains(r#"value="abc""#));
+        assert!(!page.contains("Your BMI is"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_submission_shows_weight_hint() {
+        let (status, page) = send(submit("weight_kg=&height_m=1.75")).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(page.contains("At 1.75 m, Normal weight is 56.7–76.6 kg"));
+
+        let (_, page) = send(Request::get("/plain").body(Body::empty()).unwrap()).await;
+        assert!(!page.contains("weight-hint"));
+    }
+
+    #[tokio::test]
+    async fn test_main_page_links_to_plain_mode() {
+        let (_, page) = send(Request::get("/").body(Body::empty()).unwrap()).await;
+        assert!(page.contains(r#"href="/plain""#));
+    }
+}
@@ -0,0 +1,736 @@
+//! Protocol Buffers transport for `/api/calculate` and its batch route.
+//!
+//! Message types mirror `proto/bmi.proto`. They are declared with prost
+//! derives directly so building does not require `protoc`.
+//!
+//! Conversion to and from the domain types lives in the `From` impls below,
+//! so every transport shares the same validation in `process_bmi_request`.
+
+use std::collections::HashMap;
+
+use axum::{
+    body::Bytes,
+    extract::{rejection::BytesRejection, FromRequest, Json, Request, State},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use prost::Message;
+use tracing::{event, Level};
+
+use crate::{
+    error::BmiError,
+    http::{calculate_bmi_handler, calculate_fresh, calculate_json, CALCULATE_FORMATS},
+    i18n::{self, Locale},
+    negotiate::Format,
+    timings::{self, Timings},
+    AppState,
+};
+
+/// Media type for protobuf-encoded request and response bodies.
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// Protobuf form of [`crate::BmiRequest`], in SI units only; the imperial
+/// and `height_cm` inputs are JSON-only.
+#[derive(Clone, PartialEq, Message)]
+pub struct BmiRequest {
+    /// Weight in kilograms.
+    #[prost(double, tag = "1")]
+    pub weight_kg: f64,
+    /// Height in meters.
+    #[prost(double, tag = "2")]
+    pub height_m: f64,
+    /// Reject values that look like another unit instead of warning.
+    #[prost(bool, tag = "3")]
+    pub strict_units: bool,
+    /// Decimal places of `bmi_rounded` (defaults to 1).
+    #[prost(uint32, optional, tag = "4")]
+    pub precision: Option<u32>,
+    /// Add `bmi_prime` and `ponderal_index` to the response.
+    #[prost(bool, tag = "5")]
+    pub extended: bool,
+    /// Threshold table for the categories, a [`Classification`].
+    #[prost(enumeration = "Classification", tag = "6")]
+    pub classification: i32,
+    /// Single-use token from `GET /api/form-token`; a replay returns the
+    /// first response.
+    #[prost(string, optional, tag = "7")]
+    pub form_token: Option<String>,
+}
+
+/// Protobuf form of [`crate::bmi::Classification`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Classification {
+    /// WHO international cutoffs.
+    Standard = 0,
+    /// WHO cutoffs for Asian populations.
+    AsianPacific = 1,
+}
+
+impl From<Classification> for crate::bmi::Classification {
+    fn from(classification: Classification) -> Self {
+        match classification {
+            Classification::Standard => Self::Standard,
+            Classification::AsianPacific => Self::AsianPacific,
+        }
+    }
+}
+
+impl From<crate::bmi::Classification> for Classification {
+    fn from(classification: crate::bmi::Classification) -> Self {
+        match classification {
+            crate::bmi::Classification::Standard => Self::Standard,
+            crate::bmi::Classification::AsianPacific => Self::AsianPacific,
+        }
+    }
+}
+
+/// Protobuf form of [`crate::BmiResponse`].
+#[derive(Clone, PartialEq, Message)]
+pub struct BmiResponse {
+    /// Calculated BMI value.
+    #[prost(double, tag = "1")]
+    pub bmi: f64,
+    /// Health category based on WHO standards.
+    #[prost(string, tag = "2")]
+    pub category: String,
+    /// Non-fatal issues with the input.
+    #[prost(string, repeated, tag = "3")]
+    pub warnings: Vec<String>,
+    /// Unit system of the request (`metric`).
+    #[prost(string, tag = "4")]
+    pub units: String,
+    /// Weight used for the calculation, in kilograms.
+    #[prost(double, tag = "5")]
+    pub weight_kg: f64,
+    /// `bmi` rounded to the requested precision.
+    #[prost(double, tag = "6")]
+    pub bmi_rounded: f64,
+    /// Normal-weight range at the submitted height.
+    #[prost(message, optional, tag = "7")]
+    pub healthy_weight_range: Option<HealthyWeightRange>,
+    /// BMI relative to the upper normal limit, when `extended` was set.
+    #[prost(message, optional, tag = "8")]
+    pub bmi_prime: Option<Indicator>,
+    /// Weight over height cubed (kg/m³), when `extended` was set.
+    #[prost(message, optional, tag = "9")]
+    pub ponderal_index: Option<Indicator>,
+    /// Finer WHO class.
+    #[prost(string, tag = "10")]
+    pub category_detailed: String,
+    /// Stable code of `category` in every locale.
+    #[prost(string, tag = "11")]
+    pub category_code: String,
+    /// Threshold table the categories come from, a [`Classification`].
+    #[prost(enumeration = "Classification", tag = "12")]
+    pub classification: i32,
+    /// `<stage>_us` durations, for admins sending `X-Debug-Timings: true`.
+    #[prost(map = "string, uint64", tag = "13")]
+    pub timings: HashMap<String, u64>,
+}
+
+/// Normal-weight range in kilograms, rounded to one decimal.
+#[derive(Clone, PartialEq, Message)]
+pub struct HealthyWeightRange {
+    /// Lowest normal weight.
+    #[prost(double, tag = "1")]
+    pub min_kg: f64,
+    /// Weight where "Overweight" starts.
+    #[prost(double, tag = "2")]
+    pub max_kg: f64,
+}
+
+/// A secondary index with its reading.
+#[derive(Clone, PartialEq, Message)]
+pub struct Indicator {
+    /// Value of the index.
+    #[prost(double, tag = "1")]
+    pub value: f64,
+    /// What the value suggests.
+    #[prost(string, tag = "2")]
+    pub interpretation: String,
+}
+
+impl From<crate::bmi::Indicator> for Indicator {
+    fn from(indicator: crate::bmi::Indicator) -> Self {
+        Self {
+            value: indicator.value,
+            interpretation: indicator.interpretation.to_string(),
+        }
+    }
+}
+
+/// Protobuf error body for rejected requests.
+#[derive(Clone, PartialEq, Message)]
+pub struct Error {
+    /// Human-readable error description.
+    #[prost(string, tag = "1")]
+    pub message: String,
+    /// Machine-readable code, as in the JSON error body.
+    #[prost(string, tag = "2")]
+    pub code: String,
+    /// Fields out of bounds, for `validation_failed`.
+    #[prost(message, repeated, tag = "3")]
+    pub fields: Vec<FieldViolation>,
+}
+
+/// Protobuf form of [`crate::validation::FieldViolation`].
+#[derive(Clone, PartialEq, Message)]
+pub struct FieldViolation {
+    /// Request field as sent.
+    #[prost(string, tag = "1")]
+    pub field: String,
+    /// The rule it broke.
+    #[prost(string, tag = "2")]
+    pub constraint: String,
+    /// The submitted value.
+    #[prost(double, tag = "3")]
+    pub value: f64,
+    /// Suspected unit mix-up; empty if none.
+    #[prost(string, tag = "4")]
+    pub hint: String,
+}
+
+impl From<crate::error::ErrorDetail> for Error {
+    fn from(detail: crate::error::ErrorDetail) -> Self {
+        Self {
+            message: detail.message,
+            code: detail.code.to_string(),
+            fields: detail
+                .fields
+                .into_iter()
+                .map(FieldViolation::from)
+                .collect(),
+        }
+    }
+}
+
+/// Protobuf body of `POST /api/calculate/batch`.
+#[derive(Clone, PartialEq, Message)]
+pub struct BatchRequest {
+    /// Entries, each calculated on its own.
+    #[prost(message, repeated, tag = "1")]
+    pub entries: Vec<BmiRequest>,
+}
+
+/// Protobuf form of [`crate::batch::BatchResponse`].
+#[derive(Clone, PartialEq, Message)]
+pub struct BatchResponse {
+    /// Number of entries in the request.
+    #[prost(uint32, tag = "1")]
+    pub processed: u32,
+    /// Number of entries that were rejected.
+    #[prost(uint32, tag = "2")]
+    pub failed: u32,
+    /// One item per entry, in request order.
+    #[prost(message, repeated, tag = "3")]
+    pub results: Vec<BatchItem>,
+}
+
+/// Outcome of one batch entry.
+#[derive(Clone, PartialEq, Message)]
+pub struct BatchItem {
+    /// Position of the entry in the request.
+    #[prost(uint32, tag = "1")]
+    pub index: u32,
+    /// The result or the error.
+    #[prost(oneof = "batch_item::Outcome", tags = "2, 3")]
+    pub outcome: Option<batch_item::Outcome>,
+}
+
+/// Nested types of [`BatchItem`].
+pub mod batch_item {
+    /// What became of a batch entry.
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Outcome {
+        /// The entry was calculated.
+        #[prost(message, boxed, tag = "2")]
+        Result(Box<super::BmiResponse>),
+        /// The entry was rejected.
+        #[prost(message, tag = "3")]
+        Error(super::Error),
+    }
+}
+
+impl From<crate::batch::BatchResponse> for BatchResponse {
+    fn from(response: crate::batch::BatchResponse) -> Self {
+        let index = |index: usize| u32::try_from(index).unwrap_or(u32::MAX);
+        Self {
+            processed: index(response.processed),
+            failed: index(response.failed),
+            results: response
+                .results
+                .into_iter()
+                .map(|item| match item {
+                    crate::batch::BatchItem::Ok { index: at, result } => BatchItem {
+                        index: index(at),
+                        outcome: Some(batch_item::Outcome::Result(Box::new(result.into()))),
+                    },
+                    crate::batch::BatchItem::Err { index: at, error } => BatchItem {
+                        index: index(at),
+                        outcome: Some(batch_item::Outcome::Error(error.into())),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::validation::FieldViolation> for FieldViolation {
+    fn from(violation: crate::validation::FieldViolation) -> Self {
+        Self {
+            field: violation.field.to_string(),
+            constraint: violation.constraint,
+            value: violation.value,
+            hint: violation.hint.unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<BmiRequest> for crate::BmiRequest {
+    type Error = BmiError;
+
+    /// Fails on a `classification` this server does not know.
+    fn try_from(request: BmiRequest) -> Result<Self, BmiError> {
+        let classification = Classification::try_from(request.classification).map_err(|_| {
+            BmiError::MalformedPayload(format!("Unknown classification {}", request.classification))
+        })?;
+        Ok(Self {
+            weight_kg: Some(request.weight_kg),
+            height_m: Some(request.height_m),
+            strict_units: request.strict_units,
+            // Out-of-range values saturate and are rejected by validation
+            precision: request
+                .precision
+                .map(|precision| u8::try_from(precision).unwrap_or(u8::MAX)),
+            extended: request.extended,
+            classification: classification.into(),
+            form_token: request.form_token,
+            ..Self::default()
+        })
+    }
+}
+
+impl From<crate::BmiResponse> for BmiResponse {
+    fn from(response: crate::BmiResponse) -> Self {
+        Self {
+            bmi: response.bmi,
+            category: response.category,
+            warnings: response.warnings,
+            units: response.units.as_str().to_string(),
+            weight_kg: response.weight_kg,
+            bmi_rounded: response.bmi_rounded,
+            healthy_weight_range: Some(HealthyWeightRange {
+                min_kg: response.healthy_weight_range.min_kg,
+                max_kg: response.healthy_weight_range.max_kg,
+            }),
+            bmi_prime: response.bmi_prime.map(Indicator::from),
+            ponderal_index: response.ponderal_index.map(Indicator::from),
+            category_detailed: response.category_detailed,
+            category_code: response.category_code,
+            classification: Classification::from(response.classification).into(),
+            timings: HashMap::new(),
+        }
+    }
+}
+
+/// Handles `/api/calculate` in both JSON and protobuf encodings.
+///
+/// The request body is decoded according to `Content-Type`; the response is
+/// protobuf only when `Accept` asks for it, otherwise JSON as before. Either
+/// way form tokens, history, live statistics and timings work as on the JSON
+/// route.
+///
+/// # Errors
+///
+/// Returns an encoded [`Error`] with the [`BmiError`] status: 422 if the
+/// protobuf body cannot be decoded or a measurement is out of bounds, 400
+/// for other invalid fields such as `precision`.
+pub async fn calculate_handler(State(state): State<AppState>, request: Request) -> Response {
+    let wants_protobuf = accepts_protobuf(request.headers());
+    if !wants_protobuf && !is_protobuf(request.headers()) {
+        return calculate_bmi_handler(State(state), request).await;
+    }
+
+    let mut timings = Timings::new(timings::requested(
+        state.admin_token.as_deref(),
+        request.headers(),
+    ));
+    let session = request
+        .extensions()
+        .get::<crate::session::Session>()
+        .cloned();
+    let locale = Locale::requested(request.headers(), request.uri().query());
+    let format = Format::requested(request.headers(), request.uri().query(), CALCULATE_FORMATS);
+    let payload = timings
+        .measure_async("deserialization", read_request(request))
+        .await;
+
+    if !wants_protobuf {
+        let format = match format {
+            Ok(format) => format,
+            Err(error) => return error_response(error, false),
+        };
+        return calculate_json(&state, session.as_ref(), locale, format, payload, timings).await;
+    }
+
+    let outcome = match payload {
+        Ok(payload) => calculate_fresh(&state, session.as_ref(), &payload, &mut timings).await,
+        Err(error) => Err(error),
+    };
+    match outcome {
+        Ok(mut response) => {
+            i18n::localize(&mut response, locale);
+            let mut message = BmiResponse::from(response);
+            if timings.is_enabled() {
+                message.timings = timings.finish_micros().into_iter().collect();
+            }
+            i18n::respond(locale, encoded(StatusCode::OK, message))
+        }
+        Err(error) => i18n::respond(
+            locale,
+            error_response(i18n::localize_error(error, locale), wants_protobuf),
+        ),
+    }
+}
+
+/// Decodes a calculation request body: protobuf, or JSON when declared so.
+async fn read_request(request: Request) -> Result<crate::BmiRequest, BmiError> {
+    if !is_protobuf(request.headers()) {
+        let Json(payload) = Json::<crate::BmiRequest>::from_request(request, &()).await?;
+        return Ok(payload);
+    }
+    let body = Bytes::from_request(request, &())
+        .await
+        .map_err(body_error)?;
+    match BmiRequest::decode(body) {
+        Ok(message) => message.try_into(),
+        Err(err) => {
+            event!(
+                name: "bmi.proto.decode_failed",
+                Level::WARN,
+                error = %err,
+                "Malformed protobuf payload: {{error}}"
+            );
+            Err(BmiError::MalformedPayload(
+                "Malformed protobuf payload".to_string(),
+            ))
+        }
+    }
+}
+
+/// Handles `/api/calculate/batch` when either side of it is protobuf.
+///
+/// The body is a [`BatchRequest`] or a JSON array according to
+/// `Content-Type`; the answer is a [`BatchResponse`] only when `Accept` asks
+/// for it.
+///
+/// # Errors
+///
+/// As [`crate::batch::batch_handler`]; a body that does not decode is a 422
+/// `malformed_payload`, while an entry with an unknown classification is an
+/// item error.
+pub(crate) async fn batch_handler(state: &AppState, locale: Locale, request: Request) -> Response {
+    let wants_protobuf = accepts_protobuf(request.headers());
+    let entries = if is_protobuf(request.headers()) {
+        read_batch(request).await
+    } else {
+        crate::batch::read_json(request).await
+    };
+    match entries.and_then(|entries| crate::batch::run(state, entries, locale)) {
+        Ok(response) if wants_protobuf => i18n::respond(
+            locale,
+            encoded(StatusCode::OK, BatchResponse::from(response)),
+        ),
+        Ok(response) => i18n::respond(locale, Json(response)),
+        Err(error) => i18n::respond(
+            locale,
+            error_response(i18n::localize_error(error, locale), wants_protobuf),
+        ),
+    }
+}
+
+/// Decodes a [`BatchRequest`] body, converting each entry on its own.
+async fn read_batch(
+    request: Request,
+) -> Result<Vec<Result<crate::BmiRequest, BmiError>>, BmiError> {
+    let body = Bytes::from_request(request, &())
+        .await
+        .map_err(body_error)?;
+    let batch = BatchRequest::decode(body)
+        .map_err(|_| BmiError::MalformedPayload("Malformed protobuf payload".to_string()))?;
+    Ok(batch.entries.into_iter().map(TryInto::try_into).collect())
+}
+
+/// Maps an unreadable body like [`BmiError`]'s JSON rejections.
+fn body_error(rejection: BytesRejection) -> BmiError {
+    if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        BmiError::PayloadTooLarge(rejection.body_text())
+    } else {
+        BmiError::MalformedPayload(rejection.body_text())
+    }
+}
+
+/// Returns true if the request body is declared as protobuf.
+pub(crate) fn is_protobuf(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(PROTOBUF_CONTENT_TYPE))
+}
+
+/// Returns true if the client lists protobuf among acceptable media types.
+pub(crate) fn accepts_protobuf(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|item| item.trim().starts_with(PROTOBUF_CONTENT_TYPE))
+        })
+}
+
+/// Builds an error response in the negotiated encoding.
+fn error_response(error: BmiError, wants_protobuf: bool) -> Response {
+    if wants_protobuf {
+        encoded(error.status(), Error::from(error.body()))
+    } else {
+        error.into_response()
+    }
+}
+
+/// Encodes a message as a protobuf response body.
+fn encoded(status: StatusCode, message: impl Message) -> Response {
+    (
+        status,
+        [(
+            CONTENT_TYPE,
+            HeaderValue::from_static(PROTOBUF_CONTENT_TYPE),
+        )],
+        message.encode_to_vec(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header::AUTHORIZATION};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn post(content_type: &str, accept: &str, body: Vec<u8>) -> (StatusCode, Bytes) {
+        let request = Request::post("/api/calculate")
+            .header(CONTENT_TYPE, content_type)
+            .header(ACCEPT, accept)
+            .body(Body::from(body))
+            .unwrap();
+        let response = crate::build_router(crate::AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        (
+            status,
+            response.into_body().collect().await.unwrap().to_bytes(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_protobuf_round_trip() {
+        let request = BmiRequest {
+            weight_kg: 70.0,
+            height_m: 1.75,
+            precision: Some(2),
+            extended: true,
+            ..BmiRequest::default()
+        };
+        let (status, body) = post(
+            PROTOBUF_CONTENT_TYPE,
+            PROTOBUF_CONTENT_TYPE,
+            request.encode_to_vec(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let response = BmiResponse::decode(body).unwrap();
+        assert!((response.bmi - 22.857).abs() < 0.01);
+        assert_eq!(response.bmi_rounded, 22.86);
+        let range = response.healthy_weight_range.unwrap();
+        assert_eq!((range.min_kg, range.max_kg), (56.7, 76.6));
+        let prime = response.bmi_prime.unwrap();
+        assert_eq!(prime.interpretation, "Within the normal range");
+        assert!(response.ponderal_index.is_some());
+        assert_eq!(response.category, "Normal weight");
+    }
+
+    #[tokio::test]
+    async fn test_classification_round_trip() {
+        let request = BmiRequest {
+            weight_kg: 75.0,
+            height_m: 1.75,
+            classification: Classification::AsianPacific.into(),
+            ..BmiRequest::default()
+        };
+        let (status, body) = post(
+            PROTOBUF_CONTENT_TYPE,
+            PROTOBUF_CONTENT_TYPE,
+            request.encode_to_vec(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let response = BmiResponse::decode(body).unwrap();
+        assert_eq!(
+            response.category, "Overweight",
+            "normal under the standard table"
+        );
+        assert_eq!(response.classification(), Classification::AsianPacific);
+
+        let unknown = BmiRequest {
+            classification: 9,
+            ..request
+        };
+        let (status, body) = post(
+            PROTOBUF_CONTENT_TYPE,
+            PROTOBUF_CONTENT_TYPE,
+            unknown.encode_to_vec(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(Error::decode(body).unwrap().code, "malformed_payload");
+    }
+
+    #[tokio::test]
+    async fn test_protobuf_batch_round_trip() {
+        let entry = |weight_kg| BmiRequest {
+            weight_kg,
+            height_m: 1.75,
+            ..BmiRequest::default()
+        };
+        let batch = BatchRequest {
+            entries: vec![entry(70.0), entry(-1.0)],
+        };
+        let request = Request::post("/api/calculate/batch")
+            .header(CONTENT_TYPE, PROTOBUF_CONTENT_TYPE)
+            .header(ACCEPT, PROTOBUF_CONTENT_TYPE)
+            .body(Body::from(batch.encode_to_vec()))
+            .unwrap();
+        let response = crate::build_router(crate::AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let response = BatchResponse::decode(body).unwrap();
+        assert_eq!((response.processed, response.failed), (2, 1));
+        let Some(batch_item::Outcome::Result(result)) = &response.results[0].outcome else {
+            panic!("expected a result: {:?}", response.results[0]);
+        };
+        assert_eq!(result.category, "Normal weight");
+        let Some(batch_item::Outcome::Error(error)) = &response.results[1].outcome else {
+            panic!("expected an error: {:?}", response.results[1]);
+        };
+        assert_eq!(response.results[1].index, 1);
+        assert_eq!(error.code, "validation_failed");
+        assert_eq!(error.fields[0].field, "weight_kg");
+    }
+
+    #[tokio::test]
+    async fn test_protobuf_shares_the_json_pipeline() {
+        let state = crate::AppState {
+            admin_token: Some(std::sync::Arc::from("secret")),
+            ..crate::AppState::default()
+        };
+        let request = BmiRequest {
+            weight_kg: 70.0,
+            height_m: 1.75,
+            form_token: Some(state.form_tokens.issue()),
+            ..BmiRequest::default()
+        };
+        let send = || {
+            let request = Request::post("/api/calculate")
+                .header(CONTENT_TYPE, PROTOBUF_CONTENT_TYPE)
+                .header(ACCEPT, PROTOBUF_CONTENT_TYPE)
+                .header(AUTHORIZATION, "Bearer secret")
+                .header(timings::HEADER, "true")
+                .body(Body::from(request.encode_to_vec()))
+                .unwrap();
+            crate::build_router(state.clone()).oneshot(request)
+        };
+
+        let response = send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let first = BmiResponse::decode(body).unwrap();
+        assert!(first.timings.contains_key("deserialization_us"));
+        assert!(first.timings.contains_key("calculation_us"));
+        assert_eq!(state.live_stats.snapshot().total, 1);
+
+        let body = send().await.unwrap().into_body().collect().await.unwrap();
+        let replayed = BmiResponse::decode(body.to_bytes()).unwrap();
+        assert_eq!(replayed.bmi, first.bmi);
+        assert_eq!(state.live_stats.snapshot().total, 1, "replay not recorded");
+    }
+
+    #[tokio::test]
+    async fn test_protobuf_request_with_json_response() {
+        let request = BmiRequest {
+            weight_kg: 70.0,
+            height_m: 1.75,
+            ..BmiRequest::default()
+        };
+        let (status, body) = post(
+            PROTOBUF_CONTENT_TYPE,
+            "application/json",
+            request.encode_to_vec(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["category"], "Normal weight");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_protobuf_returns_422() {
+        let (status, body) = post(
+            PROTOBUF_CONTENT_TYPE,
+            PROTOBUF_CONTENT_TYPE,
+            vec![0xff, 0xff, 0xff],
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let error = Error::decode(body).unwrap();
+        assert_eq!(error.message, "Malformed protobuf payload");
+        assert_eq!(error.code, "malformed_payload");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_measurements_return_protobuf_error() {
+        let request = BmiRequest {
+            weight_kg: -1.0,
+            height_m: 1.75,
+            ..BmiRequest::default()
+        };
+        let (status, body) = post(
+            PROTOBUF_CONTENT_TYPE,
+            PROTOBUF_CONTENT_TYPE,
+            request.encode_to_vec(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let error = Error::decode(body).unwrap();
+        assert!(!error.message.is_empty());
+        assert_eq!(error.code, "validation_failed");
+        assert_eq!(error.fields[0].field, "weight_kg");
+        assert_eq!(error.fields[0].value, -1.0);
+    }
+}
@@ -0,0 +1,408 @@
+//! Per-client request rate limiting on `/api/*`.
+//!
+//! Each client IP gets a token bucket refilled at `RATE_LIMIT_PER_SEC`
+//! requests per second (default 10, `0` disables limiting) holding up to
+//! `RATE_LIMIT_BURST` requests (default 20). Requests beyond that get a 429
+//! with a JSON error and `Retry-After`. Buckets of idle clients are dropped
+//! once they would be full again, so memory follows the active clients.
+//!
+//! Behind a proxy such as the Heroku router, set
+//! `RATE_LIMIT_TRUST_FORWARDED=1` to key on the last `X-Forwarded-For` entry.
+//!
+//! Every limited response carries the client's state after the request:
+//! `X-RateLimit-Limit` (the burst), `X-RateLimit-Remaining` and
+//! `X-RateLimit-Reset` (seconds until the bucket is full again). Set
+//! `rate_limit.headers = false` to leave them out. All settings can also be
+//! made in `bmi.toml`, see [`crate::config`].
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header::RETRY_AFTER, HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{event, Level};
+
+use crate::{error::BmiError, telemetry, AppState};
+
+/// Default `RATE_LIMIT_PER_SEC`.
+pub const DEFAULT_PER_SEC: f64 = 10.0;
+
+/// Default `RATE_LIMIT_BURST`.
+pub const DEFAULT_BURST: f64 = 20.0;
+
+/// Header set by proxies with the client address last.
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Requests a full bucket holds.
+pub const LIMIT_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+
+/// Requests left right now.
+pub const REMAINING_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+/// Seconds until the bucket is full again.
+pub const RESET_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// A client's bucket right after a [`RateLimiter::check`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quota {
+    /// Burst size.
+    pub limit: u64,
+    /// Whole requests still available.
+    pub remaining: u64,
+    /// Time until the bucket is full.
+    pub reset: Duration,
+}
+
+impl Quota {
+    /// Adds the `X-RateLimit-*` headers to `headers`.
+    fn apply(&self, headers: &mut HeaderMap) {
+        let reset = self.reset.as_secs_f64().ceil() as u64;
+        for (name, value) in [
+            (LIMIT_HEADER, self.limit),
+            (REMAINING_HEADER, self.remaining),
+            (RESET_HEADER, reset),
+        ] {
+            headers.insert(name, HeaderValue::from(value));
+        }
+    }
+}
+
+/// A request refused by [`RateLimiter::check`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Exceeded {
+    /// The client's unchanged bucket.
+    pub quota: Quota,
+    /// Time until the next request would be allowed.
+    pub retry_after: Duration,
+}
+
+/// One client's bucket.
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Shared token buckets keyed by client IP.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_sec: f64,
+    burst: f64,
+    trust_forwarded: bool,
+    headers: bool,
+    buckets: Mutex<Buckets>,
+}
+
+/// Buckets plus the time of the last idle sweep.
+#[derive(Debug)]
+struct Buckets {
+    by_ip: HashMap<IpAddr, Bucket>,
+    swept: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `per_sec` requests per second per client,
+    /// with bursts of up to `burst`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `per_sec` is not positive or `burst` is below one.
+    pub fn new(per_sec: f64, burst: f64, trust_forwarded: bool) -> Self {
+        assert!(per_sec > 0.0, "rate must be positive");
+        assert!(burst >= 1.0, "burst must allow at least one request");
+        Self {
+            per_sec,
+            burst,
+            trust_forwarded,
+            headers: true,
+            buckets: Mutex::new(Buckets {
+                by_ip: HashMap::new(),
+                swept: Instant::now(),
+            }),
+        }
+    }
+
+    /// Sets whether responses carry the `X-RateLimit-*` headers (default on).
+    pub fn with_headers(mut self, enabled: bool) -> Self {
+        self.headers = enabled;
+        self
+    }
+
+    /// Takes a token for `ip` at `now`, returning the bucket afterwards.
+    ///
+    /// The token and the snapshot are taken under one lock, so concurrent
+    /// requests each see their own result.
+    ///
+    /// # Errors
+    ///
+    /// Returns the bucket and how long until a token is available if it is
+    /// empty.
+    pub fn check(&self, ip: IpAddr, now: Instant) -> Result<Quota, Exceeded> {
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.sweep(&mut buckets, now);
+
+        let bucket = buckets.by_ip.entry(ip).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_sec).min(self.burst);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let quota = Quota {
+            limit: self.burst as u64,
+            remaining: bucket.tokens as u64,
+            reset: Duration::from_secs_f64((self.burst - bucket.tokens) / self.per_sec),
+        };
+        if allowed {
+            Ok(quota)
+        } else {
+            Err(Exceeded {
+                quota,
+                retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_sec),
+            })
+        }
+    }
+
+    /// Number of clients currently tracked.
+    pub fn tracked(&self) -> usize {
+        self.buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .by_ip
+            .len()
+    }
+
+    /// Drops buckets that have refilled completely, at most once per refill
+    /// period, so cleanup cost stays amortized.
+    fn sweep(&self, buckets: &mut Buckets, now: Instant) {
+        let refill = Duration::from_secs_f64(self.burst / self.per_sec);
+        if now.saturating_duration_since(buckets.swept) < refill {
+            return;
+        }
+        buckets
+            .by_ip
+            .retain(|_, bucket| now.saturating_duration_since(bucket.updated) < refill);
+        buckets.swept = now;
+    }
+
+    /// Picks the client address for a request.
+    fn client_ip(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+        let forwarded = self
+            .trust_forwarded
+            .then(|| headers.get(FORWARDED_FOR)?.to_str().ok())
+            .flatten()
+            .and_then(|value| value.rsplit(',').next()?.trim().parse().ok());
+        forwarded.or(peer).map(|ip: IpAddr| ip.to_canonical())
+    }
+}
+
+/// Rejects API requests from clients over their rate.
+///
+/// Requests without a known peer (in-process calls) are not limited.
+pub async fn limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(limiter) = &state.rate_limiter else {
+        return next.run(request).await;
+    };
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let Some(ip) = limiter.client_ip(request.headers(), peer) else {
+        return next.run(request).await;
+    };
+
+    match limiter.check(ip, Instant::now()) {
+        Ok(quota) => {
+            let mut response = next.run(request).await;
+            if limiter.headers {
+                quota.apply(response.headers_mut());
+            }
+            response
+        }
+        Err(exceeded) => {
+            telemetry::rate_limited();
+            let retry_after = exceeded.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            event!(
+                name: "app.rate_limit.exceeded",
+                Level::WARN,
+                client = %ip,
+                retry_after_secs = retry_after,
+                "Rate limit exceeded by {{client}}"
+            );
+            let error =
+                BmiError::RateLimited(format!("Too many requests; retry in {retry_after} s"));
+            let mut response = ([(RETRY_AFTER, retry_after.to_string())], error).into_response();
+            if limiter.headers {
+                exceeded.quota.apply(response.headers_mut());
+            }
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{header::CONTENT_TYPE, StatusCode},
+    };
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    const CLIENT: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(203, 0, 113, 7));
+
+    #[test]
+    fn test_bucket_refills() {
+        let limiter = RateLimiter::new(2.0, 2.0, false);
+        let start = Instant::now();
+
+        assert!(limiter.check(CLIENT, start).is_ok());
+        assert!(limiter.check(CLIENT, start).is_ok());
+        let exceeded = limiter.check(CLIENT, start).unwrap_err();
+        assert_eq!(exceeded.retry_after, Duration::from_millis(500));
+        assert_eq!(exceeded.quota.remaining, 0);
+        assert_eq!(exceeded.quota.reset, Duration::from_secs(1));
+        assert!(limiter
+            .check(CLIENT, start + Duration::from_millis(500))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_idle_clients_are_dropped() {
+        let limiter = RateLimiter::new(10.0, 10.0, false);
+        let start = Instant::now();
+        for last_octet in 0..100 {
+            let ip = IpAddr::V4(std::net::Ipv4Addr::new(198, 51, 100, last_octet));
+            limiter.check(ip, start).unwrap();
+        }
+        assert_eq!(limiter.tracked(), 100);
+
+        limiter
+            .check(CLIENT, start + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(limiter.tracked(), 1);
+    }
+
+    #[test]
+    fn test_forwarded_for_needs_opt_in() {
+        let mut headers = HeaderMap::new();
+        headers.insert(FORWARDED_FOR, "10.0.0.1, 192.0.2.9".parse().unwrap());
+        let peer = Some(CLIENT);
+
+        let direct = RateLimiter::new(1.0, 1.0, false);
+        assert_eq!(direct.client_ip(&headers, peer), peer);
+        let proxied = RateLimiter::new(1.0, 1.0, true);
+        assert_eq!(
+            proxied.client_ip(&headers, peer),
+            Some("192.0.2.9".parse().unwrap())
+        );
+    }
+
+    fn state(limiter: RateLimiter) -> AppState {
+        AppState {
+            rate_limiter: Some(Arc::new(limiter)),
+            ..AppState::default()
+        }
+    }
+
+    async fn send(state: &AppState, mut request: Request) -> Response {
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::new(CLIENT, 50_000)));
+        crate::build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    fn calculate() -> Request {
+        Request::post("/api/calculate")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"weight_kg": 70, "height_m": 1.75}"#))
+            .unwrap()
+    }
+
+    fn header(response: &Response, name: &HeaderName) -> u64 {
+        response.headers()[name].to_str().unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_past_the_limit_get_429() {
+        let state = state(RateLimiter::new(0.001, 3.0, false));
+
+        for _ in 0..3 {
+            assert_eq!(send(&state, calculate()).await.status(), StatusCode::OK);
+        }
+        let response = send(&state, calculate()).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(header(&response, &RETRY_AFTER) >= 1);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"]["code"], "rate_limited");
+
+        for uri in ["/", "/healthz"] {
+            let response = send(&state, Request::get(uri).body(Body::empty()).unwrap()).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            assert!(response.headers().get(LIMIT_HEADER).is_none(), "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_headers_count_down() {
+        let state = state(RateLimiter::new(1.0, 3.0, false));
+
+        let mut remaining = Vec::new();
+        for _ in 0..4 {
+            let response = send(&state, calculate()).await;
+            assert_eq!(header(&response, &LIMIT_HEADER), 3);
+            remaining.push((
+                response.status(),
+                header(&response, &REMAINING_HEADER),
+                header(&response, &RESET_HEADER),
+            ));
+        }
+        assert_eq!(
+            remaining,
+            [
+                (StatusCode::OK, 2, 1),
+                (StatusCode::OK, 1, 2),
+                (StatusCode::OK, 0, 3),
+                (StatusCode::TOO_MANY_REQUESTS, 0, 3),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_headers_can_be_suppressed() {
+        let state = state(RateLimiter::new(0.001, 1.0, false).with_headers(false));
+
+        let allowed = send(&state, calculate()).await;
+        let refused = send(&state, calculate()).await;
+
+        assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(refused.headers().contains_key(RETRY_AFTER));
+        for response in [allowed, refused] {
+            for name in [LIMIT_HEADER, REMAINING_HEADER, RESET_HEADER] {
+                assert!(response.headers().get(&name).is_none(), "{name}");
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Request IDs for correlating log events.
+//!
+//! Every request gets an `x-request-id`: the client's own if it sent one,
+//! otherwise a fresh UUID. The ID is echoed in the response and recorded on
+//! a `request` span around the handler, so every `event!` emitted while
+//! serving the request carries it.
+
+use axum::{extract::Request, http::HeaderName, Router};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{info_span, Span};
+
+/// Header carrying the request ID in both directions.
+pub const HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Wraps every route of `router` with ID assignment, a span and propagation.
+pub fn apply(router: Router) -> Router {
+    router.layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::new(HEADER, MakeRequestUuid))
+            .layer(TraceLayer::new_for_http().make_span_with(span))
+            .layer(PropagateRequestIdLayer::new(HEADER)),
+    )
+}
+
+/// Opens the per-request span once the ID has been assigned.
+fn span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get(&HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    info_span!(
+        "request",
+        method = %request.method(),
+        path = request.uri().path(),
+        request_id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header::CONTENT_TYPE, response::Response};
+    use tower::ServiceExt;
+
+    async fn calculate(request_id: Option<&str>) -> Response {
+        let mut builder = Request::post("/api/calculate").header(CONTENT_TYPE, "application/json");
+        if let Some(request_id) = request_id {
+            builder = builder.header(&HEADER, request_id);
+        }
+        let request = builder
+            .body(Body::from(r#"{"weight_kg": 70, "height_m": 1.75}"#))
+            .unwrap();
+        crate::build_router(crate::AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_client_request_id_round_trips() {
+        let response = calculate(Some("kiosk-42")).await;
+
+        assert_eq!(response.headers()[&HEADER], "kiosk-42");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_generated() {
+        let first = calculate(None).await;
+        let second = calculate(None).await;
+
+        let id = |response: &Response| response.headers()[&HEADER].to_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&id(&first)).is_ok());
+        assert_ne!(id(&first), id(&second));
+    }
+}
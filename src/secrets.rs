@@ -0,0 +1,135 @@
+//! Secret-bearing configuration values.
+//!
+//! Every `[secrets]` key can be given inline (`admin_token`, or the
+//! `ADMIN_TOKEN` variable) or through the Docker/Kubernetes file convention
+//! (`admin_token_file`, or `ADMIN_TOKEN_FILE=/run/secrets/admin_token`), in
+//! which case the file is read once at startup and trimmed. Setting both forms
+//! is an error.
+
+use std::{fmt, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+/// Where a secret value came from; logged instead of the value itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// Not configured.
+    Unset,
+    /// The key itself, from `bmi.toml` or a variable.
+    Inline,
+    /// The file named by the `*_file` key.
+    File,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Unset => "unset",
+            Self::Inline => "inline",
+            Self::File => "file",
+        })
+    }
+}
+
+/// A resolved secret.
+pub struct Secret {
+    /// The value; `None` when unset or empty.
+    pub value: Option<String>,
+    /// Where it came from.
+    pub source: Source,
+}
+
+/// The resolved `[secrets]` section.
+pub struct Secrets {
+    /// Bearer token for `/api/admin/*`.
+    pub admin_token: Secret,
+    /// HMAC key for measurement tokens.
+    pub token_key: Secret,
+    /// HMAC key for history session cookies.
+    pub session_key: Secret,
+}
+
+/// Resolves secret `secrets.<name>` from its inline value or file.
+///
+/// Empty values count as unset.
+///
+/// # Errors
+///
+/// Returns error if:
+/// - Both `value` and `file` are set
+/// - The file cannot be read
+pub fn resolve(name: &str, value: Option<&str>, file: Option<&Path>) -> Result<Secret> {
+    match (value, file) {
+        (Some(_), Some(_)) => {
+            bail!("secrets.{name} and secrets.{name}_file are mutually exclusive; set only one")
+        }
+        (Some(value), None) => Ok(non_empty(value.to_string(), Source::Inline)),
+        (None, Some(path)) => {
+            let value = std::fs::read_to_string(path)
+                .with_context(|| format!("cannot read secrets.{name}_file ({})", path.display()))?;
+            Ok(non_empty(value.trim().to_string(), Source::File))
+        }
+        (None, None) => Ok(Secret {
+            value: None,
+            source: Source::Unset,
+        }),
+    }
+}
+
+fn non_empty(value: String, source: Source) -> Secret {
+    if value.is_empty() {
+        Secret {
+            value: None,
+            source: Source::Unset,
+        }
+    } else {
+        Secret {
+            value: Some(value),
+            source,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direct_and_unset() {
+        let secret = resolve("admin_token", Some("s3cret"), None).unwrap();
+        assert_eq!(secret.value.as_deref(), Some("s3cret"));
+        assert_eq!(secret.source, Source::Inline);
+
+        let secret = resolve("admin_token", Some(""), None).unwrap();
+        assert_eq!((secret.value, secret.source), (None, Source::Unset));
+    }
+
+    #[test]
+    fn test_file_is_read_and_trimmed() {
+        let path = std::env::temp_dir().join(format!("bmi-secret-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "from-file\n").unwrap();
+
+        let resolved = resolve("admin_token", None, Some(&path));
+        std::fs::remove_file(&path).unwrap();
+        let secret = resolved.unwrap();
+
+        assert_eq!(secret.value.as_deref(), Some("from-file"));
+        assert_eq!(secret.source, Source::File);
+    }
+
+    #[test]
+    fn test_both_forms_are_mutually_exclusive() {
+        let file = Path::new("/run/secrets/admin_token");
+        let error = resolve("admin_token", Some("a"), Some(file)).err().unwrap();
+
+        assert!(error.to_string().contains("mutually exclusive"));
+    }
+
+    #[test]
+    fn test_unreadable_file_names_the_key() {
+        let file = Path::new("/nonexistent/bmi-secret");
+        let error = resolve("admin_token", None, Some(file)).err().unwrap();
+
+        assert!(error.to_string().contains("secrets.admin_token_file"));
+    }
+}
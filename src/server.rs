@@ -0,0 +1,180 @@
+//! Server startup and shutdown.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use tracing::{event, Level};
+
+use crate::{config, connection, health, listener, memory, telemetry, tls, AppState};
+
+/// Serves the application with `config` until SIGTERM or Ctrl-C.
+///
+/// Initializes logging, binds the listeners, and drains connections on
+/// shutdown.
+///
+/// # Errors
+///
+/// Returns error if:
+/// - The port is already in use or the network interface is unavailable
+/// - [`AppState::new`] fails, e.g. on an unreadable secret file or history
+///   database
+/// - The TLS certificate or key cannot be loaded
+pub async fn run(config: config::AppConfig) -> Result<()> {
+    // Initialize tracing subscriber for structured logging (M-LOG-STRUCTURED)
+    tracing_subscriber::fmt()
+        .with_env_filter(config.log_filter.as_str())
+        .init();
+
+    event!(
+        name: "app.startup.initiated",
+        Level::INFO,
+        "Starting BMI Calculator application"
+    );
+
+    let state = AppState::new(&config)?;
+    if state.mock.is_some() {
+        event!(
+            name: "app.mock.enabled",
+            Level::WARN,
+            "Mock mode enabled: API routes return canned fixtures"
+        );
+    }
+    if let Some(window) = state.maintenance.current() {
+        event!(
+            name: "app.maintenance.boot",
+            Level::WARN,
+            message = window.message.as_str(),
+            "Starting in maintenance mode: {{message}}"
+        );
+    }
+
+    if let Some(handle) = &state.metrics {
+        telemetry::spawn_upkeep(handle.clone());
+    }
+    state.memory.log_sizing();
+    memory::spawn_sampler(Arc::clone(&state.memory));
+
+    let connections = Arc::clone(&state.connections);
+    let lifecycle = Arc::clone(&state.health);
+    // Build application routes
+    let app = crate::build_router(state);
+
+    let port = config.port;
+    let addr = std::net::SocketAddr::new(config.bind, port);
+    let tls = config.tls_acceptor()?;
+
+    // Bind exclusively, with SO_REUSEPORT, or adopt an inherited socket
+    let mode = listener::ListenerMode::from_config(&config)?;
+    let listener = listener::bind(addr, mode).await?;
+    let address = listener.local_addr()?.to_string();
+    let redirect = match config.tls.redirect_port {
+        Some(redirect_port) => {
+            let addr = std::net::SocketAddr::new(config.bind, redirect_port);
+            Some(listener::bind(addr, listener::ListenerMode::Exclusive).await?)
+        }
+        None => None,
+    };
+
+    event!(
+        name: "app.server.listening",
+        Level::INFO,
+        address = address.as_str(),
+        listener_mode = mode.as_str(),
+        tls = tls.is_some(),
+        "Server listening on {{address}} ({{listener_mode}}, tls: {{tls}})"
+    );
+    if let Some(redirect) = &redirect {
+        let address = redirect.local_addr()?.to_string();
+        event!(
+            name: "app.server.redirect_listening",
+            Level::INFO,
+            address = address.as_str(),
+            "Redirecting plain HTTP on {{address}} to HTTPS"
+        );
+    }
+    lifecycle.set_phase(health::Phase::Ready);
+
+    let limits = connections.limits();
+    event!(
+        name: "app.connection.limits",
+        Level::INFO,
+        max_total = limits.max_total,
+        max_per_ip = limits.max_per_ip,
+        header_timeout_secs = limits.header_timeout.as_secs(),
+        exempt_cidrs = limits.exempt.len(),
+        "Connection limits: {{max_total}} total, {{max_per_ip}} per IP, {{header_timeout_secs}}s header timeout"
+    );
+
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    println!("🚀 BMI Calculator running on {scheme}://localhost:{port}");
+    println!("📊 API endpoint: POST /api/calculate");
+    println!("📊 API endpoint: GET /api/calculate?weight_kg=70&height_m=1.75");
+    println!("📊 API endpoint: POST /api/calculate/batch");
+
+    // Both listeners run with connection-level limits until SIGTERM or Ctrl-C
+    let (stop, stopped) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        event!(
+            name: "app.shutdown.initiated",
+            Level::INFO,
+            "Shutdown signal received"
+        );
+        lifecycle.set_phase(health::Phase::Stopping);
+        let _ = stop.send(true);
+    });
+    let until_stopped = |mut stopped: tokio::sync::watch::Receiver<bool>| async move {
+        let _ = stopped.wait_for(|stopping| *stopping).await;
+    };
+    let drain_timeout = std::time::Duration::from_secs(config.drain_timeout_secs);
+
+    let redirect = redirect.map(|redirect| {
+        tokio::spawn(connection::serve(
+            redirect,
+            tls::redirect_router(port),
+            Arc::clone(&connections),
+            until_stopped(stopped.clone()),
+            drain_timeout,
+            None,
+        ))
+    });
+    connection::serve(
+        listener,
+        app,
+        connections,
+        until_stopped(stopped),
+        drain_timeout,
+        tls,
+    )
+    .await;
+    if let Some(redirect) = redirect {
+        redirect.await?;
+    }
+
+    Ok(())
+}
+
+/// Completes on Ctrl-C, or SIGTERM on unix (sent by `docker stop` and Heroku).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
@@ -0,0 +1,229 @@
+//! Anonymous sessions scoping the calculation history.
+//!
+//! While history is enabled, [`issue`] gives each browser a random user id in
+//! a signed, HttpOnly `bmi_session` cookie on its first visit. Calculations
+//! are stored under that id and `GET /api/history` only lists the caller's
+//! own. A cookie whose signature does not verify is replaced by a new session,
+//! never an error.
+//!
+//! The cookie is signed with HMAC-SHA256 under `BMI_SESSION_KEY` (or
+//! `BMI_SESSION_KEY_FILE`). Without one a random key is generated at startup
+//! and a warning logged, since every session then ends on restart.
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{COOKIE, SET_COOKIE},
+        HeaderMap, HeaderValue,
+    },
+    middleware::Next,
+    response::Response,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::AppState;
+
+/// Name of the session cookie.
+pub const COOKIE_NAME: &str = "bmi_session";
+
+/// Cookie lifetime in seconds (one year).
+const MAX_AGE_SECS: u64 = 365 * 24 * 60 * 60;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Anonymous user id of the current request, set by [`issue`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session(pub String);
+
+/// Key signing session cookies.
+#[derive(Clone)]
+pub struct SessionKey(Vec<u8>);
+
+impl Default for SessionKey {
+    /// A random key; sessions signed with it end when the process does.
+    fn default() -> Self {
+        let mut key = uuid::Uuid::new_v4().into_bytes().to_vec();
+        key.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+        Self(key)
+    }
+}
+
+impl SessionKey {
+    /// Wraps a configured key.
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self(key.into())
+    }
+
+    fn mac(&self, id: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        mac.update(id.as_bytes());
+        mac
+    }
+
+    /// Cookie value for `id`: the id, a dot and its signature.
+    pub fn sign(&self, id: &str) -> String {
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(id).finalize().into_bytes());
+        format!("{id}.{signature}")
+    }
+
+    /// Returns the id in `value` if its signature verifies.
+    pub fn verify(&self, value: &str) -> Option<Session> {
+        let (id, signature) = value.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(id).verify_slice(&signature).ok()?;
+        Some(Session(id.to_string()))
+    }
+}
+
+/// Finds the session cookie among the `Cookie` headers.
+fn cookie(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (name, value) = pair.trim().split_once('=')?;
+            (name == COOKIE_NAME).then_some(value)
+        })
+}
+
+/// Attaches the caller's [`Session`], starting one when the cookie is
+/// missing or invalid. Does nothing while history is disabled.
+pub async fn issue(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    if state.storage.is_none() {
+        return next.run(request).await;
+    }
+    if let Some(session) =
+        cookie(request.headers()).and_then(|value| state.session_key.verify(value))
+    {
+        request.extensions_mut().insert(session);
+        return next.run(request).await;
+    }
+
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let value = state.session_key.sign(&id);
+    request.extensions_mut().insert(Session(id));
+    let mut response = next.run(request).await;
+    let cookie =
+        format!("{COOKIE_NAME}={value}; Path=/; Max-Age={MAX_AGE_SECS}; HttpOnly; SameSite=Lax");
+    if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+        response.headers_mut().append(SET_COOKIE, cookie);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use axum::{
+        body::Body,
+        http::{header::CONTENT_TYPE, StatusCode},
+    };
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn with_history() -> AppState {
+        AppState {
+            storage: Some(Arc::new(SqliteStorage::open("sqlite::memory:").unwrap())),
+            ..AppState::default()
+        }
+    }
+
+    /// Sends `request` with `jar`, storing any cookie the server sets.
+    async fn send(state: &AppState, jar: &mut Option<String>, request: Request) -> Value {
+        let mut request = request;
+        if let Some(cookie) = jar {
+            request.headers_mut().insert(
+                COOKIE,
+                HeaderValue::from_str(&format!("theme=dark; {cookie}")).unwrap(),
+            );
+        }
+        let response = crate::build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        if let Some(set) = response.headers().get(SET_COOKIE) {
+            let set = set.to_str().unwrap();
+            assert!(set.contains("HttpOnly"), "{set}");
+            *jar = set.split(';').next().map(str::to_string);
+        }
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+    }
+
+    async fn calculate(state: &AppState, jar: &mut Option<String>, weight_kg: u32) {
+        let body = format!(r#"{{"weight_kg": {weight_kg}, "height_m": 1.75}}"#);
+        let request = Request::post("/api/calculate")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        send(state, jar, request).await;
+    }
+
+    async fn weights(state: &AppState, jar: &mut Option<String>) -> Vec<f64> {
+        let request = Request::get("/api/history").body(Body::empty()).unwrap();
+        send(state, jar, request).await["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["weight_kg"].as_f64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_signature_verifies_only_with_the_same_key() {
+        let key = SessionKey::new("secret");
+        let value = key.sign("abc");
+
+        assert_eq!(key.verify(&value), Some(Session("abc".to_string())));
+        assert_eq!(SessionKey::new("other").verify(&value), None);
+        assert_eq!(key.verify(&value.replace("abc", "abd")), None);
+        for garbage in ["", "abc", "abc.", "abc.!!!"] {
+            assert_eq!(key.verify(garbage), None, "{garbage}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cookie_jars_see_disjoint_histories() {
+        let state = with_history();
+        let (mut alice, mut bob) = (None, None);
+
+        calculate(&state, &mut alice, 70).await;
+        calculate(&state, &mut bob, 90).await;
+        calculate(&state, &mut alice, 71).await;
+
+        assert!(alice.is_some() && alice != bob);
+        assert_eq!(weights(&state, &mut alice).await, [71.0, 70.0]);
+        assert_eq!(weights(&state, &mut bob).await, [90.0]);
+    }
+
+    #[tokio::test]
+    async fn test_tampered_cookie_starts_a_new_session() {
+        let state = with_history();
+        let mut jar = None;
+        calculate(&state, &mut jar, 70).await;
+
+        let original = jar.clone().unwrap();
+        let (id, signature) = original.rsplit_once('.').unwrap();
+        let mut forged = Some(format!("{}0.{signature}", id));
+        assert!(weights(&state, &mut forged).await.is_empty());
+        assert_ne!(forged.as_deref(), Some(original.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_no_cookie_without_history() {
+        let response = crate::build_router(AppState::default())
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert!(response.headers().get(SET_COOKIE).is_none());
+    }
+}
@@ -0,0 +1,432 @@
+//! Public status page.
+//!
+//! `GET /status` renders a minimal HTML summary and `GET /api/status` the
+//! same data as JSON: overall state, each readiness check as up or down, the
+//! error rate and p95 latency of recent requests, and any maintenance window.
+//! Figures are rounded and check errors are left out, so the page is safe to
+//! expose publicly.
+//!
+//! Readiness checks only run when the cached snapshot is older than
+//! `status_cache_secs`, and responses carry a matching `Cache-Control`, so a
+//! crowd refreshing the page during an incident adds no load on subsystems.
+
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::header::CACHE_CONTROL,
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{html, AppState};
+
+/// Default lifetime of a snapshot.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// Requests kept for the error rate and latency figures.
+const WINDOW: usize = 1000;
+
+/// Error rate, in percent, from which the service counts as degraded.
+const DEGRADED_ERROR_PCT: f64 = 5.0;
+
+/// Latencies are rounded up to a multiple of this many milliseconds.
+const LATENCY_STEP_MS: f64 = 10.0;
+
+/// Overall or per-component state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// Working normally.
+    Operational,
+    /// Serving, with a failing check or elevated errors.
+    Degraded,
+    /// A maintenance window is active.
+    Maintenance,
+    /// A component check failed.
+    Down,
+}
+
+impl Condition {
+    /// Label shown on the HTML page.
+    fn label(self) -> &'static str {
+        match self {
+            Self::Operational => "Operational",
+            Self::Degraded => "Degraded",
+            Self::Maintenance => "Under maintenance",
+            Self::Down => "Down",
+        }
+    }
+}
+
+/// One readiness check, without its error detail.
+#[derive(Clone, Debug, Serialize)]
+pub struct Component {
+    /// Name the check was registered under.
+    pub name: String,
+    /// `operational` or `down`.
+    pub status: Condition,
+}
+
+/// Rounded figures over the most recent requests.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Traffic {
+    /// Share of 5xx responses, in whole percent.
+    pub error_rate_pct: u32,
+    /// 95th percentile latency, rounded up to 10 ms; absent without traffic.
+    pub p95_latency_ms: Option<u64>,
+}
+
+/// Announced maintenance, as shown in the page banner.
+#[derive(Clone, Debug, Serialize)]
+pub struct MaintenanceNotice {
+    /// Operator message.
+    pub message: String,
+    /// Expected end, if announced.
+    pub estimated_end: Option<DateTime<Utc>>,
+}
+
+/// Body of `GET /api/status`.
+#[derive(Clone, Debug, Serialize)]
+pub struct Snapshot {
+    /// Overall state.
+    pub status: Condition,
+    /// Readiness checks in registration order.
+    pub components: Vec<Component>,
+    /// Recent request figures.
+    pub traffic: Traffic,
+    /// Active maintenance window, if any.
+    pub maintenance: Option<MaintenanceNotice>,
+    /// When the snapshot was taken.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One finished request.
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    failed: bool,
+    latency: Duration,
+}
+
+/// Recent requests and the cached snapshot.
+#[derive(Debug)]
+pub struct StatusPage {
+    ttl: Duration,
+    recent: Mutex<VecDeque<Sample>>,
+    /// Held while refreshing, so concurrent misses share one set of checks.
+    cached: tokio::sync::Mutex<Option<(Instant, Snapshot)>>,
+}
+
+impl Default for StatusPage {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_TTL)
+    }
+}
+
+impl StatusPage {
+    /// Creates the page with snapshots reused for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            recent: Mutex::new(VecDeque::with_capacity(WINDOW)),
+            cached: tokio::sync::Mutex::default(),
+        }
+    }
+
+    /// Adds a finished request to the window.
+    pub fn record(&self, failed: bool, latency: Duration) {
+        let mut recent = self.recent.lock().expect("status window lock poisoned");
+        if recent.len() == WINDOW {
+            recent.pop_front();
+        }
+        recent.push_back(Sample { failed, latency });
+    }
+
+    /// Rounded error rate and p95 of the window.
+    fn traffic(&self) -> Traffic {
+        let recent = self.recent.lock().expect("status window lock poisoned");
+        if recent.is_empty() {
+            return Traffic::default();
+        }
+        let failed = recent.iter().filter(|sample| sample.failed).count();
+        let mut latencies: Vec<Duration> = recent.iter().map(|sample| sample.latency).collect();
+        latencies.sort_unstable();
+        let rank = (latencies.len() * 95).div_ceil(100).max(1);
+        let p95_ms = latencies[rank - 1].as_secs_f64() * 1000.0;
+        Traffic {
+            error_rate_pct: (failed as f64 * 100.0 / recent.len() as f64).round() as u32,
+            p95_latency_ms: Some(((p95_ms / LATENCY_STEP_MS).ceil() * LATENCY_STEP_MS) as u64),
+        }
+    }
+}
+
+/// Returns the cached snapshot, or takes a new one once it has expired.
+async fn snapshot(state: &AppState) -> Snapshot {
+    let page = &state.status;
+    let mut cached = page.cached.lock().await;
+    if let Some((taken, snapshot)) = &*cached {
+        if taken.elapsed() < page.ttl {
+            return snapshot.clone();
+        }
+    }
+
+    let components: Vec<Component> = state
+        .health
+        .run_checks()
+        .await
+        .into_iter()
+        .map(|check| Component {
+            name: check.name,
+            status: if check.error.is_none() {
+                Condition::Operational
+            } else {
+                Condition::Down
+            },
+        })
+        .collect();
+    let traffic = page.traffic();
+    let maintenance = state.maintenance.current().map(|window| MaintenanceNotice {
+        message: window.message,
+        estimated_end: window.estimated_end,
+    });
+    let status = if maintenance.is_some() {
+        Condition::Maintenance
+    } else if components
+        .iter()
+        .any(|component| component.status == Condition::Down)
+        || f64::from(traffic.error_rate_pct) >= DEGRADED_ERROR_PCT
+    {
+        Condition::Degraded
+    } else {
+        Condition::Operational
+    };
+
+    let snapshot = Snapshot {
+        status,
+        components,
+        traffic,
+        maintenance,
+        updated_at: Utc::now(),
+    };
+    *cached = Some((Instant::now(), snapshot.clone()));
+    snapshot
+}
+
+/// `Cache-Control` matching the snapshot lifetime.
+fn cache_control(state: &AppState) -> String {
+    format!("public, max-age={}", state.status.ttl.as_secs())
+}
+
+/// Feeds every routed request into the status window.
+pub async fn record(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let response = next.run(request).await;
+    state
+        .status
+        .record(response.status().is_server_error(), started.elapsed());
+    response
+}
+
+/// Handles `GET /api/status`.
+pub async fn status_json_handler(State(state): State<AppState>) -> Response {
+    let snapshot = snapshot(&state).await;
+    ([(CACHE_CONTROL, cache_control(&state))], Json(snapshot)).into_response()
+}
+
+/// Handles `GET /status`.
+pub async fn status_page_handler(State(state): State<AppState>) -> Response {
+    let snapshot = snapshot(&state).await;
+    (
+        [(CACHE_CONTROL, cache_control(&state))],
+        Html(render(&snapshot)),
+    )
+        .into_response()
+}
+
+/// Renders the HTML page for a snapshot.
+fn render(snapshot: &Snapshot) -> String {
+    let components: String = snapshot
+        .components
+        .iter()
+        .map(|component| {
+            format!(
+                "<li>{}: <strong>{}</strong></li>\n",
+                html::escape(&component.name),
+                component.status.label()
+            )
+        })
+        .collect();
+    let components = if components.is_empty() {
+        String::new()
+    } else {
+        format!("<h2>Components</h2>\n<ul>\n{components}</ul>")
+    };
+    let maintenance = snapshot
+        .maintenance
+        .as_ref()
+        .map(|notice| {
+            let until = notice
+                .estimated_end
+                .map(|end| format!(" Expected back by {}.", end.format("%Y-%m-%d %H:%M UTC")))
+                .unwrap_or_default();
+            format!(
+                r#"<p class="maintenance" role="status">{}.{until}</p>"#,
+                html::escape(notice.message.trim_end_matches('.'))
+            )
+        })
+        .unwrap_or_default();
+    let latency = snapshot
+        .traffic
+        .p95_latency_ms
+        .map_or_else(|| "no recent requests".to_string(), |ms| format!("{ms} ms"));
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8">
+<meta name="viewport" content="width=device-width, initial-scale=1.0">
+<title>BMI Calculator status</title>
+<style>
+body {{ font-family: sans-serif; max-width: 40em; margin: 1em auto; padding: 0 1em; line-height: 1.5; }}
+</style>
+</head>
+<body>
+<main>
+<h1>BMI Calculator status</h1>
+<p>Overall: <strong>{status}</strong></p>
+{maintenance}
+{components}
+<h2>Recent requests</h2>
+<ul>
+<li>Error rate: {error_rate}%</li>
+<li>95th percentile latency: {latency}</li>
+</ul>
+<p>Updated {updated}.</p>
+</main>
+</body>
+</html>"#,
+        status = snapshot.status.label(),
+        error_rate = snapshot.traffic.error_rate_pct,
+        updated = snapshot.updated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::CheckFn;
+    use axum::{body::Body, http::StatusCode};
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get(state: &AppState, uri: &str) -> (StatusCode, String, String) {
+        let response = crate::build_router(state.clone())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let cache = response.headers()[CACHE_CONTROL]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, cache, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    fn check(result: Result<(), &'static str>) -> CheckFn {
+        Arc::new(move || Box::pin(async move { result.map_err(String::from) }))
+    }
+
+    #[tokio::test]
+    async fn test_json_shape() {
+        let state = AppState::default();
+        state.health.register("storage", check(Ok(())));
+        for millis in 1..=100 {
+            state
+                .status
+                .record(millis > 97, Duration::from_millis(millis));
+        }
+
+        let (status, cache, body) = get(&state, "/api/status").await;
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(cache, "public, max-age=10");
+        assert_eq!(body["status"], "operational");
+        assert_eq!(body["components"][0]["name"], "storage");
+        assert_eq!(body["components"][0]["status"], "operational");
+        assert_eq!(body["traffic"]["error_rate_pct"], 3);
+        assert_eq!(body["traffic"]["p95_latency_ms"], 100);
+        assert!(body["maintenance"].is_null());
+        assert!(body["updated_at"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_is_cached() {
+        let state = AppState {
+            status: Arc::new(StatusPage::new(Duration::from_secs(60))),
+            ..AppState::default()
+        };
+        let (_, _, first) = get(&state, "/api/status").await;
+
+        state.health.register("storage", check(Err("disk full")));
+        let (_, cache, second) = get(&state, "/api/status").await;
+        assert_eq!(first, second);
+        assert_eq!(cache, "public, max-age=60");
+
+        let state = AppState {
+            status: Arc::new(StatusPage::new(Duration::ZERO)),
+            ..state
+        };
+        let (_, _, fresh) = get(&state, "/api/status").await;
+        assert!(fresh.contains(r#""status":"degraded""#), "{fresh}");
+    }
+
+    #[tokio::test]
+    async fn test_page_shows_degraded_component() {
+        let state = AppState::default();
+        state
+            .health
+            .register("storage", check(Err("secret detail")));
+        state.health.register("webhooks", check(Ok(())));
+
+        let (status, _, page) = get(&state, "/status").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            page.contains("Overall: <strong>Degraded</strong>"),
+            "{page}"
+        );
+        assert!(page.contains("storage: <strong>Down</strong>"), "{page}");
+        assert!(
+            page.contains("webhooks: <strong>Operational</strong>"),
+            "{page}"
+        );
+        assert!(page.contains("no recent requests"), "{page}");
+        assert!(!page.contains("secret detail"));
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_takes_precedence() {
+        let state = AppState::default();
+        state.maintenance.set(Some(crate::maintenance::Window {
+            message: "Upgrading <db>".to_string(),
+            estimated_end: None,
+        }));
+
+        let (status, _, page) = get(&state, "/status").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains("Under maintenance"), "{page}");
+        assert!(page.contains("Upgrading &lt;db&gt;."), "{page}");
+    }
+}
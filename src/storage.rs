@@ -0,0 +1,709 @@
+//! Optional calculation history.
+//!
+//! With `database_url` set (`BMI_DATABASE_URL`), every successful
+//! `POST /api/calculate` is stored with its timestamp, SI inputs, BMI and
+//! category, and `GET /api/history` lists the caller's stored rows newest
+//! first. Rows belong to the anonymous session that made them (see
+//! [`crate::session`]).
+//! Replayed form tokens are not stored again, and GET calculations stay free
+//! of side effects. Without a URL nothing is stored and the history route
+//! answers 404.
+//!
+//! [`SqliteStorage`] applies pending migrations when it opens, tracking the
+//! schema version in SQLite's `user_version`. A failed write is logged and
+//! the user still gets their BMI.
+
+use std::{path::Path, sync::Mutex, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{error::BmiError, session::Session, units, AppState, BmiRequest, BmiResponse};
+
+/// Rows returned when `limit` is not given.
+pub const DEFAULT_LIMIT: u32 = 50;
+
+/// Most rows returned by one history request.
+pub const MAX_LIMIT: u32 = 500;
+
+/// Rows [`SqliteStorage`] reads per lock while walking a session's history.
+const PAGE_ROWS: usize = 256;
+
+/// How long a write waits for a locked database file.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema changes in order; entry `n` moves `user_version` from `n` to `n + 1`.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE calculations (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        recorded_at TEXT NOT NULL,
+        units TEXT NOT NULL,
+        weight_kg REAL NOT NULL,
+        height_m REAL NOT NULL,
+        bmi REAL NOT NULL,
+        category TEXT NOT NULL
+    )",
+    "ALTER TABLE calculations ADD COLUMN session TEXT;
+    CREATE INDEX calculations_by_session ON calculations (session, id)",
+];
+
+/// A successful calculation to store.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Calculation {
+    /// When the calculation was made.
+    #[schema(value_type = String, format = DateTime)]
+    pub recorded_at: DateTime<Utc>,
+    /// Unit system the request was given in.
+    pub units: units::UnitSystem,
+    /// Weight in kilograms, after any conversion.
+    pub weight_kg: f64,
+    /// Height in meters, after any conversion.
+    pub height_m: f64,
+    /// Calculated BMI.
+    pub bmi: f64,
+    /// WHO category.
+    pub category: String,
+}
+
+/// A stored calculation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Record {
+    /// Row identifier, increasing with insertion order.
+    pub id: i64,
+    /// The stored values.
+    #[serde(flatten)]
+    pub calculation: Calculation,
+}
+
+/// Backend holding the calculation history.
+///
+/// Methods block; callers run them on the blocking thread pool.
+pub trait Storage: Send + Sync {
+    /// Stores one calculation made by `session`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the backend cannot be written.
+    fn insert(&self, session: Option<&str>, calculation: &Calculation) -> Result<()>;
+
+    /// Lists up to `limit` of `session`'s records newest first, skipping
+    /// `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the backend cannot be read.
+    fn history(&self, session: &str, limit: u32, offset: u32) -> Result<Vec<Record>>;
+
+    /// Calls `visit` with each of `session`'s records, oldest first, without
+    /// loading them all at once.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the backend cannot be read or `visit` fails.
+    fn each_for_session(
+        &self,
+        session: &str,
+        visit: &mut dyn FnMut(Record) -> Result<()>,
+    ) -> Result<()>;
+}
+
+/// History in a SQLite database.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    /// Opens `url` and applies pending migrations.
+    ///
+    /// Accepts `sqlite::memory:`, `sqlite://<path>`, `sqlite:<path>` or a
+    /// bare file path.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the database cannot be opened or migrated.
+    pub fn open(url: &str) -> Result<Self> {
+        let connection = match url.trim() {
+            "sqlite::memory:" | ":memory:" => Connection::open_in_memory(),
+            url => {
+                let path = url
+                    .strip_prefix("sqlite://")
+                    .or_else(|| url.strip_prefix("sqlite:"))
+                    .unwrap_or(url);
+                if path.is_empty() {
+                    bail!("database_url has no path");
+                }
+                Connection::open(Path::new(path))
+            }
+        }
+        .with_context(|| format!("cannot open database {url}"))?;
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .context("cannot configure database")?;
+        migrate(&connection)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Calls `visit` with every record and its session, oldest first, one
+    /// row at a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the database cannot be read or `visit` fails.
+    pub fn each_record(
+        &self,
+        mut visit: impl FnMut(Option<String>, Record) -> Result<()>,
+    ) -> Result<()> {
+        let connection = self.lock();
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT {COLUMNS}, session FROM calculations ORDER BY id"
+            ))
+            .context("cannot read history")?;
+        let mut rows = statement.query([]).context("cannot read history")?;
+        while let Some(row) = rows.next().context("cannot read history row")? {
+            let session = row.get(7).context("cannot read history row")?;
+            visit(session, read_record(row)?)?;
+        }
+        Ok(())
+    }
+
+    /// Inserts or overwrites records by id in one transaction; returns how
+    /// many rows were added or changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if an item is an error or the database cannot be
+    /// written; nothing is stored then.
+    pub fn upsert(
+        &self,
+        records: impl IntoIterator<Item = Result<(Option<String>, Record)>>,
+    ) -> Result<u64> {
+        let mut connection = self.lock();
+        let transaction = connection.transaction().context("cannot store history")?;
+        let mut changed = 0;
+        {
+            let mut statement = transaction
+                .prepare(
+                    "INSERT INTO calculations
+                         (id, recorded_at, units, weight_kg, height_m, bmi, category, session)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                     ON CONFLICT (id) DO UPDATE SET
+                         recorded_at = excluded.recorded_at,
+                         units = excluded.units,
+                         weight_kg = excluded.weight_kg,
+                         height_m = excluded.height_m,
+                         bmi = excluded.bmi,
+                         category = excluded.category,
+                         session = excluded.session
+                     WHERE (recorded_at, units, weight_kg, height_m, bmi, category, session)
+                         IS NOT (excluded.recorded_at, excluded.units, excluded.weight_kg,
+                                 excluded.height_m, excluded.bmi, excluded.category,
+                                 excluded.session)",
+                )
+                .context("cannot store history")?;
+            for record in records {
+                let (session, record) = record?;
+                let calculation = &record.calculation;
+                changed += statement
+                    .execute(params![
+                        record.id,
+                        calculation.recorded_at.to_rfc3339(),
+                        calculation.units.as_str(),
+                        calculation.weight_kg,
+                        calculation.height_m,
+                        calculation.bmi,
+                        calculation.category,
+                        session,
+                    ])
+                    .with_context(|| format!("cannot store history row {}", record.id))?
+                    as u64;
+            }
+        }
+        transaction.commit().context("cannot store history")?;
+        Ok(changed)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Brings the schema up to date.
+fn migrate(connection: &Connection) -> Result<()> {
+    let version: usize = connection
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .context("cannot read schema version")?;
+    if version > MIGRATIONS.len() {
+        bail!(
+            "database schema version {version} is newer than this build ({})",
+            MIGRATIONS.len()
+        );
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let next = index + 1;
+        connection
+            .execute_batch(&format!(
+                "BEGIN; {migration}; PRAGMA user_version = {next}; COMMIT;"
+            ))
+            .with_context(|| format!("migration {next} failed"))?;
+        event!(
+            name: "app.storage.migrated",
+            Level::INFO,
+            version = next,
+            "Database schema migrated to version {{version}}"
+        );
+    }
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn insert(&self, session: Option<&str>, calculation: &Calculation) -> Result<()> {
+        self.lock()
+            .execute(
+                "INSERT INTO calculations
+                     (recorded_at, units, weight_kg, height_m, bmi, category, session)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    calculation.recorded_at.to_rfc3339(),
+                    calculation.units.as_str(),
+                    calculation.weight_kg,
+                    calculation.height_m,
+                    calculation.bmi,
+                    calculation.category,
+                    session,
+                ],
+            )
+            .context("cannot store calculation")?;
+        Ok(())
+    }
+
+    fn history(&self, session: &str, limit: u32, offset: u32) -> Result<Vec<Record>> {
+        let connection = self.lock();
+        let mut statement = connection
+            .prepare_cached(&format!(
+                "SELECT {COLUMNS} FROM calculations WHERE session = ?1
+                 ORDER BY id DESC LIMIT ?2 OFFSET ?3"
+            ))
+            .context("cannot read history")?;
+        let mut rows = statement
+            .query(params![session, limit, offset])
+            .context("cannot read history")?;
+        let mut records = Vec::new();
+        while let Some(row) = rows.next().context("cannot read history row")? {
+            records.push(read_record(row)?);
+        }
+        Ok(records)
+    }
+
+    /// Reads [`PAGE_ROWS`] rows at a time and releases the connection
+    /// before visiting them, so a slow visitor never holds up other calls.
+    fn each_for_session(
+        &self,
+        session: &str,
+        visit: &mut dyn FnMut(Record) -> Result<()>,
+    ) -> Result<()> {
+        let mut after = i64::MIN;
+        loop {
+            let page = {
+                let connection = self.lock();
+                let mut statement = connection
+                    .prepare_cached(&format!(
+                        "SELECT {COLUMNS} FROM calculations WHERE session = ?1 AND id > ?2
+                         ORDER BY id LIMIT ?3"
+                    ))
+                    .context("cannot read history")?;
+                let mut rows = statement
+                    .query(params![session, after, PAGE_ROWS])
+                    .context("cannot read history")?;
+                let mut page = Vec::with_capacity(PAGE_ROWS);
+                while let Some(row) = rows.next().context("cannot read history row")? {
+                    page.push(read_record(row)?);
+                }
+                page
+            };
+            let Some(last) = page.last() else {
+                return Ok(());
+            };
+            after = last.id;
+            let complete = page.len() < PAGE_ROWS;
+            for record in page {
+                visit(record)?;
+            }
+            if complete {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Columns [`read_record`] expects, in order.
+const COLUMNS: &str = "id, recorded_at, units, weight_kg, height_m, bmi, category";
+
+/// Converts a row selected with [`COLUMNS`].
+fn read_record(row: &rusqlite::Row<'_>) -> Result<Record> {
+    let id = row.get(0).context("cannot read history row")?;
+    let read = || -> rusqlite::Result<(String, String, f64, f64, f64, String)> {
+        Ok((
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            row.get(6)?,
+        ))
+    };
+    let (recorded_at, units, weight_kg, height_m, bmi, category) =
+        read().with_context(|| format!("cannot read history row {id}"))?;
+    Ok(Record {
+        id,
+        calculation: Calculation {
+            recorded_at: DateTime::parse_from_rfc3339(&recorded_at)
+                .with_context(|| format!("row {id} has an invalid timestamp"))?
+                .with_timezone(&Utc),
+            units: match units.as_str() {
+                "imperial" => units::UnitSystem::Imperial,
+                _ => units::UnitSystem::Metric,
+            },
+            weight_kg,
+            height_m,
+            bmi,
+            category,
+        },
+    })
+}
+
+/// Stores a freshly computed response under `session`; failures are only
+/// logged.
+pub async fn record(
+    state: &AppState,
+    session: Option<&Session>,
+    request: &BmiRequest,
+    response: &BmiResponse,
+) {
+    let Some(storage) = state.storage.clone() else {
+        return;
+    };
+    // The request was just calculated, so it normalizes again
+    let Ok(measurements) = units::normalize(request) else {
+        return;
+    };
+    let calculation = Calculation {
+        recorded_at: Utc::now(),
+        units: response.units,
+        weight_kg: measurements.weight_kg,
+        height_m: measurements.height_m,
+        bmi: response.bmi,
+        category: response.category.clone(),
+    };
+    let session = session.map(|Session(id)| id.clone());
+    let outcome =
+        tokio::task::spawn_blocking(move || storage.insert(session.as_deref(), &calculation))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|outcome| outcome);
+    if let Err(error) = outcome {
+        event!(
+            name: "app.storage.write_failed",
+            Level::WARN,
+            error = format!("{error:#}"),
+            "Calculation not stored: {{error}}"
+        );
+    }
+}
+
+/// Query parameters for `GET /api/history`.
+#[derive(Debug, Default, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    /// Rows to return, at most 500 (default 50).
+    pub limit: Option<u32>,
+    /// Newest rows to skip (default 0).
+    pub offset: Option<u32>,
+}
+
+/// Response body for `GET /api/history`.
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct HistoryResponse {
+    /// Limit applied.
+    pub limit: u32,
+    /// Offset applied.
+    pub offset: u32,
+    /// Stored calculations, newest first.
+    pub records: Vec<Record>,
+}
+
+/// Handles `GET /api/history`.
+///
+/// # Errors
+///
+/// Returns HTTP 404 when no database is configured, HTTP 400 for a
+/// non-numeric or too large `limit`, and HTTP 500 if the database cannot be
+/// read.
+#[utoipa::path(
+    get,
+    path = "/api/history",
+    tag = "history",
+    summary = "List stored calculations",
+    description = "The calling session's calculations, newest first. Available when the server runs with a database.",
+    params(HistoryQuery),
+    responses(
+        (status = 200, description = "Stored calculations", body = HistoryResponse),
+        (status = 400, description = "Invalid limit or offset", body = crate::error::ErrorResponse),
+        (status = 404, description = "History is not enabled", body = crate::error::ErrorResponse),
+        (status = 500, description = "Database unavailable", body = crate::error::ErrorResponse),
+    )
+)]
+pub async fn history_handler(
+    State(state): State<AppState>,
+    session: Option<Extension<Session>>,
+    query: Result<Query<HistoryQuery>, QueryRejection>,
+) -> Response {
+    let Some(storage) = state.storage.clone() else {
+        return BmiError::NotFound(
+            "History is not enabled on this server (set BMI_DATABASE_URL)".to_string(),
+        )
+        .into_response();
+    };
+    let Query(query) = match query {
+        Ok(query) => query,
+        Err(rejection) => return BmiError::InvalidQuery(rejection.body_text()).into_response(),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit > MAX_LIMIT {
+        return BmiError::InvalidQuery(format!("limit must be at most {MAX_LIMIT}"))
+            .into_response();
+    }
+    let offset = query.offset.unwrap_or(0);
+
+    // Without a session cookie there is nothing of the caller's to list
+    let Some(Extension(Session(session))) = session else {
+        return Json(HistoryResponse {
+            limit,
+            offset,
+            records: Vec::new(),
+        })
+        .into_response();
+    };
+
+    let outcome = tokio::task::spawn_blocking(move || storage.history(&session, limit, offset))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|outcome| outcome);
+    match outcome {
+        Ok(records) => Json(HistoryResponse {
+            limit,
+            offset,
+            records,
+        })
+        .into_response(),
+        Err(error) => {
+            event!(
+                name: "app.storage.read_failed",
+                Level::WARN,
+                error = format!("{error:#}"),
+                "History not available: {{error}}"
+            );
+            BmiError::Internal("History is temporarily unavailable".to_string()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{
+            header::{CONTENT_TYPE, COOKIE},
+            StatusCode,
+        },
+    };
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn memory() -> SqliteStorage {
+        SqliteStorage::open("sqlite::memory:").unwrap()
+    }
+
+    fn with_storage(storage: impl Storage + 'static) -> AppState {
+        AppState {
+            storage: Some(Arc::new(storage)),
+            ..AppState::default()
+        }
+    }
+
+    /// Sends `request` as one fixed session.
+    async fn send(state: &AppState, mut request: Request) -> (StatusCode, Value) {
+        let cookie = format!(
+            "{}={}",
+            crate::session::COOKIE_NAME,
+            state.session_key.sign("tester")
+        );
+        request
+            .headers_mut()
+            .insert(COOKIE, cookie.parse().unwrap());
+        let response = crate::build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    async fn calculate(state: &AppState, body: &str) -> StatusCode {
+        let request = Request::post("/api/calculate")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        send(state, request).await.0
+    }
+
+    async fn history(state: &AppState, query: &str) -> (StatusCode, Value) {
+        let uri = format!("/api/history{query}");
+        send(state, Request::get(uri).body(Body::empty()).unwrap()).await
+    }
+
+    fn calculation(bmi: f64) -> Calculation {
+        Calculation {
+            recorded_at: Utc::now(),
+            units: units::UnitSystem::Metric,
+            weight_kg: 70.0,
+            height_m: 1.75,
+            bmi,
+            category: "Normal weight".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let storage = memory();
+        let connection = storage.lock();
+        migrate(&connection).unwrap();
+
+        let version: usize = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn test_history_is_per_session_newest_first_and_paged() {
+        let storage = memory();
+        for bmi in [21.0, 22.0, 23.0] {
+            storage.insert(Some("a"), &calculation(bmi)).unwrap();
+            storage.insert(Some("b"), &calculation(bmi + 10.0)).unwrap();
+        }
+
+        let page: Vec<f64> = storage
+            .history("a", 2, 0)
+            .unwrap()
+            .iter()
+            .map(|record| record.calculation.bmi)
+            .collect();
+        assert_eq!(page, [23.0, 22.0]);
+        let rest = storage.history("a", 2, 2).unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(
+            rest[0].calculation,
+            storage.history("a", 1, 2).unwrap()[0].calculation
+        );
+    }
+
+    #[tokio::test]
+    async fn test_calculations_are_recorded() {
+        let state = with_storage(memory());
+        let token = state.form_tokens.issue();
+        let replayed = format!(r#"{{"weight_kg": 80, "height_m": 1.8, "form_token": "{token}"}}"#);
+
+        assert_eq!(
+            calculate(&state, r#"{"weight_kg": 70, "height_m": 1.75}"#).await,
+            StatusCode::OK
+        );
+        assert_eq!(calculate(&state, &replayed).await, StatusCode::OK);
+        assert_eq!(calculate(&state, &replayed).await, StatusCode::OK);
+        assert_eq!(
+            calculate(&state, r#"{"weight_kg": -1, "height_m": 1.75}"#).await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let (status, body) = history(&state, "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["limit"], DEFAULT_LIMIT);
+        let records = body["records"].as_array().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["weight_kg"], 80.0);
+        assert_eq!(records[0]["height_m"], 1.8);
+        assert_eq!(records[1]["category"], "Normal weight");
+        assert!(records[1]["recorded_at"].is_string());
+
+        let (_, body) = history(&state, "?limit=1&offset=1").await;
+        assert_eq!(body["records"][0]["weight_kg"], 70.0);
+        for query in ["?limit=abc", "?limit=501"] {
+            let (status, body) = history(&state, query).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+            assert_eq!(body["error"]["code"], "invalid_query");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_disabled_without_database() {
+        let state = AppState::default();
+
+        assert_eq!(
+            calculate(&state, r#"{"weight_kg": 70, "height_m": 1.75}"#).await,
+            StatusCode::OK
+        );
+        let (status, body) = history(&state, "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "not_found");
+    }
+
+    struct Unreachable;
+
+    impl Storage for Unreachable {
+        fn insert(&self, _: Option<&str>, _: &Calculation) -> Result<()> {
+            bail!("connection refused")
+        }
+
+        fn history(&self, _: &str, _: u32, _: u32) -> Result<Vec<Record>> {
+            bail!("connection refused")
+        }
+
+        fn each_for_session(&self, _: &str, _: &mut dyn FnMut(Record) -> Result<()>) -> Result<()> {
+            bail!("connection refused")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_database_failure_still_returns_bmi() {
+        let state = with_storage(Unreachable);
+
+        assert_eq!(
+            calculate(&state, r#"{"weight_kg": 70, "height_m": 1.75}"#).await,
+            StatusCode::OK
+        );
+        let (status, body) = history(&state, "").await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"]["code"], "internal_error");
+    }
+}
@@ -0,0 +1,256 @@
+//! Prometheus metrics.
+//!
+//! [`track`] records every API request as `http_requests_total` and
+//! `http_request_duration_seconds`, labeled by method, matched route and
+//! status. Calculations add `bmi_calculation_success_total` and
+//! `bmi_validation_failed_total` (by error code) for alerting on validation
+//! spikes. `GET /metrics` serves the scrape output.
+
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+use crate::AppState;
+
+/// Latency buckets in seconds, from sub-millisecond to slow batches.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+/// How often [`spawn_upkeep`] drains histogram buffers.
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Installs the process-wide recorder on first call and returns its handle.
+///
+/// Later calls return the same handle, so tests can share one registry.
+///
+/// # Errors
+///
+/// Returns error if another recorder was installed outside this function.
+pub fn install() -> Result<PrometheusHandle> {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    if let Some(handle) = HANDLE.get() {
+        return Ok(handle.clone());
+    }
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full("http_request_duration_seconds".to_string()),
+            LATENCY_BUCKETS,
+        )
+        .context("invalid latency buckets")?
+        .install_recorder()
+        .context("a metrics recorder is already installed")?;
+    Ok(HANDLE.get_or_init(|| handle).clone())
+}
+
+/// Periodically compacts histogram samples so memory stays bounded.
+///
+/// Needed because the recorder runs without its own HTTP exporter.
+pub fn spawn_upkeep(handle: PrometheusHandle) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            handle.run_upkeep();
+        }
+    });
+}
+
+/// Records method, route, status and duration of each request.
+pub async fn track(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    metrics::counter!("http_requests_total", &labels).increment(1);
+    metrics::histogram!("http_request_duration_seconds", &labels)
+        .record(started.elapsed().as_secs_f64());
+    response
+}
+
+/// Counts a successful calculation.
+pub fn calculation_succeeded() {
+    metrics::counter!("bmi_calculation_success_total").increment(1);
+}
+
+/// Counts a rejected calculation by error code.
+pub fn validation_failed(code: &'static str) {
+    metrics::counter!("bmi_validation_failed_total", "code" => code).increment(1);
+}
+
+/// Counts a connection refused at accept time, by the cap it hit.
+pub fn connection_rejected(reason: &'static str) {
+    metrics::counter!("connections_rejected_total", "reason" => reason).increment(1);
+}
+
+/// Counts a connection closed for not sending its headers in time.
+pub fn connection_timed_out() {
+    metrics::counter!("connections_timed_out_total").increment(1);
+}
+
+/// Counts an API request refused with 429.
+pub fn rate_limited() {
+    metrics::counter!("rate_limited_total").increment(1);
+}
+
+/// Counts a request shed with 503 under memory pressure.
+pub fn load_shed() {
+    metrics::counter!("load_shed_total").increment(1);
+}
+
+/// Handles `GET /metrics` in the Prometheus text format.
+///
+/// # Errors
+///
+/// Returns HTTP 404 when metrics are disabled.
+pub async fn metrics_handler(State(state): State<AppState>) -> Response {
+    match &state.metrics {
+        Some(handle) => (
+            [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+            handle.render(),
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        connection::{self, Connections},
+        memory::Memory,
+        rate_limit::RateLimiter,
+    };
+    use axum::{body::Body, http::header::CONTENT_TYPE};
+    use http_body_util::BodyExt;
+    use std::{net::IpAddr, sync::Arc};
+    use tower::ServiceExt;
+
+    async fn send(state: &AppState, request: Request) -> (StatusCode, String) {
+        let response = crate::build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    fn calculate(body: &str) -> Request {
+        Request::post("/api/calculate")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_calculations_show_up_in_metrics() {
+        let state = AppState {
+            metrics: Some(install().unwrap()),
+            ..AppState::default()
+        };
+        send(&state, calculate(r#"{"weight_kg": 70, "height_m": 1.75}"#)).await;
+        send(&state, calculate(r#"{"weight_kg": -1, "height_m": 1.75}"#)).await;
+
+        let (status, text) = send(
+            &state,
+            Request::get("/metrics").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(text.contains("bmi_calculation_success_total"), "{text}");
+        assert!(
+            text.contains(r#"bmi_validation_failed_total{code="validation_failed"}"#),
+            "{text}"
+        );
+        assert!(
+            text.contains(
+                r#"http_requests_total{method="POST",route="/api/calculate",status="200"}"#
+            ),
+            "{text}"
+        );
+        assert!(
+            text.contains("http_request_duration_seconds_bucket"),
+            "{text}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refusals_show_up_in_metrics() {
+        let state = AppState {
+            metrics: Some(install().unwrap()),
+            rate_limiter: Some(Arc::new(RateLimiter::new(0.001, 1.0, true))),
+            memory: Arc::new(Memory::new(Some(64))),
+            connections: Arc::new(Connections::new(connection::Limits {
+                max_total: 0,
+                ..connection::Limits::default()
+            })),
+            ..AppState::default()
+        };
+        let from = |client: &str, uri: &str, body: &str| {
+            Request::post(uri)
+                .header(CONTENT_TYPE, "application/json")
+                .header("x-forwarded-for", client)
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let single = r#"{"weight_kg": 70, "height_m": 1.75}"#;
+        let peer = IpAddr::from([192, 0, 2, 1]);
+
+        assert!(state.connections.admit(peer).is_err());
+        state.connections.timed_out(peer);
+        state.memory.observe(60 * 1024 * 1024);
+        let (status, _) = send(&state, from("10.0.0.1", "/api/calculate/batch", "[]")).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        send(&state, from("10.0.0.2", "/api/calculate", single)).await;
+        let (status, _) = send(&state, from("10.0.0.2", "/api/calculate", single)).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+        let (_, text) = send(
+            &state,
+            Request::get("/metrics").body(Body::empty()).unwrap(),
+        )
+        .await;
+        for metric in [
+            r#"connections_rejected_total{reason="total_cap"}"#,
+            "connections_timed_out_total",
+            "rate_limited_total",
+            "load_shed_total",
+        ] {
+            assert!(text.contains(metric), "{metric} missing from {text}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_disabled_without_handle() {
+        let (status, _) = send(
+            &AppState::default(),
+            Request::get("/metrics").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
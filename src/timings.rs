@@ -0,0 +1,180 @@
+//! Per-stage timings for investigating calculation latency.
+//!
+//! An admin sending `X-Debug-Timings: true` on `POST /api/calculate` gets a
+//! `timings` block with each pipeline stage in microseconds, and the same
+//! numbers as a `bmi.debug.timings` event in the request span. Without the
+//! header, [`Timings::measure`] just calls through.
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use axum::http::{HeaderMap, HeaderName};
+use serde_json::{Map, Value};
+use tracing::{event, Level};
+
+use crate::admin;
+
+/// Header that asks for the breakdown.
+pub const HEADER: HeaderName = HeaderName::from_static("x-debug-timings");
+
+/// Returns true if the request asks for timings and carries the admin token.
+pub fn requested(admin_token: Option<&str>, headers: &HeaderMap) -> bool {
+    headers
+        .get(&HEADER)
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"true"))
+        && admin::authorize(admin_token, headers).is_ok()
+}
+
+/// Stage durations, collected only when enabled.
+#[derive(Debug, Default)]
+pub struct Timings {
+    stages: Option<Vec<(&'static str, Duration)>>,
+}
+
+impl Timings {
+    /// Creates a collector; a disabled one records nothing.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            stages: enabled.then(Vec::new),
+        }
+    }
+
+    /// Returns true if stages are being recorded.
+    pub fn is_enabled(&self) -> bool {
+        self.stages.is_some()
+    }
+
+    /// Runs `f`, recording its duration as `stage` when enabled.
+    pub fn measure<T>(&mut self, stage: &'static str, f: impl FnOnce() -> T) -> T {
+        let Some(stages) = &mut self.stages else {
+            return f();
+        };
+        let started = Instant::now();
+        let output = f();
+        stages.push((stage, started.elapsed()));
+        output
+    }
+
+    /// Awaits `future`, recording its duration as `stage` when enabled.
+    pub async fn measure_async<T>(
+        &mut self,
+        stage: &'static str,
+        future: impl Future<Output = T>,
+    ) -> T {
+        let Some(stages) = &mut self.stages else {
+            return future.await;
+        };
+        let started = Instant::now();
+        let output = future.await;
+        stages.push((stage, started.elapsed()));
+        output
+    }
+
+    /// Emits the stages as an event and returns them as `{"<stage>_us": n}`.
+    pub fn finish(self) -> Value {
+        let block: Map<String, Value> = self
+            .finish_micros()
+            .into_iter()
+            .map(|(stage, micros)| (stage, micros.into()))
+            .collect();
+        Value::Object(block)
+    }
+
+    /// Emits the stages as an event and returns `("<stage>_us", n)` pairs.
+    pub fn finish_micros(self) -> Vec<(String, u64)> {
+        let stages = self.stages.unwrap_or_default();
+        let total: Duration = stages.iter().map(|(_, duration)| *duration).sum();
+        event!(
+            name: "bmi.debug.timings",
+            Level::INFO,
+            stages = ?stages,
+            total_us = micros(total),
+            "Pipeline timings: {{total_us}}us"
+        );
+        stages
+            .into_iter()
+            .map(|(stage, duration)| (format!("{stage}_us"), micros(duration)))
+            .collect()
+    }
+}
+
+/// Whole microseconds, saturating.
+fn micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::header::{AUTHORIZATION, CONTENT_TYPE},
+    };
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn calculate(headers: &[(&str, &str)]) -> Value {
+        let state = crate::AppState {
+            admin_token: Some(Arc::from("secret")),
+            ..crate::AppState::default()
+        };
+        let mut builder = Request::post("/api/calculate").header(CONTENT_TYPE, "application/json");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        let request = builder
+            .body(Body::from(r#"{"weight_kg": 70, "height_m": 1.75}"#))
+            .unwrap();
+        let response = crate::build_router(state).oneshot(request).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_disabled_records_nothing() {
+        let mut timings = Timings::new(false);
+        assert_eq!(timings.measure("calculation", || 42), 42);
+        assert!(!timings.is_enabled());
+        assert_eq!(timings.finish(), Value::Object(Map::new()));
+    }
+
+    #[tokio::test]
+    async fn test_timings_require_admin_token() {
+        let authorized = calculate(&[
+            ("x-debug-timings", "true"),
+            (AUTHORIZATION.as_str(), "Bearer secret"),
+        ])
+        .await;
+        for stage in [
+            "deserialization",
+            "normalization",
+            "calculation",
+            "validation",
+            "categorization",
+            "serialization",
+        ] {
+            assert!(
+                authorized["timings"][format!("{stage}_us")].is_u64(),
+                "{stage}: {authorized}"
+            );
+        }
+        assert_eq!(authorized["category"], "Normal weight");
+
+        for headers in [
+            &[("x-debug-timings", "true")][..],
+            &[
+                ("x-debug-timings", "true"),
+                (AUTHORIZATION.as_str(), "Bearer wrong"),
+            ],
+            &[(AUTHORIZATION.as_str(), "Bearer secret")],
+        ] {
+            let body = calculate(headers).await;
+            assert!(body.get("timings").is_none(), "{headers:?}");
+            assert_eq!(body["category"], "Normal weight");
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Native HTTPS with rustls.
+//!
+//! With `tls.cert_path` and `tls.key_path` set (PEM files), the main port
+//! speaks TLS, offering HTTP/2 and HTTP/1.1 through ALPN. `tls.redirect_port`
+//! adds a plain HTTP listener answering every request with a `308` to the
+//! same URL over HTTPS. Without a certificate the server runs plain HTTP as
+//! before.
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::Request,
+    http::{
+        header::{HOST, LOCATION},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Router,
+};
+use tokio_rustls::{
+    rustls::{
+        crypto::ring,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+/// Loads the certificate chain and private key into a TLS acceptor.
+///
+/// # Errors
+///
+/// Returns error if a file is missing or holds no valid PEM item, or if the
+/// key does not suit the certificate.
+pub fn acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor> {
+    let chain = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("cannot read certificate {}", cert_path.display()))?;
+    if chain.is_empty() {
+        bail!("no certificate found in {}", cert_path.display());
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("cannot read private key {}", key_path.display()))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .context("no usable TLS protocol versions")?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .with_context(|| {
+            format!(
+                "key {} does not match certificate {}",
+                key_path.display(),
+                cert_path.display()
+            )
+        })?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Router sending every request to the HTTPS listener on `https_port`.
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |request: Request| async move { redirect(&request, https_port) })
+}
+
+/// Builds the `308` for one request.
+///
+/// # Errors
+///
+/// Returns HTTP 400 when the request has no `Host` header to redirect to.
+fn redirect(request: &Request, https_port: u16) -> Response {
+    let host = request
+        .headers()
+        .get(HOST)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|host| host.parse::<axum::http::uri::Authority>().ok());
+    let Some(host) = host else {
+        return (StatusCode::BAD_REQUEST, "Missing Host header").into_response();
+    };
+    let port = if https_port == 443 {
+        String::new()
+    } else {
+        format!(":{https_port}")
+    };
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or("/", |path| path.as_str());
+    let location = format!("https://{}{port}{path}", host.host());
+    (StatusCode::PERMANENT_REDIRECT, [(LOCATION, location)]).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use std::{net::SocketAddr, path::PathBuf, time::Duration};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+    use tokio_rustls::{
+        rustls::{pki_types::ServerName, ClientConfig, RootCertStore},
+        TlsConnector,
+    };
+    use tower::ServiceExt;
+
+    /// A scratch directory removed on drop.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("bmi-tls-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, name: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn self_signed() -> rcgen::CertifiedKey {
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+    }
+
+    #[test]
+    fn test_loads_generated_certificate() {
+        let scratch = Scratch::new();
+        let certified = self_signed();
+        let cert = scratch.write("cert.pem", &certified.cert.pem());
+        let key = scratch.write("key.pem", &certified.key_pair.serialize_pem());
+
+        assert!(acceptor(&cert, &key).is_ok());
+    }
+
+    #[test]
+    fn test_certificate_errors_name_the_file() {
+        let scratch = Scratch::new();
+        let certified = self_signed();
+        let cert = scratch.write("cert.pem", &certified.cert.pem());
+        let key = scratch.write("key.pem", &certified.key_pair.serialize_pem());
+        let garbage = scratch.write("garbage.pem", "not a certificate\n");
+        let other_key = scratch.write("other.pem", &self_signed().key_pair.serialize_pem());
+        let missing = scratch.0.join("missing.pem");
+
+        for (cert, key, expected) in [
+            (&missing, &key, "missing.pem"),
+            (&garbage, &key, "garbage.pem"),
+            (&key, &key, "key.pem"),
+            (&cert, &missing, "missing.pem"),
+            (&cert, &garbage, "garbage.pem"),
+            (&cert, &other_key, "does not match"),
+        ] {
+            let Err(error) = acceptor(cert, key) else {
+                panic!("{expected}: loaded");
+            };
+            let error = format!("{error:#}");
+            assert!(error.contains(expected), "{expected}: {error}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_serves_https() {
+        let scratch = Scratch::new();
+        let certified = self_signed();
+        let cert = scratch.write("cert.pem", &certified.cert.pem());
+        let key = scratch.write("key.pem", &certified.key_pair.serialize_pem());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(crate::connection::serve(
+            listener,
+            crate::build_router(crate::AppState::default()),
+            Arc::new(crate::connection::Connections::new(Default::default())),
+            std::future::pending(),
+            Duration::ZERO,
+            Some(acceptor(&cert, &key).unwrap()),
+        ));
+
+        let mut roots = RootCertStore::empty();
+        roots.add(certified.cert.der().clone()).unwrap();
+        let client = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let mut stream = TlsConnector::from(Arc::new(client))
+            .connect(ServerName::try_from("localhost").unwrap(), tcp)
+            .await
+            .unwrap();
+        stream
+            .write_all(
+                b"GET /api/changelog HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response).await;
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_redirects_to_https() {
+        for (https_port, host, expected) in [
+            (
+                8443,
+                "example.com:8080",
+                "https://example.com:8443/api/hints?x=1",
+            ),
+            (443, "example.com", "https://example.com/api/hints?x=1"),
+        ] {
+            let response = redirect_router(https_port)
+                .oneshot(
+                    Request::get("/api/hints?x=1")
+                        .header(HOST, host)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+            assert_eq!(response.headers()[LOCATION], expected);
+        }
+    }
+}
@@ -0,0 +1,730 @@
+//! Unit systems and unit sanity checks for calculation requests.
+//!
+//! [`normalize`] turns metric or imperial request fields into the SI values
+//! the calculation uses. A single heuristics table, keyed on the field as
+//! sent (`height_cm`, `weight_lb`, ...), then drives both the default
+//! behavior for suspicious values (a warning in the response) and
+//! `strict_units` (rejection), so the two cannot diverge. Under the default
+//! [`Bounds`](crate::validation::Bounds) most such values are out of range
+//! anyway, and the suspicion becomes the hint of the `422`.
+
+use std::{fmt, ops::RangeInclusive};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::BmiError, BmiRequest};
+
+/// Kilograms per pound (exact, international avoirdupois pound).
+pub const KG_PER_LB: f64 = 0.453_592_37;
+
+/// Meters per inch (exact).
+pub const M_PER_IN: f64 = 0.0254;
+
+/// Unit system of a calculation request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    /// `weight_kg` and `height_m` (or `height_cm`).
+    #[default]
+    Metric,
+    /// `weight_lb` and `height_in`, or `height_ft` plus `height_in`.
+    Imperial,
+}
+
+impl UnitSystem {
+    /// Lowercase name as used on the wire.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Metric => "metric",
+            Self::Imperial => "imperial",
+        }
+    }
+}
+
+/// Request measurements converted to SI units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measurements {
+    /// Weight in kilograms.
+    pub weight_kg: f64,
+    /// Height in meters.
+    pub height_m: f64,
+}
+
+/// Converts the request's measurement fields to SI units.
+///
+/// # Errors
+///
+/// Returns a [`BmiError`] if fields from the other unit system are present,
+/// several weight or height inputs conflict, or a required field is missing.
+pub fn normalize(request: &BmiRequest) -> Result<Measurements, BmiError> {
+    Ok(Measurements {
+        weight_kg: normalize_weight(request)?,
+        height_m: normalize_height(request)?,
+    })
+}
+
+/// Resolves the single weight input to kilograms.
+///
+/// Stones work with either unit system since UK users often pair them with
+/// metric heights.
+fn normalize_weight(request: &BmiRequest) -> Result<f64, BmiError> {
+    let stones_kg = match (request.weight_st, request.weight_lb_remainder) {
+        (None, Some(_)) => {
+            return Err(BmiError::ConflictingFields(
+                "weight_lb_remainder requires weight_st".to_string(),
+            ))
+        }
+        (Some(_), Some(pounds)) if !(0.0..14.0).contains(&pounds) => {
+            return Err(BmiError::InvalidWeight(
+                "weight_lb_remainder must be at least 0 and below 14".to_string(),
+            ))
+        }
+        (Some(stones), pounds) => Some((stones * 14.0 + pounds.unwrap_or(0.0)) * KG_PER_LB),
+        (None, None) => None,
+    };
+
+    match (
+        request.units,
+        request.weight_kg,
+        request.weight_lb,
+        stones_kg,
+    ) {
+        (UnitSystem::Metric, _, Some(_), _) => Err(BmiError::ConflictingFields(
+            "weight_lb requires \"units\": \"imperial\"".to_string(),
+        )),
+        (UnitSystem::Imperial, Some(_), _, _) => Err(BmiError::ConflictingFields(
+            "weight_kg cannot be combined with \"units\": \"imperial\"".to_string(),
+        )),
+        (_, Some(kg), None, None) => Ok(kg),
+        (_, None, Some(pounds), None) => Ok(pounds * KG_PER_LB),
+        (_, None, None, Some(kg)) => Ok(kg),
+        (UnitSystem::Metric, None, None, None) => Err(BmiError::InvalidWeight(
+            "weight_kg or weight_st is required".to_string(),
+        )),
+        (UnitSystem::Imperial, None, None, None) => Err(BmiError::InvalidWeight(
+            "weight_lb or weight_st is required".to_string(),
+        )),
+        _ => Err(BmiError::ConflictingFields(
+            "Only one of weight_kg, weight_lb or weight_st may be given".to_string(),
+        )),
+    }
+}
+
+/// Resolves the height inputs of the selected unit system to meters.
+fn normalize_height(request: &BmiRequest) -> Result<f64, BmiError> {
+    match request.units {
+        UnitSystem::Metric => {
+            if request.height_ft.is_some() || request.height_in.is_some() {
+                return Err(BmiError::ConflictingFields(
+                    "height_ft and height_in require \"units\": \"imperial\"".to_string(),
+                ));
+            }
+            match (request.height_m, request.height_cm) {
+                (Some(_), Some(_)) => Err(BmiError::ConflictingFields(
+                    "height_m and height_cm are mutually exclusive".to_string(),
+                )),
+                (Some(meters), None) => Ok(meters),
+                (None, Some(centimeters)) => Ok(centimeters / 100.0),
+                (None, None) => Err(BmiError::InvalidHeight(
+                    "height_m or height_cm is required".to_string(),
+                )),
+            }
+        }
+        UnitSystem::Imperial => {
+            if request.height_m.is_some() || request.height_cm.is_some() {
+                return Err(BmiError::ConflictingFields(
+                    "height_m and height_cm cannot be combined with \"units\": \"imperial\""
+                        .to_string(),
+                ));
+            }
+            let inches = match (request.height_ft, request.height_in) {
+                (Some(_), Some(inches)) if inches < 0.0 => {
+                    return Err(BmiError::InvalidHeight(
+                        "height_in must not be negative alongside height_ft".to_string(),
+                    ))
+                }
+                (Some(feet), inches) => feet * 12.0 + inches.unwrap_or(0.0),
+                (None, Some(inches)) => inches,
+                (None, None) => {
+                    return Err(BmiError::InvalidHeight(
+                        "height_in or height_ft is required".to_string(),
+                    ))
+                }
+            };
+            Ok(inches * M_PER_IN)
+        }
+    }
+}
+
+/// Request field a heuristic applies to, in the unit it is sent in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    /// `weight_kg`
+    WeightKg,
+    /// `weight_lb`
+    WeightLb,
+    /// `height_m`
+    HeightM,
+    /// `height_cm`
+    HeightCm,
+    /// `height_ft`
+    HeightFt,
+    /// `height_in`
+    HeightIn,
+}
+
+impl Field {
+    /// JSON field name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::WeightKg => "weight_kg",
+            Self::WeightLb => "weight_lb",
+            Self::HeightM => "height_m",
+            Self::HeightCm => "height_cm",
+            Self::HeightFt => "height_ft",
+            Self::HeightIn => "height_in",
+        }
+    }
+
+    /// Unit the field is expected in.
+    pub fn expected_unit(self) -> &'static str {
+        match self {
+            Self::WeightKg => "kilograms",
+            Self::WeightLb => "pounds",
+            Self::HeightM => "meters",
+            Self::HeightCm => "centimeters",
+            Self::HeightFt => "feet",
+            Self::HeightIn => "inches",
+        }
+    }
+
+    /// The value `request` sent in this field, if it stands for the whole
+    /// measurement; `height_in` next to `height_ft` is only the remainder.
+    fn sent(self, request: &BmiRequest) -> Option<f64> {
+        match self {
+            Self::WeightKg => request.weight_kg,
+            Self::WeightLb => request.weight_lb,
+            Self::HeightM => request.height_m,
+            Self::HeightCm => request.height_cm,
+            Self::HeightFt => request.height_ft,
+            Self::HeightIn => request.height_in.filter(|_| request.height_ft.is_none()),
+        }
+    }
+}
+
+/// A range of values plausible only under another unit.
+#[derive(Debug)]
+pub struct Heuristic {
+    /// Field the range applies to.
+    pub field: Field,
+    /// Values (in the field's nominal unit) that trigger the heuristic.
+    pub range: RangeInclusive<f64>,
+    /// Unit the client most likely meant.
+    pub suspected_unit: &'static str,
+    /// Request field that accepts the suspected unit directly, if any.
+    pub alternative: Option<&'static str>,
+}
+
+/// Suspicious ranges, non-overlapping per field.
+pub const HEURISTICS: &[Heuristic] = &[
+    Heuristic {
+        field: Field::HeightM,
+        range: 4.0..=8.0,
+        suspected_unit: "feet",
+        alternative: None,
+    },
+    Heuristic {
+        field: Field::HeightM,
+        range: 40.0..=99.9,
+        suspected_unit: "inches",
+        alternative: None,
+    },
+    Heuristic {
+        field: Field::HeightM,
+        range: 100.0..=250.0,
+        suspected_unit: "centimeters",
+        alternative: Some("height_cm"),
+    },
+    Heuristic {
+        field: Field::HeightM,
+        range: 1000.0..=2500.0,
+        suspected_unit: "millimeters",
+        alternative: None,
+    },
+    Heuristic {
+        field: Field::HeightCm,
+        range: 1.0..=2.5,
+        suspected_unit: "meters",
+        alternative: Some("height_m"),
+    },
+    Heuristic {
+        field: Field::HeightCm,
+        range: 1000.0..=2500.0,
+        suspected_unit: "millimeters",
+        alternative: None,
+    },
+    Heuristic {
+        field: Field::HeightFt,
+        range: 40.0..=99.9,
+        suspected_unit: "inches",
+        alternative: Some("height_in"),
+    },
+    Heuristic {
+        field: Field::HeightIn,
+        range: 1.0..=2.5,
+        suspected_unit: "meters",
+        alternative: None,
+    },
+    Heuristic {
+        field: Field::HeightIn,
+        range: 4.0..=8.0,
+        suspected_unit: "feet",
+        alternative: Some("height_ft"),
+    },
+    Heuristic {
+        field: Field::HeightIn,
+        range: 100.0..=250.0,
+        suspected_unit: "centimeters",
+        alternative: None,
+    },
+    Heuristic {
+        field: Field::WeightKg,
+        range: 1000.0..=300_000.0,
+        suspected_unit: "grams",
+        alternative: None,
+    },
+    Heuristic {
+        field: Field::WeightLb,
+        range: 1.0..=24.9,
+        suspected_unit: "stones",
+        alternative: Some("weight_st"),
+    },
+];
+
+/// A value that matched a heuristic.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suspicion {
+    /// Field holding the value.
+    pub field: Field,
+    /// Value as sent.
+    pub value: f64,
+    /// Unit the client most likely meant.
+    pub suspected_unit: &'static str,
+    /// Request field that accepts the suspected unit directly, if any.
+    pub alternative: Option<&'static str>,
+}
+
+impl fmt::Display for Suspicion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} looks like {}; expected {}",
+            self.field.name(),
+            self.value,
+            self.suspected_unit,
+            self.field.expected_unit()
+        )?;
+        if let Some(alternative) = self.alternative {
+            write!(f, " (or send {alternative})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns every value in `request`, as sent, that looks like a unit mix-up.
+///
+/// Meant for requests that [`normalize`] accepted, so each measurement comes
+/// from fields of one unit system.
+pub fn check(request: &BmiRequest) -> Vec<Suspicion> {
+    HEURISTICS
+        .iter()
+        .filter_map(|heuristic| {
+            let value = heuristic.field.sent(request)?;
+            heuristic.range.contains(&value).then_some(Suspicion {
+                field: heuristic.field,
+                value,
+                suspected_unit: heuristic.suspected_unit,
+                alternative: heuristic.alternative,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metric(weight_kg: f64, height_m: f64) -> BmiRequest {
+        BmiRequest {
+            weight_kg: Some(weight_kg),
+            height_m: Some(height_m),
+            ..BmiRequest::default()
+        }
+    }
+
+    /// A plausible request with `value` in `field`.
+    fn sent_in(field: Field, value: f64) -> BmiRequest {
+        let mut request = match field {
+            Field::WeightKg | Field::HeightM | Field::HeightCm => metric(70.0, 1.75),
+            _ => imperial(154.0, None, Some(69.0)),
+        };
+        match field {
+            Field::WeightKg => request.weight_kg = Some(value),
+            Field::WeightLb => request.weight_lb = Some(value),
+            Field::HeightM => request.height_m = Some(value),
+            Field::HeightCm => (request.height_m, request.height_cm) = (None, Some(value)),
+            Field::HeightFt => (request.height_in, request.height_ft) = (None, Some(value)),
+            Field::HeightIn => request.height_in = Some(value),
+        }
+        request
+    }
+
+    #[test]
+    fn test_plausible_values_pass() {
+        assert!(check(&metric(70.0, 1.75)).is_empty());
+        assert!(check(&metric(3.5, 0.5)).is_empty());
+        assert!(check(&metric(250.0, 2.4)).is_empty());
+        assert!(check(&sent_in(Field::HeightCm, 175.0)).is_empty());
+        assert!(check(&imperial(154.0, None, Some(69.0))).is_empty());
+        // Inches next to feet are a remainder, not a height
+        assert!(check(&imperial(154.0, Some(5.0), Some(6.0))).is_empty());
+        // Stones are not held to pound ranges
+        assert!(check(&BmiRequest {
+            weight_st: Some(11.0),
+            ..sent_in(Field::HeightCm, 180.0)
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn test_every_range_is_detected_at_its_bounds() {
+        for heuristic in HEURISTICS {
+            for value in [*heuristic.range.start(), *heuristic.range.end()] {
+                let request = sent_in(heuristic.field, value);
+                assert!(normalize(&request).is_ok(), "{request:?}");
+                assert_eq!(
+                    check(&request),
+                    [Suspicion {
+                        field: heuristic.field,
+                        value,
+                        suspected_unit: heuristic.suspected_unit,
+                        alternative: heuristic.alternative,
+                    }],
+                    "{value} for {}",
+                    heuristic.field.name()
+                );
+            }
+        }
+    }
+
+    fn imperial(weight_lb: f64, height_ft: Option<f64>, height_in: Option<f64>) -> BmiRequest {
+        BmiRequest {
+            units: UnitSystem::Imperial,
+            weight_lb: Some(weight_lb),
+            height_ft,
+            height_in,
+            ..BmiRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_normalize_imperial() {
+        let inches = normalize(&imperial(154.0, None, Some(69.0))).unwrap();
+        assert!((inches.weight_kg - 69.853).abs() < 0.001);
+        assert!((inches.height_m - 1.7526).abs() < 1e-9);
+
+        let feet = normalize(&imperial(154.0, Some(5.0), Some(9.0))).unwrap();
+        assert_eq!(feet, inches);
+
+        let feet_only = normalize(&imperial(154.0, Some(6.0), None)).unwrap();
+        assert!((feet_only.height_m - 1.8288).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_normalize_rejects_mixed_and_missing_fields() {
+        let mixed = BmiRequest {
+            weight_kg: Some(70.0),
+            ..imperial(154.0, None, Some(69.0))
+        };
+        assert!(normalize(&mixed)
+            .unwrap_err()
+            .to_string()
+            .contains("cannot be combined"));
+
+        let metric_with_pounds = BmiRequest {
+            units: UnitSystem::Metric,
+            height_m: Some(1.75),
+            ..imperial(154.0, None, None)
+        };
+        assert!(normalize(&metric_with_pounds)
+            .unwrap_err()
+            .to_string()
+            .contains("require"));
+
+        assert!(normalize(&imperial(154.0, None, None)).is_err());
+        assert!(normalize(&imperial(154.0, Some(5.0), Some(-1.0))).is_err());
+        assert!(normalize(&BmiRequest::default()).is_err());
+    }
+
+    #[test]
+    fn test_height_cm_is_converted() {
+        let request = BmiRequest {
+            weight_kg: Some(70.0),
+            height_cm: Some(175.0),
+            ..BmiRequest::default()
+        };
+        let measurements = normalize(&request).unwrap();
+
+        assert!((measurements.height_m - 1.75).abs() < 1e-12);
+        assert!(check(&request).is_empty());
+    }
+
+    #[test]
+    fn test_height_m_and_height_cm_are_exclusive() {
+        let both = BmiRequest {
+            weight_kg: Some(70.0),
+            height_m: Some(1.75),
+            height_cm: Some(175.0),
+            ..BmiRequest::default()
+        };
+        assert!(normalize(&both)
+            .unwrap_err()
+            .to_string()
+            .contains("mutually exclusive"));
+
+        let neither = BmiRequest {
+            weight_kg: Some(70.0),
+            ..BmiRequest::default()
+        };
+        assert!(normalize(&neither)
+            .unwrap_err()
+            .to_string()
+            .contains("height_m or height_cm is required"));
+    }
+
+    fn stones(weight_st: f64, weight_lb_remainder: Option<f64>) -> BmiRequest {
+        BmiRequest {
+            weight_st: Some(weight_st),
+            weight_lb_remainder,
+            height_cm: Some(180.0),
+            ..BmiRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_stones_and_pounds_are_converted() {
+        let measurements = normalize(&stones(11.0, Some(4.0))).unwrap();
+        assert!((measurements.weight_kg - 71.668).abs() < 0.001);
+
+        let whole = normalize(&stones(10.0, None)).unwrap();
+        assert!((whole.weight_kg - 63.503).abs() < 0.001);
+
+        let imperial_height = BmiRequest {
+            units: UnitSystem::Imperial,
+            height_cm: None,
+            height_in: Some(70.0),
+            ..stones(11.0, Some(4.0))
+        };
+        assert!(normalize(&imperial_height).is_ok());
+    }
+
+    #[test]
+    fn test_stone_remainder_and_conflicts_are_rejected() {
+        assert!(normalize(&stones(11.0, Some(14.0)))
+            .unwrap_err()
+            .to_string()
+            .contains("below 14"));
+        assert!(normalize(&stones(11.0, Some(-1.0))).is_err());
+
+        let conflicting = BmiRequest {
+            weight_kg: Some(70.0),
+            ..stones(11.0, None)
+        };
+        assert!(normalize(&conflicting)
+            .unwrap_err()
+            .to_string()
+            .contains("Only one of weight_kg, weight_lb or weight_st"));
+
+        let orphan = BmiRequest {
+            weight_st: None,
+            weight_kg: Some(70.0),
+            ..stones(0.0, Some(4.0))
+        };
+        assert!(normalize(&orphan)
+            .unwrap_err()
+            .to_string()
+            .contains("requires weight_st"));
+    }
+
+    async fn calculate(body: &str) -> String {
+        send(body).await.1
+    }
+
+    async fn send(body: &str) -> (axum::http::StatusCode, String) {
+        send_to(crate::AppState::default(), body).await
+    }
+
+    /// Bounds wide enough for every heuristic range, as a veterinary
+    /// deployment might configure.
+    fn wide_bounds() -> crate::AppState {
+        crate::AppState {
+            bounds: std::sync::Arc::new(crate::validation::Bounds {
+                weight_kg: 0.1..=500_000.0,
+                height_m: 0.1..=3000.0,
+            }),
+            ..crate::AppState::default()
+        }
+    }
+
+    async fn send_to(state: crate::AppState, body: &str) -> (axum::http::StatusCode, String) {
+        use axum::{body::Body, extract::Request, http::header::CONTENT_TYPE};
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        let request = Request::post("/api/calculate")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = crate::build_router(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_default_mode_warns() {
+        let (_, body) = send_to(wide_bounds(), r#"{"weight_kg": 70.0, "height_m": 175}"#).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert!(json["bmi"].is_number());
+        assert_eq!(
+            json["warnings"][0],
+            "height_m 175 looks like centimeters; expected meters (or send height_cm)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_rejects() {
+        let (_, body) = send_to(
+            wide_bounds(),
+            r#"{"weight_kg": 70000, "height_m": 1.75, "strict_units": true}"#,
+        )
+        .await;
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"]["code"], "suspected_unit_mismatch");
+        assert!(json["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("looks like grams"));
+    }
+
+    #[tokio::test]
+    async fn test_imperial_fields_are_checked_as_sent() {
+        use axum::http::StatusCode;
+
+        // 20 "pounds" is within the default bounds, so it computes with a warning
+        let pounds = r#"{"units": "imperial", "weight_lb": 20, "height_in": 69"#;
+        let (status, body) = send(&format!("{pounds}}}")).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            json["warnings"][0],
+            "weight_lb 20 looks like stones; expected pounds (or send weight_st)"
+        );
+
+        let (status, body) = send(&format!(r#"{pounds}, "strict_units": true}}"#)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"]["code"], "suspected_unit_mismatch");
+
+        let (status, body) =
+            send(r#"{"units": "imperial", "weight_lb": 154, "height_in": 5.75}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"]["fields"][0]["field"], "height_in");
+        assert_eq!(
+            json["error"]["fields"][0]["hint"],
+            "height_in 5.75 looks like feet; expected inches (or send height_ft)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hints_name_the_field_as_sent() {
+        let (_, body) = send(r#"{"weight_kg": 70, "height_cm": 1.75}"#).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"]["fields"][0]["field"], "height_cm");
+        assert_eq!(
+            json["error"]["fields"][0]["hint"],
+            "height_cm 1.75 looks like meters; expected centimeters (or send height_m)"
+        );
+
+        // Stones have no heuristics of their own, and none borrowed from kilograms
+        let (_, body) = send(r#"{"weight_st": 2000, "height_cm": 180}"#).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"]["fields"][0]["field"], "weight_st");
+        assert!(json["error"]["fields"][0].get("hint").is_none(), "{json}");
+    }
+
+    #[tokio::test]
+    async fn test_imperial_request_echoes_unit_system() {
+        let body =
+            calculate(r#"{"units": "imperial", "weight_lb": 154, "height_ft": 5, "height_in": 9}"#)
+                .await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(json["units"], "imperial");
+        assert!((json["bmi"].as_f64().unwrap() - 22.74).abs() < 0.01);
+
+        let metric = calculate(r#"{"weight_kg": 70.0, "height_m": 1.75}"#).await;
+        let json: serde_json::Value = serde_json::from_str(&metric).unwrap();
+        assert_eq!(json["units"], "metric");
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_height_fields_return_400() {
+        use axum::http::StatusCode;
+
+        let (status, body) = send(r#"{"weight_kg": 70, "height_m": 1.75, "height_cm": 175}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("mutually exclusive"), "{body}");
+
+        let (status, _) = send(r#"{"weight_kg": 70}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = send(r#"{"weight_kg": 70, "height_cm": 175}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("Normal weight"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn test_stone_request_reports_weight_kg() {
+        let (status, body) =
+            send(r#"{"weight_st": 11, "weight_lb_remainder": 4, "height_cm": 180}"#).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!((json["weight_kg"].as_f64().unwrap() - 71.668).abs() < 0.001);
+
+        let (status, body) = send(r#"{"weight_st": 11, "weight_kg": 70, "height_m": 1.8}"#).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert!(body.contains("Only one of"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn test_plausible_response_has_no_warnings() {
+        let body =
+            calculate(r#"{"weight_kg": 70.0, "height_m": 1.75, "strict_units": true}"#).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert!(json.get("warnings").is_none());
+    }
+
+    #[test]
+    fn test_message_names_suspected_unit() {
+        let suspicion = &check(&metric(70.0, 175.0))[0];
+        assert_eq!(
+            suspicion.to_string(),
+            "height_m 175 looks like centimeters; expected meters (or send height_cm)"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Plausibility bounds on measurements.
+//!
+//! After the inputs are converted to SI units, weight and height must fall
+//! within [`Bounds`] (1–700 kg and 0.3–3.0 m unless configured otherwise in
+//! `[validation]`). Every failing field is reported at once in a
+//! `422 validation_failed` error, with the constraint and the value as sent,
+//! so a request like 5000 kg at 0.01 m is refused rather than answered with
+//! a BMI of fifty million.
+
+use std::ops::RangeInclusive;
+
+use serde::Serialize;
+
+use crate::{error::BmiError, units, BmiRequest};
+
+/// Accepted ranges, both ends included.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bounds {
+    /// Weight in kilograms.
+    pub weight_kg: RangeInclusive<f64>,
+    /// Height in meters.
+    pub height_m: RangeInclusive<f64>,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self {
+            weight_kg: 1.0..=700.0,
+            height_m: 0.3..=3.0,
+        }
+    }
+}
+
+/// One field outside its bounds.
+#[derive(Clone, Debug, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct FieldViolation {
+    /// Request field as sent, e.g. `weight_lb`.
+    pub field: &'static str,
+    /// The rule it broke, in SI units.
+    pub constraint: String,
+    /// The submitted value; `null` if it was not a number.
+    pub value: f64,
+    /// Suspected unit mix-up behind the value, if any (see `units`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+/// The weight field the request used, with its value.
+fn weight_input(request: &BmiRequest) -> (&'static str, f64) {
+    [
+        ("weight_kg", request.weight_kg),
+        ("weight_lb", request.weight_lb),
+        ("weight_st", request.weight_st),
+    ]
+    .into_iter()
+    .find_map(|(name, value)| value.map(|value| (name, value)))
+    .unwrap_or(("weight_kg", f64::NAN))
+}
+
+/// The height field the request used, with its value.
+fn height_input(request: &BmiRequest) -> (&'static str, f64) {
+    [
+        ("height_m", request.height_m),
+        ("height_cm", request.height_cm),
+        ("height_ft", request.height_ft),
+        ("height_in", request.height_in),
+    ]
+    .into_iter()
+    .find_map(|(name, value)| value.map(|value| (name, value)))
+    .unwrap_or(("height_m", f64::NAN))
+}
+
+/// Checks the converted `measurements` of `request` against `bounds`.
+///
+/// # Errors
+///
+/// Returns [`BmiError::Validation`] listing every field out of bounds,
+/// including NaN and infinite values.
+pub fn check(
+    request: &BmiRequest,
+    measurements: units::Measurements,
+    bounds: &Bounds,
+) -> Result<(), BmiError> {
+    let suspicions = units::check(request);
+    let hint = |field: &str| {
+        suspicions
+            .iter()
+            .find(|suspicion| suspicion.field.name() == field)
+            .map(ToString::to_string)
+    };
+    let mut violations = Vec::new();
+    if !bounds.weight_kg.contains(&measurements.weight_kg) {
+        let (field, value) = weight_input(request);
+        violations.push(FieldViolation {
+            field,
+            constraint: constraint(&bounds.weight_kg, "kg"),
+            value,
+            hint: hint(field),
+        });
+    }
+    if !bounds.height_m.contains(&measurements.height_m) {
+        let (field, value) = height_input(request);
+        violations.push(FieldViolation {
+            field,
+            constraint: constraint(&bounds.height_m, "m"),
+            value,
+            hint: hint(field),
+        });
+    }
+
+    match violations.as_slice() {
+        [] => Ok(()),
+        [violation] => Err(BmiError::Validation(
+            format!("{} must be {}", violation.field, violation.constraint),
+            violations,
+        )),
+        _ => Err(BmiError::Validation(
+            format!(
+                "{} fields are out of range: {}",
+                violations.len(),
+                violations
+                    .iter()
+                    .map(|violation| violation.field)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            violations,
+        )),
+    }
+}
+
+fn constraint(range: &RangeInclusive<f64>, unit: &str) -> String {
+    format!("between {} and {} {unit}", range.start(), range.end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{process_bmi_request_with, units::UnitSystem};
+
+    fn metric(weight_kg: f64, height_m: f64) -> BmiRequest {
+        BmiRequest {
+            weight_kg: Some(weight_kg),
+            height_m: Some(height_m),
+            ..BmiRequest::default()
+        }
+    }
+
+    fn violations(request: &BmiRequest, bounds: &Bounds) -> Vec<FieldViolation> {
+        match process_bmi_request_with(request, bounds) {
+            Err(BmiError::Validation(_, violations)) => violations,
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_boundary_values_are_accepted() {
+        let bounds = Bounds::default();
+        for (weight_kg, height_m) in [(1.0, 1.75), (700.0, 1.75), (70.0, 0.3), (70.0, 3.0)] {
+            assert!(
+                process_bmi_request_with(&metric(weight_kg, height_m), &bounds).is_ok(),
+                "{weight_kg} kg, {height_m} m"
+            );
+        }
+    }
+
+    #[test]
+    fn test_each_bound_is_enforced() {
+        let bounds = Bounds::default();
+        for (weight_kg, height_m, field) in [
+            (0.99, 1.75, "weight_kg"),
+            (700.01, 1.75, "weight_kg"),
+            (f64::NAN, 1.75, "weight_kg"),
+            (70.0, 0.29, "height_m"),
+            (70.0, 3.01, "height_m"),
+            (70.0, f64::INFINITY, "height_m"),
+        ] {
+            let found = violations(&metric(weight_kg, height_m), &bounds);
+            assert_eq!(found.len(), 1, "{weight_kg} kg, {height_m} m");
+            assert_eq!(found[0].field, field);
+        }
+    }
+
+    #[test]
+    fn test_every_violation_is_listed() {
+        let found = violations(&metric(5000.0, 0.01), &Bounds::default());
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].field, "weight_kg");
+        assert_eq!(found[0].constraint, "between 1 and 700 kg");
+        assert_eq!(found[0].value, 5000.0);
+        assert_eq!(found[1].field, "height_m");
+        assert_eq!(found[1].value, 0.01);
+    }
+
+    #[test]
+    fn test_reports_the_field_as_sent_with_hints() {
+        let request = BmiRequest {
+            units: UnitSystem::Imperial,
+            weight_lb: Some(2000.0),
+            height_in: Some(69.0),
+            ..BmiRequest::default()
+        };
+        let found = violations(&request, &Bounds::default());
+        assert_eq!(found[0].field, "weight_lb");
+        assert_eq!(found[0].value, 2000.0);
+
+        let found = violations(&metric(70.0, 175.0), &Bounds::default());
+        assert!(found[0].hint.as_deref().unwrap().contains("centimeters"));
+    }
+
+    #[test]
+    fn test_bounds_are_configurable() {
+        let bounds = Bounds {
+            weight_kg: 0.1..=2000.0,
+            height_m: 0.1..=5.0,
+        };
+        assert!(process_bmi_request_with(&metric(1500.0, 4.0), &bounds).is_ok());
+        assert!(process_bmi_request_with(&metric(1500.0, 4.0), &Bounds::default()).is_err());
+    }
+}
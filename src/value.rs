@@ -0,0 +1,171 @@
+//! Single bare values for shortcuts, automations and shell prompts.
+//!
+//! `GET /api/calculate/value?weight_kg=70&height_m=1.75&field=bmi&decimals=1`
+//! answers `22.9` as `text/plain`, with no JSON wrapping. All calculation
+//! fields of `GET /api/calculate` are accepted and validated the same way.
+
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{error::BmiError, process_bmi_request_with, AppState, BmiRequest, BmiResponse};
+
+/// Most decimals accepted by `decimals`.
+const MAX_DECIMALS: usize = 10;
+
+/// Value returned by the endpoint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    /// The BMI itself.
+    #[default]
+    Bmi,
+    /// The category name.
+    Category,
+    /// BMI divided by the upper normal limit (25).
+    BmiPrime,
+}
+
+/// Output options for `GET /api/calculate/value`.
+#[derive(Debug, Deserialize)]
+pub struct ValueQuery {
+    /// Which value to return (defaults to `bmi`).
+    #[serde(default)]
+    pub field: Field,
+    /// Round numeric values to this many decimals.
+    pub decimals: Option<usize>,
+}
+
+/// Renders the selected field of a response as plain text.
+fn render(response: &BmiResponse, field: Field, decimals: Option<usize>) -> String {
+    let number = match field {
+        Field::Category => return response.category.clone(),
+        Field::Bmi => response.bmi,
+        Field::BmiPrime => crate::bmi::bmi_prime(response.bmi),
+    };
+    match decimals {
+        Some(decimals) => format!("{number:.decimals$}"),
+        None => number.to_string(),
+    }
+}
+
+/// Serves a single calculation value as `text/plain`.
+///
+/// # Errors
+///
+/// Returns the [`BmiError`] status with its message as plain text if a
+/// parameter is invalid, `decimals` exceeds 10, or the measurements fail
+/// validation.
+pub async fn value_handler(
+    State(state): State<AppState>,
+    request: Result<Query<BmiRequest>, QueryRejection>,
+    options: Result<Query<ValueQuery>, QueryRejection>,
+) -> Response {
+    let failed = |error: BmiError| (error.status(), error.to_string()).into_response();
+
+    let (Query(request), Query(options)) = match (request, options) {
+        (Ok(request), Ok(options)) => (request, options),
+        (Err(rejection), _) | (_, Err(rejection)) => {
+            return failed(BmiError::InvalidQuery(rejection.body_text()))
+        }
+    };
+    if options
+        .decimals
+        .is_some_and(|decimals| decimals > MAX_DECIMALS)
+    {
+        return failed(BmiError::InvalidQuery(format!(
+            "decimals must be at most {MAX_DECIMALS}"
+        )));
+    }
+
+    match process_bmi_request_with(&request, &state.bounds) {
+        Ok(response) => render(&response, options.field, options.decimals).into_response(),
+        Err(error) => failed(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{header::CONTENT_TYPE, StatusCode},
+    };
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn get(query: &str) -> (StatusCode, String, String) {
+        let response = crate::build_router(crate::AppState::default())
+            .oneshot(
+                Request::get(format!("/api/calculate/value?{query}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let content_type = response.headers()[CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (
+            status,
+            content_type,
+            String::from_utf8(bytes.to_vec()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_each_field() {
+        let measurements = "weight_kg=70&height_m=1.75";
+
+        let (status, content_type, body) = get(measurements).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(content_type.starts_with("text/plain"));
+        assert!(body.starts_with("22.857"), "{body}");
+
+        let (_, _, body) = get(&format!("{measurements}&field=category")).await;
+        assert_eq!(body, "Normal weight");
+
+        let (_, _, body) = get(&format!("{measurements}&field=bmi_prime&decimals=2")).await;
+        assert_eq!(body, "0.91");
+    }
+
+    #[tokio::test]
+    async fn test_decimals() {
+        let (_, _, body) = get("weight_kg=70&height_m=1.75&decimals=1").await;
+        assert_eq!(body, "22.9");
+
+        let (_, _, body) = get("weight_kg=70&height_m=1.75&decimals=0").await;
+        assert_eq!(body, "23");
+
+        let (_, _, body) = get("weight_kg=70&height_m=1.75&field=category&decimals=1").await;
+        assert_eq!(body, "Normal weight");
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_plain_text() {
+        for query in [
+            "weight_kg=abc&height_m=1.75",
+            "weight_kg=70",
+            "weight_kg=70&height_m=1.75&field=tdee",
+            "weight_kg=70&height_m=1.75&decimals=11",
+        ] {
+            let (status, content_type, body) = get(query).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+            assert!(content_type.starts_with("text/plain"), "{query}");
+            assert!(
+                !body.is_empty() && !body.starts_with('{'),
+                "{query}: {body}"
+            );
+        }
+
+        let (status, content_type, body) = get("weight_kg=0&height_m=1.75").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(content_type.starts_with("text/plain"));
+        assert_eq!(body, "weight_kg must be between 1 and 700 kg");
+    }
+}
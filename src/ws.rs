@@ -0,0 +1,195 @@
+//! Live calculation over a WebSocket, for inputs that change while typing.
+//!
+//! `GET /api/ws` upgrades to a WebSocket. Each text (or binary) message is a
+//! JSON [`BmiRequest`]; the reply to it is the [`BmiResponse`] or the
+//! `{"error": ...}` body `/api/calculate` would answer with, in order, over
+//! the same connection. A message that is not a valid request gets an error
+//! reply and the connection stays open.
+//!
+//! Messages are capped at [`MAX_MESSAGE_BYTES`]; a larger one ends the
+//! connection. A connection that sends nothing for `ws_idle_timeout_secs`
+//! (60 by default) is closed with a normal close frame.
+
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use tracing::{event, Level};
+
+use crate::{
+    error::{BmiError, ErrorResponse},
+    process_bmi_request_with, AppState, BmiRequest,
+};
+
+/// Largest message accepted, in bytes; requests are a few hundred.
+pub const MAX_MESSAGE_BYTES: usize = 4 * 1024;
+
+/// Connection limits.
+#[derive(Debug)]
+pub struct Settings {
+    /// Silence after which a connection is closed.
+    pub idle_timeout: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Upgrades `GET /api/ws` to a calculation socket.
+pub async fn ws_handler(State(state): State<AppState>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade
+        .max_message_size(MAX_MESSAGE_BYTES)
+        .max_frame_size(MAX_MESSAGE_BYTES)
+        .on_upgrade(move |socket| serve(socket, state))
+}
+
+/// Answers messages until the client leaves, errs or goes idle.
+async fn serve(mut socket: WebSocket, state: AppState) {
+    let idle_timeout = state.ws.idle_timeout;
+    let mut answered = 0_u64;
+    let reason = loop {
+        let message = match tokio::time::timeout(idle_timeout, socket.recv()).await {
+            Err(_) => {
+                let frame = CloseFrame {
+                    code: close_code::NORMAL,
+                    reason: "idle timeout".into(),
+                };
+                let _ = socket.send(Message::Close(Some(frame))).await;
+                break "idle";
+            }
+            Ok(None) | Ok(Some(Ok(Message::Close(_)))) => break "closed",
+            Ok(Some(Err(_))) => break "error",
+            Ok(Some(Ok(message))) => message,
+        };
+        let reply = match message {
+            Message::Text(text) => answer(&state, text.as_bytes()),
+            Message::Binary(bytes) => answer(&state, &bytes),
+            // Pings are answered by axum
+            Message::Ping(_) | Message::Pong(_) | Message::Close(_) => continue,
+        };
+        if socket.send(Message::Text(reply)).await.is_err() {
+            break "error";
+        }
+        answered += 1;
+    };
+    event!(
+        name: "ws.connection.closed",
+        Level::DEBUG,
+        answered,
+        reason,
+        "WebSocket closed ({{reason}}) after {{answered}} answers"
+    );
+}
+
+/// The JSON reply to one message.
+fn answer(state: &AppState, message: &[u8]) -> String {
+    let outcome = serde_json::from_slice::<BmiRequest>(message)
+        .map_err(|error| BmiError::MalformedPayload(format!("Invalid request: {error}")))
+        .and_then(|request| process_bmi_request_with(&request, &state.bounds));
+    match outcome {
+        Ok(response) => serde_json::to_string(&response),
+        Err(error) => serde_json::to_string(&ErrorResponse {
+            error: error.body(),
+        }),
+    }
+    .expect("replies serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::SocketAddr, sync::Arc};
+
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::{connect_async, tungstenite, MaybeTlsStream, WebSocketStream};
+
+    type Client = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+    async fn connect(state: AppState) -> Client {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, crate::build_router(state)).await });
+        let (client, _) = connect_async(format!("ws://{addr}/api/ws")).await.unwrap();
+        client
+    }
+
+    async fn reply(client: &mut Client, message: &str) -> serde_json::Value {
+        client
+            .send(tungstenite::Message::text(message))
+            .await
+            .unwrap();
+        match client.next().await.unwrap().unwrap() {
+            tungstenite::Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text reply, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_each_message_gets_its_response() {
+        let mut client = connect(AppState::default()).await;
+
+        for (weight_kg, bmi) in [(60.0, 19.6), (70.0, 22.9), (80.0, 26.1)] {
+            let body = reply(
+                &mut client,
+                &format!(r#"{{"weight_kg": {weight_kg}, "height_m": 1.75}}"#),
+            )
+            .await;
+            assert_eq!(body["bmi_rounded"], bmi, "{weight_kg} kg");
+            assert_eq!(body["weight_kg"], weight_kg);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bad_messages_get_errors_and_keep_the_socket() {
+        let mut client = connect(AppState::default()).await;
+
+        let body = reply(&mut client, "{not json").await;
+        assert_eq!(body["error"]["code"], "malformed_payload");
+
+        let body = reply(&mut client, r#"{"weight_kg": 5000, "height_m": 1.75}"#).await;
+        assert_eq!(body["error"]["code"], "validation_failed");
+        assert_eq!(body["error"]["fields"][0]["field"], "weight_kg");
+
+        let body = reply(&mut client, r#"{"weight_kg": 70, "height_m": 1.75}"#).await;
+        assert_eq!(body["category"], "Normal weight");
+    }
+
+    #[tokio::test]
+    async fn test_idle_and_oversized_connections_are_closed() {
+        let state = AppState {
+            ws: Arc::new(Settings {
+                idle_timeout: Duration::from_millis(100),
+            }),
+            ..AppState::default()
+        };
+        let mut client = connect(state).await;
+        match client.next().await.unwrap().unwrap() {
+            tungstenite::Message::Close(Some(frame)) => assert_eq!(frame.reason, "idle timeout"),
+            other => panic!("expected a close frame, got {other:?}"),
+        }
+
+        let mut client = connect(AppState::default()).await;
+        let oversized = format!(
+            r#"{{"weight_kg": 70, "pad": "{}"}}"#,
+            "x".repeat(MAX_MESSAGE_BYTES)
+        );
+        client
+            .send(tungstenite::Message::text(oversized))
+            .await
+            .unwrap();
+        let next = client.next().await;
+        assert!(
+            !matches!(next, Some(Ok(tungstenite::Message::Text(_)))),
+            "{next:?}"
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! XML transport for legacy integrations.
+//!
+//! With the `xml` feature, `/api/calculate` answers `application/xml` when
+//! `Accept` asks for it or `?format=xml` is given, and accepts
+//! `Content-Type: application/xml` request bodies. The documented shapes are
+//! the types below; element names match the JSON field names:
+//!
+//! ```xml
+//! <bmi_request><weight_kg>70</weight_kg><height_m>1.75</height_m></bmi_request>
+//!
+//! <bmi_result>
+//!   <bmi>22.857142857142858</bmi><bmi_rounded>22.9</bmi_rounded>
+//!   <category>Normal weight</category><category_code>normal_weight</category_code>
+//!   ...
+//!   <warning>...</warning>
+//! </bmi_result>
+//!
+//! <error><code>validation_failed</code><message>...</message>
+//!   <field><name>weight_kg</name><constraint>...</constraint><value>5000</value></field>
+//! </error>
+//! ```
+//!
+//! As in `proto`, these are separate types converted from the domain ones,
+//! so the XML schema only changes on purpose.
+
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{
+        header::{HeaderMap, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::BmiError, BmiRequest, BmiResponse};
+
+/// Media type of XML request and response bodies.
+pub const XML_CONTENT_TYPE: &str = "application/xml";
+
+/// XML form of [`BmiResponse`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "bmi_result")]
+pub struct BmiResult {
+    /// Calculated BMI value.
+    pub bmi: f64,
+    /// `bmi` rounded to the requested precision.
+    pub bmi_rounded: f64,
+    /// Category label, in the negotiated language.
+    pub category: String,
+    /// Stable code of `category`.
+    pub category_code: String,
+    /// Finer WHO class.
+    pub category_detailed: String,
+    /// `standard` or `asian_pacific`.
+    pub classification: String,
+    /// `metric` or `imperial`.
+    pub units: String,
+    /// Weight used for the calculation, in kilograms.
+    pub weight_kg: f64,
+    /// Normal-weight range at the submitted height.
+    pub healthy_weight_range: HealthyWeightRange,
+    /// BMI Prime, when `extended` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bmi_prime: Option<Indicator>,
+    /// Ponderal index, when `extended` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ponderal_index: Option<Indicator>,
+    /// One `<warning>` per non-fatal issue.
+    #[serde(default, rename = "warning", skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// XML form of [`crate::bmi::HealthyWeightRange`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HealthyWeightRange {
+    /// Lowest normal weight in kilograms.
+    pub min_kg: f64,
+    /// Weight in kilograms where "Overweight" starts.
+    pub max_kg: f64,
+    /// `min_kg` in pounds, for imperial requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_lb: Option<f64>,
+    /// `max_kg` in pounds, for imperial requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_lb: Option<f64>,
+}
+
+/// XML form of [`crate::bmi::Indicator`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Indicator {
+    /// Value of the index.
+    pub value: f64,
+    /// What the value suggests.
+    pub interpretation: String,
+}
+
+/// XML error body for rejected requests.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "error")]
+pub struct Error {
+    /// Machine-readable code, as in the JSON error body.
+    pub code: String,
+    /// Human-readable error description.
+    pub message: String,
+    /// One `<field>` per value out of bounds, for `validation_failed`.
+    #[serde(default, rename = "field", skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldViolation>,
+}
+
+/// XML form of [`crate::validation::FieldViolation`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldViolation {
+    /// Request field as sent.
+    pub name: String,
+    /// The rule it broke.
+    pub constraint: String,
+    /// The submitted value.
+    pub value: f64,
+    /// Suspected unit mix-up, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl From<BmiResponse> for BmiResult {
+    fn from(response: BmiResponse) -> Self {
+        let range = response.healthy_weight_range;
+        let indicator = |indicator: crate::bmi::Indicator| Indicator {
+            value: indicator.value,
+            interpretation: indicator.interpretation.to_string(),
+        };
+        Self {
+            bmi: response.bmi,
+            bmi_rounded: response.bmi_rounded,
+            category: response.category,
+            category_code: response.category_code,
+            category_detailed: response.category_detailed,
+            classification: response.classification.as_str().to_string(),
+            units: response.units.as_str().to_string(),
+            weight_kg: response.weight_kg,
+            healthy_weight_range: HealthyWeightRange {
+                min_kg: range.min_kg,
+                max_kg: range.max_kg,
+                min_lb: range.min_lb,
+                max_lb: range.max_lb,
+            },
+            bmi_prime: response.bmi_prime.map(indicator),
+            ponderal_index: response.ponderal_index.map(indicator),
+            warnings: response.warnings,
+        }
+    }
+}
+
+impl From<BmiError> for Error {
+    fn from(error: BmiError) -> Self {
+        Self {
+            code: error.code().to_string(),
+            message: error.message().to_string(),
+            fields: match error {
+                BmiError::Validation(_, violations) => violations
+                    .into_iter()
+                    .map(|violation| FieldViolation {
+                        name: violation.field.to_string(),
+                        constraint: violation.constraint,
+                        value: violation.value,
+                        hint: violation.hint,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+/// Returns true if the request body is declared as XML.
+pub fn is_xml(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(XML_CONTENT_TYPE))
+}
+
+/// Reads and decodes an XML request body (see [`decode_request`]).
+///
+/// # Errors
+///
+/// Returns [`BmiError::PayloadTooLarge`] past the body limit, otherwise as
+/// [`decode_request`].
+pub async fn read_request(request: Request) -> Result<BmiRequest, BmiError> {
+    let body = Bytes::from_request(request, &())
+        .await
+        .map_err(|rejection| {
+            if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                BmiError::PayloadTooLarge(rejection.body_text())
+            } else {
+                BmiError::MalformedPayload(rejection.body_text())
+            }
+        })?;
+    decode_request(&body)
+}
+
+/// Decodes a `<bmi_request>` body; elements are the JSON field names.
+///
+/// # Errors
+///
+/// Returns [`BmiError::MalformedPayload`] if the body is not UTF-8 or not a
+/// valid request document.
+pub fn decode_request(body: &Bytes) -> Result<BmiRequest, BmiError> {
+    let text = std::str::from_utf8(body)
+        .map_err(|_| BmiError::MalformedPayload("XML body is not UTF-8".to_string()))?;
+    quick_xml::de::from_str(text)
+        .map_err(|err| BmiError::MalformedPayload(format!("Malformed XML payload: {err}")))
+}
+
+/// Serializes `body` as an XML response.
+pub fn encoded(status: StatusCode, body: &impl Serialize) -> Response {
+    match quick_xml::se::to_string(body) {
+        Ok(xml) => (status, [(CONTENT_TYPE, XML_CONTENT_TYPE)], xml).into_response(),
+        Err(err) => BmiError::Internal(format!("XML serialization failed: {err}")).into_response(),
+    }
+}
+
+/// Renders `error` as an XML `<error>` with its status.
+pub fn error_response(error: BmiError) -> Response {
+    encoded(error.status(), &Error::from(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{process_bmi_request, units::UnitSystem, validation};
+    use axum::{body::Body, http::header::ACCEPT};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    fn request() -> BmiRequest {
+        BmiRequest {
+            weight_kg: Some(70.0),
+            height_m: Some(1.75),
+            extended: true,
+            ..BmiRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_result_round_trips() {
+        let mut response = process_bmi_request(&request()).unwrap();
+        response.warnings = vec!["first".to_string(), "second & more".to_string()];
+        let result = BmiResult::from(response);
+
+        let xml = quick_xml::se::to_string(&result).unwrap();
+        assert!(
+            xml.starts_with("<bmi_result><bmi>22.857142857142858</bmi>"),
+            "{xml}"
+        );
+        assert!(xml.contains("<category>Normal weight</category>"), "{xml}");
+        assert!(
+            xml.contains("<warning>second &amp; more</warning>"),
+            "{xml}"
+        );
+        assert_eq!(quick_xml::de::from_str::<BmiResult>(&xml).unwrap(), result);
+
+        let imperial = process_bmi_request(&BmiRequest {
+            units: UnitSystem::Imperial,
+            weight_lb: Some(154.0),
+            height_in: Some(69.0),
+            ..BmiRequest::default()
+        })
+        .unwrap();
+        let result = BmiResult::from(imperial);
+        let xml = quick_xml::se::to_string(&result).unwrap();
+        assert!(!xml.contains("<bmi_prime>"), "{xml}");
+        assert_eq!(quick_xml::de::from_str::<BmiResult>(&xml).unwrap(), result);
+    }
+
+    #[test]
+    fn test_error_round_trips() {
+        let error = crate::process_bmi_request_with(
+            &BmiRequest {
+                weight_kg: Some(5000.0),
+                height_m: Some(0.01),
+                ..BmiRequest::default()
+            },
+            &validation::Bounds::default(),
+        )
+        .unwrap_err();
+        let body = Error::from(error);
+
+        let xml = quick_xml::se::to_string(&body).unwrap();
+        assert!(
+            xml.starts_with("<error><code>validation_failed</code>"),
+            "{xml}"
+        );
+        assert!(xml.contains("<field><name>weight_kg</name>"), "{xml}");
+        let decoded: Error = quick_xml::de::from_str(&xml).unwrap();
+        assert_eq!(decoded, body);
+        assert_eq!(decoded.fields.len(), 2);
+    }
+
+    #[test]
+    fn test_request_decodes_from_xml() {
+        let body = Bytes::from(
+            "<bmi_request><units>imperial</units><weight_lb>154</weight_lb>\
+             <height_in>69</height_in><precision>2</precision></bmi_request>",
+        );
+        let request = decode_request(&body).unwrap();
+        assert_eq!(request.units, UnitSystem::Imperial);
+        assert_eq!(request.weight_lb, Some(154.0));
+        assert_eq!(request.precision, Some(2));
+
+        let error = decode_request(&Bytes::from("<bmi_request><weight_kg>heavy")).unwrap_err();
+        assert_eq!(error.code(), "malformed_payload");
+    }
+
+    async fn send(request: Request) -> (StatusCode, String, String) {
+        let response = crate::build_router(crate::AppState::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let content_type = response.headers()[CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (
+            status,
+            content_type,
+            String::from_utf8(bytes.to_vec()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_calculate_speaks_xml() {
+        let (status, content_type, body) = send(
+            Request::post("/api/calculate")
+                .header(CONTENT_TYPE, XML_CONTENT_TYPE)
+                .header(ACCEPT, XML_CONTENT_TYPE)
+                .body(Body::from(
+                    "<bmi_request><weight_kg>70</weight_kg><height_m>1.75</height_m></bmi_request>",
+                ))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, XML_CONTENT_TYPE);
+        let result: BmiResult = quick_xml::de::from_str(&body).unwrap();
+        assert_eq!(result.category, "Normal weight");
+
+        let (status, content_type, body) = send(
+            Request::get("/api/calculate?weight_kg=5000&height_m=1.75&format=xml")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(content_type, XML_CONTENT_TYPE);
+        let error: Error = quick_xml::de::from_str(&body).unwrap();
+        assert_eq!(error.code, "validation_failed");
+
+        let (status, content_type, _) = send(
+            Request::post("/api/calculate")
+                .header(CONTENT_TYPE, XML_CONTENT_TYPE)
+                .body(Body::from("<bmi_request><weight_kg>70</weight_kg>"))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(content_type, "application/json");
+    }
+}
@@ -0,0 +1,103 @@
+let formToken = document.getElementById('formToken').value;
+
+// Tokens are single-use: fetch a new one once a result has been shown.
+async function refreshFormToken() {
+    try {
+        const response = await fetch('/api/form-token');
+        if (response.ok) {
+            formToken = (await response.json()).form_token;
+        }
+    } catch (_) {
+        formToken = null;
+    }
+}
+
+// Show the selected classification's legend and refresh the hint.
+const classification = document.getElementById('classification');
+classification.addEventListener('change', () => {
+    for (const legend of document.querySelectorAll('[data-classification]')) {
+        legend.hidden = legend.dataset.classification !== classification.value;
+    }
+    document.getElementById('height').dispatchEvent(new Event('input'));
+});
+
+// Show the normal weight range for the typed height, debounced.
+let hintTimer = null;
+document.getElementById('height').addEventListener('input', (e) => {
+    clearTimeout(hintTimer);
+    const hintDiv = document.getElementById('weightHint');
+    const height = parseFloat(e.target.value);
+    if (!(height > 0)) {
+        hintDiv.textContent = '';
+        return;
+    }
+    hintTimer = setTimeout(async () => {
+        try {
+            const response = await fetch(`/api/hints?height_m=${height}&unit=kg&classification=${classification.value}`);
+            hintDiv.textContent = response.ok ? (await response.json()).hint : '';
+        } catch (_) {
+            hintDiv.textContent = '';
+        }
+    }, 300);
+});
+
+document.getElementById('bmiForm').addEventListener('submit', async (e) => {
+    e.preventDefault();
+    // Replaced by the result below; only present after a no-JS submission
+    document.getElementById('formResult')?.remove();
+
+    const weight = parseFloat(document.getElementById('weight').value);
+    const height = parseFloat(document.getElementById('height').value);
+
+    const errorDiv = document.getElementById('error');
+    const resultDiv = document.getElementById('result');
+
+    errorDiv.classList.remove('show');
+    resultDiv.classList.remove('show');
+
+    try {
+        const response = await fetch('/api/calculate', {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({
+                weight_kg: weight,
+                height_m: height,
+                form_token: formToken,
+                extended: true,
+                classification: classification.value
+            })
+        });
+
+        if (!response.ok) {
+            const body = await response.json().catch(() => null);
+            throw new Error(body?.error?.message || `Request failed (${response.status})`);
+        }
+
+        const data = await response.json();
+
+        document.getElementById('bmiValue').textContent = data.bmi_rounded.toFixed(1);
+        document.getElementById('bmiCategory').textContent = data.category_detailed;
+        const range = data.healthy_weight_range;
+        document.getElementById('bmiRange').textContent =
+            `Healthy weight for your height: ${range.min_kg.toFixed(1)}–${range.max_kg.toFixed(1)} kg`;
+        const secondary = document.getElementById('bmiSecondary');
+        secondary.replaceChildren();
+        for (const [label, index, unit] of [
+            ['BMI Prime', data.bmi_prime, ''],
+            ['Ponderal index', data.ponderal_index, ' kg/m³'],
+        ]) {
+            const item = document.createElement('div');
+            item.textContent =
+                `${label}: ${index.value.toFixed(2)}${unit} (${index.interpretation})`;
+            secondary.appendChild(item);
+        }
+        resultDiv.classList.add('show');
+        refreshFormToken();
+
+    } catch (error) {
+        errorDiv.textContent = error.message || 'An error occurred';
+        errorDiv.classList.add('show');
+    }
+});
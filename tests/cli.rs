@@ -0,0 +1,96 @@
+//! The subcommands, run as a user would.
+
+use assert_cmd::Command;
+
+fn bmi_calculator() -> Command {
+    let mut command = Command::cargo_bin("bmi_calculator").unwrap();
+    // Keep a developer's bmi.toml or BMI_* overrides out of the results
+    command.env("BMI_CONFIG", "bmi.example.toml");
+    command
+}
+
+#[test]
+fn test_calc_prints_bmi_and_category() {
+    bmi_calculator()
+        .args(["calc", "--weight-kg", "70", "--height-m", "1.75"])
+        .assert()
+        .success()
+        .stdout("BMI: 22.9 (Normal weight)\n");
+
+    bmi_calculator()
+        .args(["calc", "--units", "imperial", "--weight-lb", "154"])
+        .args(["--height-ft", "5", "--height-in", "9", "--precision", "2"])
+        .assert()
+        .success()
+        .stdout("BMI: 22.74 (Normal weight)\n");
+}
+
+#[test]
+fn test_calc_json_matches_the_api_shape() {
+    let output = bmi_calculator()
+        .args(["calc", "--weight-kg", "70", "--height-m", "1.75", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let body: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(body["category_code"], "normal_weight");
+    assert_eq!(body["bmi_rounded"], 22.9);
+}
+
+#[test]
+fn test_calc_fails_on_invalid_input() {
+    let output = bmi_calculator()
+        .args(["calc", "--weight-kg", "5000", "--height-m", "1.75"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("weight_kg must be between 1 and 700 kg"),
+        "{stderr}"
+    );
+
+    bmi_calculator()
+        .args(["calc", "--height-m", "1.75"])
+        .assert()
+        .failure();
+    bmi_calculator()
+        .args(["calc", "--weight-kg", "heavy"])
+        .assert()
+        .failure()
+        .code(2);
+}
+
+#[test]
+fn test_serve_rejects_unknown_options() {
+    bmi_calculator()
+        .args(["serve", "--port", "web"])
+        .assert()
+        .failure()
+        .code(2);
+    bmi_calculator().arg("--help").assert().success();
+}
+
+#[test]
+fn test_tools_are_listed_and_validated() {
+    let output = bmi_calculator().arg("--help").output().unwrap();
+    let help = String::from_utf8(output.stdout).unwrap();
+    for command in ["serve", "calc", "token", "export", "import"] {
+        assert!(help.contains(command), "{command} missing from\n{help}");
+    }
+
+    let output = bmi_calculator()
+        .args(["token", "--weight-kg=70", "--height-m=1.75"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!String::from_utf8(output.stdout).unwrap().trim().is_empty());
+
+    bmi_calculator().arg("export").assert().failure().code(2);
+    bmi_calculator()
+        .args(["import", "--input"])
+        .assert()
+        .failure()
+        .code(2);
+}